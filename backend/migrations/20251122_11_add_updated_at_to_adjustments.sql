@@ -0,0 +1,2 @@
+-- Adjustments were created without updated_at, which the repository selects
+ALTER TABLE adjustments ADD COLUMN IF NOT EXISTS updated_at TIMESTAMP DEFAULT NOW();
@@ -0,0 +1,5 @@
+-- Quantity held for picked/packed deliveries that have not shipped yet
+ALTER TABLE product_stock ADD COLUMN IF NOT EXISTS reserved_quantity INTEGER NOT NULL DEFAULT 0;
+
+-- Deliveries start as drafts and move through explicit transitions
+ALTER TABLE deliveries ALTER COLUMN status SET DEFAULT 'draft';
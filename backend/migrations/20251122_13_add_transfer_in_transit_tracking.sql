@@ -0,0 +1,12 @@
+-- Transfers ship from the source and are received at the destination later.
+-- Whatever has been shipped but not yet received or written off is in transit.
+ALTER TABLE transfer_items ADD COLUMN IF NOT EXISTS shipped_quantity INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE transfer_items ADD COLUMN IF NOT EXISTS received_quantity INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE transfer_items ADD COLUMN IF NOT EXISTS discrepancy_quantity INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE transfer_items ADD COLUMN IF NOT EXISTS discrepancy_notes TEXT;
+ALTER TABLE transfers ADD COLUMN IF NOT EXISTS shipped_at TIMESTAMP;
+
+-- Transfers executed in one step already landed everything at the destination
+UPDATE transfer_items SET shipped_quantity = quantity, received_quantity = quantity
+WHERE transfer_id IN (SELECT id FROM transfers WHERE status = 'executed');
+UPDATE transfers SET status = 'received' WHERE status = 'executed';
@@ -0,0 +1,2 @@
+-- Physical counts record what was counted; quantity_change is derived from it
+ALTER TABLE adjustments ADD COLUMN IF NOT EXISTS counted_quantity INTEGER;
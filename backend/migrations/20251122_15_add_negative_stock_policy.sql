@@ -0,0 +1,7 @@
+-- What to do when an operation would take more stock than is available:
+-- 'forbid' rejects it, 'warn' allows it but logs a warning, 'allow' allows it.
+-- A product's policy overrides its warehouse's; with neither set, 'forbid' applies.
+ALTER TABLE warehouses ADD COLUMN IF NOT EXISTS negative_stock_policy VARCHAR(10)
+    CHECK (negative_stock_policy IN ('forbid', 'warn', 'allow'));
+ALTER TABLE products ADD COLUMN IF NOT EXISTS negative_stock_policy VARCHAR(10)
+    CHECK (negative_stock_policy IN ('forbid', 'warn', 'allow'));
@@ -0,0 +1,6 @@
+-- Posted documents are reversed, not deleted. A reversal writes compensating
+-- ledger rows that point back at the entries they undo.
+ALTER TABLE stock_ledger ADD COLUMN IF NOT EXISTS reversal_of INTEGER REFERENCES stock_ledger(id);
+
+-- Adjustments are posted on creation and can only be reversed afterwards
+ALTER TABLE adjustments ADD COLUMN IF NOT EXISTS status VARCHAR(20) NOT NULL DEFAULT 'posted';
@@ -0,0 +1,7 @@
+-- The ledger is read newest-first with a keyset cursor on id; these indexes
+-- keep the filtered variants of that scan from touching the whole table.
+CREATE INDEX IF NOT EXISTS idx_stock_ledger_product ON stock_ledger (product_id, id DESC);
+CREATE INDEX IF NOT EXISTS idx_stock_ledger_warehouse ON stock_ledger (warehouse_id, id DESC);
+CREATE INDEX IF NOT EXISTS idx_stock_ledger_user ON stock_ledger (user_id, id DESC);
+CREATE INDEX IF NOT EXISTS idx_stock_ledger_reference ON stock_ledger (reference_type, reference_id);
+CREATE INDEX IF NOT EXISTS idx_stock_ledger_created_at ON stock_ledger (created_at);
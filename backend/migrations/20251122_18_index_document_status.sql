@@ -0,0 +1,4 @@
+-- The dashboard counts open documents by status on every poll
+CREATE INDEX IF NOT EXISTS idx_receipts_status ON receipts (status, warehouse_id);
+CREATE INDEX IF NOT EXISTS idx_deliveries_status ON deliveries (status, warehouse_id);
+CREATE INDEX IF NOT EXISTS idx_transfers_status ON transfers (status);
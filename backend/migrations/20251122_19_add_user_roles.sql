@@ -0,0 +1,16 @@
+-- Users get one of three roles; permissions per role are defined in code.
+-- 'admin' manages users and everything else, 'manager' runs the catalog and
+-- stock, 'operator' works documents on the warehouse floor.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS role VARCHAR(20) NOT NULL DEFAULT 'operator'
+    CHECK (role IN ('admin', 'manager', 'operator'));
+
+DO $$
+BEGIN
+    IF EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name = 'users' AND column_name = 'is_admin') THEN
+        UPDATE users SET role = 'admin' WHERE is_admin;
+        ALTER TABLE users DROP COLUMN is_admin;
+    END IF;
+END $$;
+
+-- No existing account is promoted: an install without an admin gets one from
+-- ADMIN_EMAIL and ADMIN_PASSWORD at startup.
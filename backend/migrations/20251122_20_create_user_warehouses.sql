@@ -0,0 +1,10 @@
+-- Warehouses each user may see and move stock in. Admins are not limited;
+-- everyone else only sees documents and stock in their assigned warehouses.
+CREATE TABLE IF NOT EXISTS user_warehouses (
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    warehouse_id INTEGER NOT NULL REFERENCES warehouses(id) ON DELETE CASCADE,
+    created_at TIMESTAMP DEFAULT NOW(),
+    PRIMARY KEY (user_id, warehouse_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_warehouses_warehouse ON user_warehouses (warehouse_id);
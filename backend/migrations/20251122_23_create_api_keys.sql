@@ -0,0 +1,17 @@
+-- Keys for scanners, printers and integrations. A key acts on behalf of the
+-- admin who created it, limited to its scopes (permission names) and, when
+-- set, to one warehouse. Only the SHA-256 hash of the key is stored.
+CREATE TABLE IF NOT EXISTS api_keys (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    -- First characters of the key, so admins can tell keys apart
+    key_prefix VARCHAR(16) NOT NULL,
+    key_hash VARCHAR(64) NOT NULL UNIQUE,
+    scopes TEXT[] NOT NULL DEFAULT '{}',
+    warehouse_id INTEGER REFERENCES warehouses(id) ON DELETE CASCADE,
+    created_by INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    expires_at TIMESTAMP,
+    last_used_at TIMESTAMP,
+    revoked_at TIMESTAMP,
+    created_at TIMESTAMP DEFAULT NOW()
+);
@@ -0,0 +1,103 @@
+-- Who changed what. Row changes are captured by triggers on the audited
+-- tables, so every write is covered however it is made; auth events are
+-- inserted by the application. The actor, request id and client IP come from
+-- the app.* settings the server sets at the start of each write transaction
+-- (set_config(..., true), so they end with it). Pooled connections carry no
+-- audit state between requests; a write outside such a transaction is logged
+-- without an actor.
+CREATE TABLE IF NOT EXISTS audit_log (
+    id BIGSERIAL PRIMARY KEY,
+    occurred_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    -- No foreign keys: entries must outlive the users and keys they mention
+    actor_id INTEGER,
+    api_key_id INTEGER,
+    action VARCHAR(40) NOT NULL,
+    entity_type VARCHAR(40) NOT NULL,
+    entity_id INTEGER,
+    before JSONB,
+    after JSONB,
+    request_id VARCHAR(64),
+    client_ip VARCHAR(64)
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_log_entity ON audit_log (entity_type, entity_id, id);
+CREATE INDEX IF NOT EXISTS idx_audit_log_actor ON audit_log (actor_id, id);
+CREATE INDEX IF NOT EXISTS idx_audit_log_occurred_at ON audit_log (occurred_at);
+
+-- Append-only: entries can be added but never changed or removed
+CREATE OR REPLACE FUNCTION audit_log_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS audit_log_no_update ON audit_log;
+CREATE TRIGGER audit_log_no_update BEFORE UPDATE OR DELETE ON audit_log
+    FOR EACH ROW EXECUTE FUNCTION audit_log_append_only();
+DROP TRIGGER IF EXISTS audit_log_no_truncate ON audit_log;
+CREATE TRIGGER audit_log_no_truncate BEFORE TRUNCATE ON audit_log
+    FOR EACH STATEMENT EXECUTE FUNCTION audit_log_append_only();
+
+-- Trigger arguments: the column holding the entity id, then any columns to
+-- leave out of the snapshots (secrets, bookkeeping). An UPDATE that only
+-- touches left-out columns is not recorded.
+CREATE OR REPLACE FUNCTION audit_row_change() RETURNS trigger AS $$
+DECLARE
+    excluded TEXT[] := TG_ARGV[1:TG_NARGS - 1];
+    old_row JSONB;
+    new_row JSONB;
+BEGIN
+    IF TG_OP <> 'INSERT' THEN
+        old_row := to_jsonb(OLD) - excluded;
+    END IF;
+    IF TG_OP <> 'DELETE' THEN
+        new_row := to_jsonb(NEW) - excluded;
+    END IF;
+    IF TG_OP = 'UPDATE' AND old_row = new_row THEN
+        RETURN NULL;
+    END IF;
+
+    INSERT INTO audit_log (actor_id, api_key_id, action, entity_type, entity_id, before, after, request_id, client_ip)
+    VALUES (
+        NULLIF(current_setting('app.actor_id', true), '')::INTEGER,
+        NULLIF(current_setting('app.api_key_id', true), '')::INTEGER,
+        CASE TG_OP WHEN 'INSERT' THEN 'create' WHEN 'UPDATE' THEN 'update' ELSE 'delete' END,
+        TG_TABLE_NAME,
+        (COALESCE(new_row, old_row) ->> TG_ARGV[0])::INTEGER,
+        old_row,
+        new_row,
+        NULLIF(current_setting('app.request_id', true), ''),
+        NULLIF(current_setting('app.client_ip', true), '')
+    );
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DO $$
+DECLARE
+    audited TEXT[][] := ARRAY[
+        ['users', '''id'', ''password_hash'''],
+        ['user_warehouses', '''user_id'''],
+        ['warehouses', '''id'''],
+        ['categories', '''id'''],
+        ['products', '''id'''],
+        ['receipts', '''id'''],
+        ['receipt_items', '''id'''],
+        ['deliveries', '''id'''],
+        ['delivery_items', '''id'''],
+        ['transfers', '''id'''],
+        ['transfer_items', '''id'''],
+        ['adjustments', '''id'''],
+        ['api_keys', '''id'', ''key_hash'', ''last_used_at''']
+    ];
+    i INTEGER;
+BEGIN
+    FOR i IN 1 .. array_length(audited, 1) LOOP
+        EXECUTE format('DROP TRIGGER IF EXISTS audit_changes ON %I', audited[i][1]);
+        EXECUTE format(
+            'CREATE TRIGGER audit_changes AFTER INSERT OR UPDATE OR DELETE ON %I FOR EACH ROW EXECUTE FUNCTION audit_row_change(%s)',
+            audited[i][1], audited[i][2]
+        );
+    END LOOP;
+END;
+$$;
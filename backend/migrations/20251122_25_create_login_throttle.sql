@@ -0,0 +1,11 @@
+-- Failed login attempts per account (lowercased email) and per client IP.
+-- While locked_until is in the future, logins for that account or from that
+-- IP are refused without checking the password.
+CREATE TABLE IF NOT EXISTS login_throttle (
+    kind VARCHAR(10) NOT NULL CHECK (kind IN ('account', 'ip')),
+    subject VARCHAR(255) NOT NULL,
+    failures INTEGER NOT NULL DEFAULT 0,
+    last_failure_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    locked_until TIMESTAMP,
+    PRIMARY KEY (kind, subject)
+);
@@ -0,0 +1,3 @@
+-- Logins look accounts up by case-insensitive email, matching how login
+-- throttling keys them.
+CREATE INDEX IF NOT EXISTS idx_users_email_lower ON users (LOWER(email));
@@ -3,11 +3,16 @@ use axum::{routing::get, Router};
 use tower_http::cors::CorsLayer;
 use crate::config::database;
 use crate::router;
+use crate::services::{mail_service, user_service};
+use crate::utils::mailer;
 
 pub async fn create_app() -> Router {
     let pool = database::get_pool().await;
-    let app = router::create_router(pool)
+    user_service::bootstrap_admin(&pool)
+        .await
+        .expect("Failed to create the admin from ADMIN_EMAIL");
+    mail_service::spawn_outbox_worker(pool.clone(), mailer::from_env());
+    router::create_router(pool)
         .route("/api/v1/health", get(|| async { "ok" }))
-        .layer(CorsLayer::permissive());
-    app
+        .layer(CorsLayer::permissive())
 }
\ No newline at end of file
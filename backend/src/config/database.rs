@@ -1,12 +1,25 @@
 // src/config/database.rs
-use sqlx::PgPool;
+use sqlx::{postgres::PgPoolOptions, PgPool, Postgres, Transaction};
+
+use crate::middleware::audit;
+
 pub type DbPool = PgPool;
 
 pub async fn get_pool() -> DbPool {
     let database_url = std::env::var("DATABASE_URL")
         .expect("DATABASE_URL must be set in .env");
 
-    PgPool::connect(&database_url)
+    PgPoolOptions::new()
+        .connect(&database_url)
         .await
         .expect("Failed to connect to PostgreSQL")
-}
\ No newline at end of file
+}
+
+/// Starts a transaction carrying the current request's audit context, which
+/// the audit triggers attribute its changes to. Every write to an audited
+/// table goes through one; plain reads on the pool skip the extra round trip.
+pub async fn begin(pool: &DbPool) -> Result<Transaction<'static, Postgres>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    audit::apply(&mut tx).await?;
+    Ok(tx)
+}
@@ -1,31 +1,68 @@
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use crate::models::ids::{AdjustmentId, ProductId, UserId, WarehouseId};
+use validator::{Validate, ValidationError};
+use crate::utils::validation::{field_error, non_zero, not_blank};
+use crate::dto::list_dto::sort_keys;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct AdjustmentDto {
-    pub id: Option<i32>,
-    pub product_id: Option<i32>,
-    pub warehouse_id: Option<i32>,
-    pub user_id: Option<i32>,
+    pub id: Option<AdjustmentId>,
+    pub product_id: Option<ProductId>,
+    pub warehouse_id: Option<WarehouseId>,
+    pub user_id: Option<UserId>,
     pub reason: Option<String>,
     pub quantity_change: Option<i32>,
+    pub counted_quantity: Option<i32>,
     pub notes: Option<String>,
+    pub status: Option<String>,
     pub created_at: Option<chrono::NaiveDateTime>,
     pub updated_at: Option<chrono::NaiveDateTime>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Send either `quantity_change` to adjust by a delta, or `counted_quantity`
+/// to record a physical count and let the server compute the delta.
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+#[validate(schema(function = "one_quantity"))]
 pub struct CreateAdjustmentRequest {
-    pub product_id: i32,
-    pub warehouse_id: i32,
-    pub user_id: i32,
+    pub product_id: ProductId,
+    pub warehouse_id: WarehouseId,
+    #[validate(custom(function = "not_blank"), length(max = 50))]
     pub reason: String,
-    pub quantity_change: i32,
+    #[validate(custom(function = "non_zero"))]
+    pub quantity_change: Option<i32>,
+    #[validate(range(min = 0))]
+    pub counted_quantity: Option<i32>,
     pub notes: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+fn one_quantity(req: &CreateAdjustmentRequest) -> Result<(), ValidationError> {
+    match (req.quantity_change, req.counted_quantity) {
+        (Some(_), None) | (None, Some(_)) => Ok(()),
+        _ => Err(field_error("quantity_change", "one_quantity", "must be sent, or counted_quantity instead, but not both")),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
 pub struct UpdateAdjustmentRequest {
+    #[validate(custom(function = "not_blank"), length(max = 50))]
     pub reason: Option<String>,
-    pub quantity_change: Option<i32>,
     pub notes: Option<String>,
 }
+
+sort_keys!(AdjustmentSort { Id => "id", Reason => "reason", QuantityChange => "quantity_change", Status => "status", CreatedAt => "created_at" });
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AdjustmentFilter {
+    /// Defaults to `id`
+    pub sort: Option<AdjustmentSort>,
+    pub status: Option<String>,
+    pub reason: Option<String>,
+    pub product_id: Option<ProductId>,
+    pub warehouse_id: Option<WarehouseId>,
+    /// Created on or after this day
+    pub date_from: Option<chrono::NaiveDate>,
+    /// Created on or before this day
+    pub date_to: Option<chrono::NaiveDate>,
+}
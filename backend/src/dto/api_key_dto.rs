@@ -0,0 +1,45 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::models::ids::{ApiKeyId, UserId, WarehouseId};
+use crate::models::role::Permission;
+use validator::Validate;
+use crate::utils::validation::not_blank;
+
+/// An API key as listed to admins; the key itself is only shown once, on creation.
+#[derive(Debug, Serialize, Deserialize, ToSchema, sqlx::FromRow)]
+pub struct ApiKeyDto {
+    pub id: ApiKeyId,
+    pub name: String,
+    /// First characters of the key
+    pub key_prefix: String,
+    pub scopes: Vec<String>,
+    pub warehouse_id: Option<WarehouseId>,
+    pub created_by: UserId,
+    pub expires_at: Option<NaiveDateTime>,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+pub struct CreateApiKeyRequest {
+    #[validate(custom(function = "not_blank"), length(max = 255))]
+    pub name: String,
+    /// Permissions the key may use; the creator's role must grant them
+    #[serde(default)]
+    pub scopes: Vec<Permission>,
+    /// Limits the key to one warehouse
+    pub warehouse_id: Option<WarehouseId>,
+    /// Never expires when omitted
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreatedApiKeyDto {
+    #[serde(flatten)]
+    pub api_key: ApiKeyDto,
+    /// Send as `X-API-Key` or `Authorization: Bearer`. Not retrievable later.
+    pub key: String,
+}
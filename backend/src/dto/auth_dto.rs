@@ -1,14 +1,65 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+use crate::utils::validation::not_blank;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
 pub struct RegisterRequest {
+    #[validate(custom(function = "not_blank"), length(max = 100))]
     pub name: String,
+    #[validate(email, length(max = 255))]
     pub email: String,
+    #[validate(length(min = 8))]
     pub password: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
 pub struct LoginRequest {
+    #[validate(custom(function = "not_blank"))]
     pub email: String,
+    #[validate(length(min = 1, message = "must not be empty"))]
     pub password: String,
 }
+
+/// Returned by login and refresh. The refresh token is single-use: every
+/// refresh returns a new one and the old one stops working.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub token_type: String,
+    /// Seconds until the access token expires
+    pub expires_in: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+pub struct RefreshRequest {
+    #[validate(custom(function = "not_blank"))]
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RevokedSessionsDto {
+    pub revoked: u64,
+}
+
+/// Asks for a verification or password reset mail.
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+pub struct EmailRequest {
+    #[validate(email)]
+    pub email: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+pub struct VerifyEmailRequest {
+    #[validate(custom(function = "not_blank"))]
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+pub struct ResetPasswordRequest {
+    #[validate(custom(function = "not_blank"))]
+    pub token: String,
+    #[validate(length(min = 8))]
+    pub new_password: String,
+}
@@ -1,19 +1,37 @@
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use crate::models::ids::CategoryId;
+use validator::Validate;
+use crate::utils::validation::not_blank;
+use crate::dto::list_dto::sort_keys;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct CategoryDto {
-    pub id: Option<i32>,
+    pub id: Option<CategoryId>,
     pub name: String,
     pub created_at: Option<chrono::NaiveDateTime>,
     pub updated_at: Option<chrono::NaiveDateTime>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
 pub struct CreateCategoryRequest {
+    #[validate(custom(function = "not_blank"), length(max = 100))]
     pub name: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
 pub struct UpdateCategoryRequest {
+    #[validate(custom(function = "not_blank"), length(max = 100))]
     pub name: Option<String>,
 }
+
+sort_keys!(CategorySort { Id => "id", Name => "name", CreatedAt => "created_at" });
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CategoryFilter {
+    /// Defaults to `id`
+    pub sort: Option<CategorySort>,
+    /// Matches anywhere in the name, case-insensitively
+    pub q: Option<String>,
+}
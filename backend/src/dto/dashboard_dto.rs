@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use crate::models::ids::WarehouseId;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DashboardFilter {
+    pub warehouse_id: Option<WarehouseId>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DashboardSummaryDto {
+    pub warehouse_id: Option<WarehouseId>,
+    /// Products with a positive on-hand quantity.
+    pub products_in_stock: i64,
+    /// In stock, but at or below `reorder_threshold`.
+    pub low_stock: i64,
+    /// Nothing on hand, including products never stocked.
+    pub out_of_stock: i64,
+    /// Open documents keyed by status; finished and cancelled ones are left out.
+    pub pending_receipts: BTreeMap<String, i64>,
+    pub pending_deliveries: BTreeMap<String, i64>,
+    pub pending_transfers: BTreeMap<String, i64>,
+    pub today: MovementVolumeDto,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MovementVolumeDto {
+    /// Ledger entries written today.
+    pub moves: i64,
+    pub units_in: i64,
+    pub units_out: i64,
+}
+
+pub struct StockCountsDto {
+    pub products_in_stock: Option<i64>,
+    pub low_stock: Option<i64>,
+    pub out_of_stock: Option<i64>,
+}
+
+pub struct StatusCountDto {
+    pub status: Option<String>,
+    pub count: Option<i64>,
+}
@@ -1,28 +1,78 @@
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use crate::models::ids::{DeliveryId, DeliveryItemId, ProductId, UserId, WarehouseId};
+use validator::Validate;
+use crate::utils::validation::not_blank;
+use crate::dto::list_dto::sort_keys;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct DeliveryDto {
-    pub id: Option<i32>,
+    pub id: Option<DeliveryId>,
     pub customer_name: Option<String>,
-    pub warehouse_id: Option<i32>,
-    pub user_id: Option<i32>,
+    pub warehouse_id: Option<WarehouseId>,
+    pub user_id: Option<UserId>,
     pub status: Option<String>,
     pub created_at: Option<chrono::NaiveDateTime>,
     pub updated_at: Option<chrono::NaiveDateTime>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
 pub struct CreateDeliveryRequest {
+    #[validate(custom(function = "not_blank"), length(max = 255))]
     pub customer_name: String,
-    pub warehouse_id: i32,
-    pub user_id: i32,
-    pub status: Option<String>,
+    pub warehouse_id: WarehouseId,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
 pub struct UpdateDeliveryRequest {
+    #[validate(custom(function = "not_blank"), length(max = 255))]
     pub customer_name: Option<String>,
-    pub warehouse_id: Option<i32>,
-    pub user_id: Option<i32>,
+    pub warehouse_id: Option<WarehouseId>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct DeliveryItemDto {
+    pub id: Option<DeliveryItemId>,
+    pub delivery_id: Option<DeliveryId>,
+    pub product_id: Option<ProductId>,
+    pub quantity: Option<i32>,
+    pub created_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+pub struct CreateDeliveryItemRequest {
+    pub product_id: ProductId,
+    #[validate(range(min = 1))]
+    pub quantity: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+pub struct UpdateDeliveryItemRequest {
+    pub product_id: Option<ProductId>,
+    #[validate(range(min = 1))]
+    pub quantity: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct DeliveryWithItemsDto {
+    #[serde(flatten)]
+    pub delivery: DeliveryDto,
+    pub items: Vec<DeliveryItemDto>,
+}
+
+sort_keys!(DeliverySort { Id => "id", CustomerName => "customer_name", Status => "status", CreatedAt => "created_at", UpdatedAt => "updated_at" });
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeliveryFilter {
+    /// Defaults to `id`
+    pub sort: Option<DeliverySort>,
     pub status: Option<String>,
+    pub warehouse_id: Option<WarehouseId>,
+    /// Created on or after this day
+    pub date_from: Option<chrono::NaiveDate>,
+    /// Created on or before this day
+    pub date_to: Option<chrono::NaiveDate>,
+    /// Matches anywhere in the customer name, case-insensitively
+    pub q: Option<String>,
 }
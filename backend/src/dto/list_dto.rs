@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+pub const DEFAULT_PAGE_SIZE: i64 = 50;
+pub const MAX_PAGE_SIZE: i64 = 500;
+
+/// Paging and sort direction accepted by every list endpoint, next to the
+/// endpoint's own filters. Which field to sort by is one of those filters,
+/// `sort`, since each endpoint has its own set.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListParams {
+    /// 1-based; defaults to 1
+    pub page: Option<i64>,
+    /// Defaults to 50, at most 500
+    pub limit: Option<i64>,
+    /// `asc` (default) or `desc`
+    pub order: Option<SortOrder>,
+}
+
+impl ListParams {
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+    }
+
+    pub fn page(&self) -> i64 {
+        self.page.unwrap_or(1).max(1)
+    }
+
+    pub fn offset(&self) -> i64 {
+        (self.page() - 1).saturating_mul(self.limit())
+    }
+
+    pub fn descending(&self) -> bool {
+        matches!(self.order, Some(SortOrder::Desc))
+    }
+
+    /// Wraps one page of rows fetched with these params.
+    pub fn page_of<T>(&self, items: Vec<T>, total: i64) -> Page<T> {
+        Page { items, total, page: self.page(), limit: self.limit() }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+/// A field a list can be sorted by.
+pub trait SortKey {
+    fn column(&self) -> &'static str;
+}
+
+/// The column to order by, as the repositories match on it; `id` by default.
+///
+/// Queries can't bind a column name, so each repository's ORDER BY switches
+/// on it with one `CASE` per column type, ascending and descending, and falls
+/// back to `id` to keep pages stable.
+pub fn sort_column<S: SortKey>(sort: Option<S>) -> &'static str {
+    sort.as_ref().map_or("id", SortKey::column)
+}
+
+/// Declares an enum of sortable fields, deserialized from and mapped to the
+/// column names given.
+macro_rules! sort_keys {
+    ($(#[$meta:meta])* $name:ident { $($variant:ident => $column:literal),* $(,)? }) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, serde::Deserialize, utoipa::ToSchema)]
+        pub enum $name {
+            $(#[serde(rename = $column)] $variant,)*
+        }
+
+        impl $crate::dto::list_dto::SortKey for $name {
+            fn column(&self) -> &'static str {
+                match self {
+                    $(Self::$variant => $column,)*
+                }
+            }
+        }
+    };
+}
+pub(crate) use sort_keys;
+
+/// `q` as an ILIKE pattern matching it anywhere, with wildcards in it taken
+/// literally; `None` when blank.
+pub fn search_pattern(q: Option<&str>) -> Option<String> {
+    q.map(str::trim)
+        .filter(|q| !q.is_empty())
+        .map(|q| format!("%{}%", q.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Matching rows across all pages
+    pub total: i64,
+    pub page: i64,
+    pub limit: i64,
+}
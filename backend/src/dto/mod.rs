@@ -7,3 +7,7 @@ pub mod transfer_dto;
 pub mod adjustment_dto;
 pub mod category_dto;
 	pub mod auth_dto;
+pub mod move_history_dto;
+pub mod dashboard_dto;
+pub mod api_key_dto;
+pub mod list_dto;
@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use crate::models::ids::{ProductId, StockLedgerId, UserId, WarehouseId};
+
+/// One stock movement as a person reads it: a ledger row with names resolved
+/// and the document that caused it.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MoveDto {
+    pub id: Option<StockLedgerId>,
+    pub created_at: Option<chrono::NaiveDateTime>,
+    pub movement_type: Option<String>,
+    pub document_type: Option<String>,
+    pub document_id: Option<i32>,
+    pub document_status: Option<String>,
+    /// Supplier for receipts, customer for deliveries, reason for adjustments,
+    /// the other warehouse for transfers.
+    pub counterparty: Option<String>,
+    pub product_id: Option<ProductId>,
+    pub product_sku: Option<String>,
+    pub product_name: Option<String>,
+    pub warehouse_id: Option<WarehouseId>,
+    pub warehouse_name: Option<String>,
+    pub from_warehouse_name: Option<String>,
+    pub to_warehouse_name: Option<String>,
+    pub user_id: Option<UserId>,
+    pub user_name: Option<String>,
+    pub quantity_change: Option<i32>,
+    pub new_balance: Option<i32>,
+    pub notes: Option<String>,
+    pub reversal_of: Option<StockLedgerId>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MoveHistoryFilter {
+    /// receipt, delivery, transfer or adjustment
+    pub document_type: Option<String>,
+    pub product_id: Option<ProductId>,
+    pub warehouse_id: Option<WarehouseId>,
+    pub date_from: Option<chrono::NaiveDate>,
+    /// Inclusive
+    pub date_to: Option<chrono::NaiveDate>,
+    /// Case-insensitive match on SKU, product, warehouse, user, counterparty and notes.
+    pub q: Option<String>,
+    pub cursor: Option<i32>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MoveHistoryPage {
+    pub moves: Vec<MoveDto>,
+    pub next_cursor: Option<i32>,
+}
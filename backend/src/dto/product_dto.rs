@@ -1,30 +1,60 @@
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use crate::models::ids::{CategoryId, ProductId};
+use validator::Validate;
+use crate::utils::validation::{not_blank, stock_policy};
+use crate::dto::list_dto::sort_keys;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct ProductDto {
-    pub id: Option<i32>,
+    pub id: Option<ProductId>,
     pub sku: String,
     pub name: String,
-    pub category_id: Option<i32>,
+    pub category_id: Option<CategoryId>,
     pub unit_of_measure: String,
     pub reorder_threshold: i32,
+    pub negative_stock_policy: Option<String>,
     pub created_at: Option<chrono::NaiveDateTime>,
     pub updated_at: Option<chrono::NaiveDateTime>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
 pub struct CreateProductRequest {
+    #[validate(custom(function = "not_blank"), length(max = 50))]
     pub sku: String,
+    #[validate(custom(function = "not_blank"), length(max = 255))]
     pub name: String,
-    pub category_id: Option<i32>,
+    pub category_id: Option<CategoryId>,
+    #[validate(custom(function = "not_blank"), length(max = 50))]
     pub unit_of_measure: String,
+    #[validate(range(min = 0))]
     pub reorder_threshold: i32,
+    /// forbid / warn / allow; unset falls back to the warehouse policy
+    #[validate(custom(function = "stock_policy"))]
+    pub negative_stock_policy: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
 pub struct UpdateProductRequest {
+    #[validate(custom(function = "not_blank"), length(max = 255))]
     pub name: Option<String>,
-    pub category_id: Option<i32>,
+    pub category_id: Option<CategoryId>,
+    #[validate(custom(function = "not_blank"), length(max = 50))]
     pub unit_of_measure: Option<String>,
+    #[validate(range(min = 0))]
     pub reorder_threshold: Option<i32>,
+    #[validate(custom(function = "stock_policy"))]
+    pub negative_stock_policy: Option<String>,
+}
+
+sort_keys!(ProductSort { Id => "id", Sku => "sku", Name => "name", ReorderThreshold => "reorder_threshold", CreatedAt => "created_at" });
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ProductFilter {
+    /// Defaults to `id`
+    pub sort: Option<ProductSort>,
+    pub category_id: Option<CategoryId>,
+    /// Matches anywhere in the SKU or name, case-insensitively
+    pub q: Option<String>,
 }
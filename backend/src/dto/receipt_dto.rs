@@ -1,28 +1,81 @@
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use crate::models::ids::{ProductId, ReceiptId, ReceiptItemId, UserId, WarehouseId};
+use validator::Validate;
+use crate::utils::validation::not_blank;
+use crate::dto::list_dto::sort_keys;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct ReceiptDto {
-    pub id: Option<i32>,
+    pub id: Option<ReceiptId>,
     pub supplier_name: Option<String>,
-    pub warehouse_id: Option<i32>,
-    pub user_id: Option<i32>,
+    pub warehouse_id: Option<WarehouseId>,
+    pub user_id: Option<UserId>,
     pub status: Option<String>,
     pub created_at: Option<chrono::NaiveDateTime>,
     pub updated_at: Option<chrono::NaiveDateTime>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
 pub struct CreateReceiptRequest {
+    #[validate(custom(function = "not_blank"), length(max = 255))]
     pub supplier_name: String,
-    pub warehouse_id: i32,
-    pub user_id: i32,
-    pub status: Option<String>,
+    pub warehouse_id: WarehouseId,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
 pub struct UpdateReceiptRequest {
+    #[validate(custom(function = "not_blank"), length(max = 255))]
     pub supplier_name: Option<String>,
-    pub warehouse_id: Option<i32>,
-    pub user_id: Option<i32>,
+    pub warehouse_id: Option<WarehouseId>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct ReceiptItemDto {
+    pub id: Option<ReceiptItemId>,
+    pub receipt_id: Option<ReceiptId>,
+    pub product_id: Option<ProductId>,
+    pub quantity: Option<i32>,
+    pub expiry_date: Option<chrono::NaiveDate>,
+    pub created_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+pub struct CreateReceiptItemRequest {
+    pub product_id: ProductId,
+    #[validate(range(min = 1))]
+    pub quantity: i32,
+    pub expiry_date: Option<chrono::NaiveDate>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+pub struct UpdateReceiptItemRequest {
+    pub product_id: Option<ProductId>,
+    #[validate(range(min = 1))]
+    pub quantity: Option<i32>,
+    pub expiry_date: Option<chrono::NaiveDate>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct ReceiptWithItemsDto {
+    #[serde(flatten)]
+    pub receipt: ReceiptDto,
+    pub items: Vec<ReceiptItemDto>,
+}
+
+sort_keys!(ReceiptSort { Id => "id", SupplierName => "supplier_name", Status => "status", CreatedAt => "created_at", UpdatedAt => "updated_at" });
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ReceiptFilter {
+    /// Defaults to `id`
+    pub sort: Option<ReceiptSort>,
     pub status: Option<String>,
+    pub warehouse_id: Option<WarehouseId>,
+    /// Created on or after this day
+    pub date_from: Option<chrono::NaiveDate>,
+    /// Created on or before this day
+    pub date_to: Option<chrono::NaiveDate>,
+    /// Matches anywhere in the supplier name, case-insensitively
+    pub q: Option<String>,
 }
@@ -1,28 +1,140 @@
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use crate::models::ids::{ProductId, TransferId, TransferItemId, UserId, WarehouseId};
+use validator::{Validate, ValidationError};
+use crate::utils::validation::field_error;
+use crate::dto::list_dto::sort_keys;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct TransferDto {
-    pub id: Option<i32>,
-    pub from_warehouse_id: Option<i32>,
-    pub to_warehouse_id: Option<i32>,
-    pub user_id: Option<i32>,
+    pub id: Option<TransferId>,
+    pub from_warehouse_id: Option<WarehouseId>,
+    pub to_warehouse_id: Option<WarehouseId>,
+    pub user_id: Option<UserId>,
     pub status: Option<String>,
     pub created_at: Option<chrono::NaiveDateTime>,
     pub updated_at: Option<chrono::NaiveDateTime>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+#[validate(schema(function = "distinct_warehouses"))]
 pub struct CreateTransferRequest {
-    pub from_warehouse_id: i32,
-    pub to_warehouse_id: i32,
-    pub user_id: i32,
-    pub status: Option<String>,
+    pub from_warehouse_id: WarehouseId,
+    pub to_warehouse_id: WarehouseId,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+#[validate(schema(function = "distinct_warehouses_if_both"))]
 pub struct UpdateTransferRequest {
-    pub from_warehouse_id: Option<i32>,
-    pub to_warehouse_id: Option<i32>,
-    pub user_id: Option<i32>,
+    pub from_warehouse_id: Option<WarehouseId>,
+    pub to_warehouse_id: Option<WarehouseId>,
+}
+
+fn distinct_warehouses(req: &CreateTransferRequest) -> Result<(), ValidationError> {
+    check_distinct(Some(req.from_warehouse_id), Some(req.to_warehouse_id))
+}
+
+/// Changing only one side is checked against the stored other side in
+/// `transfer_service::update_transfer`.
+fn distinct_warehouses_if_both(req: &UpdateTransferRequest) -> Result<(), ValidationError> {
+    check_distinct(req.from_warehouse_id, req.to_warehouse_id)
+}
+
+fn check_distinct(from: Option<WarehouseId>, to: Option<WarehouseId>) -> Result<(), ValidationError> {
+    match (from, to) {
+        (Some(from), Some(to)) if from == to => {
+            Err(field_error("to_warehouse_id", "same_warehouse", "must differ from from_warehouse_id"))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct TransferItemDto {
+    pub id: Option<TransferItemId>,
+    pub transfer_id: Option<TransferId>,
+    pub product_id: Option<ProductId>,
+    pub quantity: Option<i32>,
+    pub shipped_quantity: Option<i32>,
+    pub received_quantity: Option<i32>,
+    pub discrepancy_quantity: Option<i32>,
+    pub discrepancy_notes: Option<String>,
+    pub created_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+pub struct CreateTransferItemRequest {
+    pub product_id: ProductId,
+    #[validate(range(min = 1))]
+    pub quantity: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+pub struct UpdateTransferItemRequest {
+    pub product_id: Option<ProductId>,
+    #[validate(range(min = 1))]
+    pub quantity: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct TransferWithItemsDto {
+    #[serde(flatten)]
+    pub transfer: TransferDto,
+    pub items: Vec<TransferItemDto>,
+}
+
+/// Receiving with no lines lands everything still in transit.
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+pub struct ReceiveTransferRequest {
+    #[serde(default)]
+    #[validate(nested)]
+    pub lines: Vec<ReceiveTransferLine>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+pub struct ReceiveTransferLine {
+    pub item_id: TransferItemId,
+    #[validate(range(min = 0))]
+    pub received_quantity: i32,
+    /// Units that will never arrive (lost, damaged in transit, ...).
+    #[validate(range(min = 0))]
+    pub discrepancy_quantity: Option<i32>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct InTransitDto {
+    pub transfer_id: Option<TransferId>,
+    pub item_id: Option<TransferItemId>,
+    pub product_id: Option<ProductId>,
+    pub from_warehouse_id: Option<WarehouseId>,
+    pub to_warehouse_id: Option<WarehouseId>,
+    pub quantity: Option<i32>,
+    pub shipped_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct InTransitFilter {
+    /// Defaults to `id`
+    pub sort: Option<TransferSort>,
+    pub product_id: Option<ProductId>,
+    /// Matches either the source or the destination warehouse.
+    pub warehouse_id: Option<WarehouseId>,
+}
+
+sort_keys!(TransferSort { Id => "id", Status => "status", CreatedAt => "created_at", UpdatedAt => "updated_at" });
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TransferFilter {
+    /// Defaults to `id`
+    pub sort: Option<TransferSort>,
     pub status: Option<String>,
+    /// Transfers leaving or arriving at this warehouse
+    pub warehouse_id: Option<WarehouseId>,
+    /// Created on or after this day
+    pub date_from: Option<chrono::NaiveDate>,
+    /// Created on or before this day
+    pub date_to: Option<chrono::NaiveDate>,
 }
@@ -1,25 +1,75 @@
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use crate::models::ids::{UserId, WarehouseId};
+use crate::models::role::{Permission, Role};
+use validator::Validate;
+use crate::utils::validation::not_blank;
+use crate::dto::list_dto::sort_keys;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct UserDto {
-    pub id: Option<i32>,
+    pub id: Option<UserId>,
     pub name: String,
     pub email: String,
-    pub is_admin: Option<bool>,
+    pub role: String,
     pub created_at: Option<chrono::NaiveDateTime>,
     pub updated_at: Option<chrono::NaiveDateTime>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
 pub struct CreateUserRequest {
+    #[validate(custom(function = "not_blank"), length(max = 100))]
     pub name: String,
+    #[validate(email, length(max = 255))]
     pub email: String,
+    #[validate(length(min = 8))]
     pub password: String,
+    /// Defaults to operator
+    pub role: Option<Role>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
 pub struct UpdateUserRequest {
+    #[validate(custom(function = "not_blank"), length(max = 100))]
     pub name: Option<String>,
+    #[validate(email, length(max = 255))]
     pub email: Option<String>,
+    #[validate(length(min = 8))]
     pub password: Option<String>,
 }
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+pub struct AssignRoleRequest {
+    pub role: Role,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RoleDto {
+    pub role: Role,
+    pub permissions: Vec<Permission>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
+pub struct AssignWarehousesRequest {
+    pub warehouse_ids: Vec<WarehouseId>,
+}
+
+/// The warehouses a user may see and post to. Admins see every warehouse
+/// whatever is assigned here.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UserWarehousesDto {
+    pub user_id: UserId,
+    pub warehouse_ids: Vec<WarehouseId>,
+}
+
+sort_keys!(UserSort { Id => "id", Name => "name", Email => "email", Role => "role", CreatedAt => "created_at" });
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UserFilter {
+    /// Defaults to `id`
+    pub sort: Option<UserSort>,
+    pub role: Option<Role>,
+    /// Matches anywhere in the name or email, case-insensitively
+    pub q: Option<String>,
+}
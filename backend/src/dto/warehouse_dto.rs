@@ -1,22 +1,48 @@
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use crate::models::ids::WarehouseId;
+use validator::Validate;
+use crate::utils::validation::{not_blank, stock_policy};
+use crate::dto::list_dto::sort_keys;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct WarehouseDto {
-    pub id: Option<i32>,
+    pub id: Option<WarehouseId>,
     pub name: String,
     pub location: Option<String>,
+    pub negative_stock_policy: Option<String>,
     pub created_at: Option<chrono::NaiveDateTime>,
     pub updated_at: Option<chrono::NaiveDateTime>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
 pub struct CreateWarehouseRequest {
+    #[validate(custom(function = "not_blank"), length(max = 100))]
     pub name: String,
+    #[validate(length(max = 255))]
     pub location: Option<String>,
+    /// forbid / warn / allow; unset means forbid
+    #[validate(custom(function = "stock_policy"))]
+    pub negative_stock_policy: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
 pub struct UpdateWarehouseRequest {
+    #[validate(custom(function = "not_blank"), length(max = 100))]
     pub name: Option<String>,
+    #[validate(length(max = 255))]
     pub location: Option<String>,
+    #[validate(custom(function = "stock_policy"))]
+    pub negative_stock_policy: Option<String>,
+}
+
+sort_keys!(WarehouseSort { Id => "id", Name => "name", CreatedAt => "created_at" });
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct WarehouseFilter {
+    /// Defaults to `id`
+    pub sort: Option<WarehouseSort>,
+    /// Matches anywhere in the name or location, case-insensitively
+    pub q: Option<String>,
 }
@@ -1,11 +1,13 @@
-use std::clone;
 // src/error/app_error.rs
 use axum::{
     response::{IntoResponse, Json},
-    http::StatusCode,
+    http::{header, StatusCode},
 };
-use serde_json::json;
+use serde::Serialize;
+use sqlx::error::{DatabaseError, ErrorKind};
+use validator::{ValidationError, ValidationErrors, ValidationErrorsKind};
 use thiserror::Error;
+use utoipa::ToSchema;
 
 #[derive(Debug, Error)]
 pub enum AppError {
@@ -18,42 +20,218 @@ pub enum AppError {
     #[error("Unauthorized")]
     Unauthorized,
 
+    #[error("Email address not verified")]
+    EmailNotVerified,
+
+    #[error("Missing permission: {0}")]
+    Forbidden(String),
+
     #[error("JWT error: {0}")]
     Jwt(#[from] jsonwebtoken::errors::Error),
 
     #[error("Bcrypt error: {0}")]
     Bcrypt(#[from] bcrypt::BcryptError),
 
+    #[error("Warehouse {0} is not assigned to you")]
+    WarehouseNotAssigned(crate::models::ids::WarehouseId),
+
     #[error("Not found: {0}")]
     NotFound(String),
 
     #[error("Bad request: {0}")]
     BadRequest(String),
 
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Insufficient stock of product {product_id} in warehouse {warehouse_id}: {available} available, {requested} requested")]
+    InsufficientStock {
+        product_id: crate::models::ids::ProductId,
+        warehouse_id: crate::models::ids::WarehouseId,
+        available: i32,
+        requested: i32,
+    },
+
+    #[error("Too many failed login attempts; try again in {0} seconds")]
+    TooManyAttempts(i64),
+
+    #[error("Invalid request body: {0}")]
+    InvalidBody(#[from] axum::extract::rejection::JsonRejection),
+
+    #[error("Invalid query string: {0}")]
+    InvalidQuery(#[from] axum::extract::rejection::QueryRejection),
+
+    #[error("Invalid path: {0}")]
+    InvalidPath(#[from] axum::extract::rejection::PathRejection),
+
+    #[error("Validation failed")]
+    Validation(Vec<FieldError>),
+
     #[error("Internal server error")]
     Internal,
 }
 
 pub type Result<T> = std::result::Result<T, AppError>;
 
-// Automatic conversion to HTTP response
-impl IntoResponse for AppError {
-    fn into_response(self) -> axum::response::Response {
-        let (status, message): (StatusCode, String) = match self {
+/// JSON body of every error response.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorBody {
+    /// Stable, machine-readable kind of error, e.g. `not_found` or `already_exists`
+    pub code: &'static str,
+    pub message: String,
+    /// The offending fields, when the error is about specific ones
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<FieldError>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl AppError {
+    fn status_and_body(self) -> (StatusCode, ErrorBody) {
+        let (status, code, message) = match self {
             AppError::InvalidCredentials | AppError::Unauthorized => {
-                (StatusCode::UNAUTHORIZED, "Invalid credentials".to_string())
+                (StatusCode::UNAUTHORIZED, "invalid_credentials", "Invalid credentials".to_string())
             }
-            AppError::NotFound(_) => (StatusCode::NOT_FOUND, "Resource not found".to_string()),
-            AppError::BadRequest(message) => (StatusCode::BAD_REQUEST, message),
-            AppError::Internal | AppError::Sqlx(_) | AppError::Jwt(_) | AppError::Bcrypt(_) => {
-                (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error".to_string())
+            ref e @ AppError::EmailNotVerified => (StatusCode::FORBIDDEN, "email_not_verified", e.to_string()),
+            ref e @ AppError::Forbidden(_) => (StatusCode::FORBIDDEN, "forbidden", e.to_string()),
+            ref e @ AppError::WarehouseNotAssigned(_) => (StatusCode::FORBIDDEN, "warehouse_not_assigned", e.to_string()),
+            AppError::NotFound(what) => (StatusCode::NOT_FOUND, "not_found", format!("{what} not found")),
+            AppError::BadRequest(message) => (StatusCode::BAD_REQUEST, "bad_request", message),
+            AppError::Conflict(message) => (StatusCode::CONFLICT, "conflict", message),
+            ref e @ AppError::InsufficientStock { .. } => (StatusCode::CONFLICT, "insufficient_stock", e.to_string()),
+            AppError::InvalidBody(rejection) => (rejection.status(), "invalid_body", rejection.body_text()),
+            AppError::InvalidQuery(rejection) => (StatusCode::BAD_REQUEST, "invalid_query", rejection.body_text()),
+            AppError::InvalidPath(rejection) => match rejection.status() {
+                StatusCode::BAD_REQUEST => (StatusCode::BAD_REQUEST, "invalid_path", rejection.body_text()),
+                _ => internal(&rejection),
+            },
+            AppError::Validation(details) => {
+                let message = match details.as_slice() {
+                    [only] => format!("{} {}", only.field, only.message),
+                    _ => "Validation failed".to_string(),
+                };
+                return (StatusCode::UNPROCESSABLE_ENTITY, ErrorBody { code: "validation_failed", message, details });
             }
+            ref e @ AppError::TooManyAttempts(_) => (StatusCode::TOO_MANY_REQUESTS, "too_many_attempts", e.to_string()),
+            AppError::Sqlx(sqlx::Error::RowNotFound) => (StatusCode::NOT_FOUND, "not_found", "Not found".to_string()),
+            AppError::Sqlx(sqlx::Error::Database(db)) => match constraint_violation(db.as_ref()) {
+                Some(response) => return response,
+                None => internal(&db),
+            },
+            e @ (AppError::Internal | AppError::Sqlx(_) | AppError::Jwt(_) | AppError::Bcrypt(_)) => internal(&e),
         };
+        (status, ErrorBody { code, message, details: Vec::new() })
+    }
+}
 
-        let body = Json(json!({
-            "error": message
-        }));
+impl From<ValidationErrors> for AppError {
+    fn from(errors: ValidationErrors) -> Self {
+        let mut details = Vec::new();
+        flatten(&errors, "", &mut details);
+        details.sort_by(|a, b| a.field.cmp(&b.field));
+        AppError::Validation(details)
+    }
+}
+
+/// Lists every error, naming nested fields like `lines[0].received_quantity`.
+/// Struct-level errors come keyed `__all__` and name their field in a
+/// `field` param (see [`crate::utils::validation::field_error`]).
+fn flatten(errors: &ValidationErrors, prefix: &str, out: &mut Vec<FieldError>) {
+    let path = |field: &str| match (prefix, field) {
+        (_, "__all__") => prefix.to_string(),
+        ("", _) => field.to_string(),
+        _ => format!("{prefix}.{field}"),
+    };
+    for (field, kind) in errors.errors() {
+        match kind {
+            ValidationErrorsKind::Field(errors) => {
+                for error in errors {
+                    let field = error.params.get("field").and_then(|f| f.as_str()).unwrap_or(field);
+                    out.push(FieldError { field: path(field), message: describe(error) });
+                }
+            }
+            ValidationErrorsKind::Struct(errors) => flatten(errors, &path(field), out),
+            ValidationErrorsKind::List(items) => {
+                for (index, errors) in items {
+                    flatten(errors, &format!("{}[{index}]", path(field)), out);
+                }
+            }
+        }
+    }
+}
+
+/// The rule's own message, or one made up from the built-in rule and its
+/// bounds.
+fn describe(error: &ValidationError) -> String {
+    if let Some(message) = &error.message {
+        return message.to_string();
+    }
+    let param = |name: &str| error.params.get(name).map(|v| v.to_string());
+    match (error.code.as_ref(), param("min"), param("max")) {
+        ("email", _, _) => "must be a valid email address".to_string(),
+        ("length", Some(min), Some(max)) => format!("must be {min} to {max} characters long"),
+        ("length", Some(min), None) => format!("must be at least {min} characters long"),
+        ("length", None, Some(max)) => format!("must be at most {max} characters long"),
+        ("range", Some(min), Some(max)) => format!("must be between {min} and {max}"),
+        ("range", Some(min), None) => format!("must be at least {min}"),
+        ("range", None, Some(max)) => format!("must be at most {max}"),
+        (code, _, _) => format!("is invalid ({code})"),
+    }
+}
+
+/// Logs the cause, which may carry SQL or other internals, and answers with
+/// a generic message.
+fn internal(cause: &dyn std::fmt::Display) -> (StatusCode, &'static str, String) {
+    tracing::error!("{cause}");
+    (StatusCode::INTERNAL_SERVER_ERROR, "internal_error", "Internal server error".to_string())
+}
+
+/// Maps the constraint violations a client can cause to a client error,
+/// naming the column when the constraint follows Postgres' default naming
+/// (`{table}_{column}_key`, `_fkey` or `_check`).
+fn constraint_violation(db: &dyn DatabaseError) -> Option<(StatusCode, ErrorBody)> {
+    let (status, code, suffix, message) = match db.kind() {
+        ErrorKind::UniqueViolation => (StatusCode::CONFLICT, "already_exists", "_key", "is already taken"),
+        // Deleting a row that others still point at; the constraint names
+        // the referencing table, not a field of this request.
+        ErrorKind::ForeignKeyViolation if db.message().starts_with("update or delete") => {
+            let message = match db.table() {
+                Some(table) => format!("Still referenced from {table}"),
+                None => "Still referenced by other records".to_string(),
+            };
+            return Some((StatusCode::UNPROCESSABLE_ENTITY, ErrorBody { code: "still_referenced", message, details: Vec::new() }));
+        }
+        ErrorKind::ForeignKeyViolation => (StatusCode::UNPROCESSABLE_ENTITY, "invalid_reference", "_fkey", "refers to a record that does not exist"),
+        ErrorKind::CheckViolation => (StatusCode::UNPROCESSABLE_ENTITY, "invalid_value", "_check", "is not an allowed value"),
+        _ => return None,
+    };
+    let field = db.constraint().zip(db.table()).and_then(|(constraint, table)| {
+        constraint.strip_prefix(table)?.strip_prefix('_')?.strip_suffix(suffix).map(str::to_string)
+    });
+    let (message, details) = match field {
+        Some(field) => (format!("{field} {message}"), vec![FieldError { message: message.to_string(), field }]),
+        None => (format!("A value {message}"), Vec::new()),
+    };
+    Some((status, ErrorBody { code, message, details }))
+}
+
+// Automatic conversion to HTTP response
+impl IntoResponse for AppError {
+    fn into_response(self) -> axum::response::Response {
+        let retry_after = match self {
+            AppError::TooManyAttempts(secs) => Some(secs),
+            _ => None,
+        };
+        let (status, body) = self.status_and_body();
+        let body = Json(body);
 
-        (status, body).into_response()
+        match retry_after {
+            Some(secs) => (status, [(header::RETRY_AFTER, secs.to_string())], body).into_response(),
+            None => (status, body).into_response(),
+        }
     }
 }
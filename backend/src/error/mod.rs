@@ -1,3 +1,3 @@
 // src/error/mod.rs
 pub mod app_error;
-pub use app_error::{AppError, Result};
\ No newline at end of file
+pub use app_error::{AppError, ErrorBody, Result};
\ No newline at end of file
@@ -1,32 +1,84 @@
-use axum::{extract::{State, Path, Json}, http::StatusCode, response::IntoResponse};
-use crate::dto::adjustment_dto::{CreateAdjustmentRequest, UpdateAdjustmentRequest};
+use axum::{extract::{State, Json}, http::StatusCode, response::IntoResponse};
+use crate::dto::list_dto::{ListParams, Page};
+use crate::dto::adjustment_dto::{AdjustmentFilter, CreateAdjustmentRequest, UpdateAdjustmentRequest, AdjustmentDto};
 use crate::repository::adjustment_repo;
+use crate::services::adjustment_service;
+use crate::error::{AppError, ErrorBody};
+use crate::middleware::{perm, ListQuery, Authorized, AuthUser, ValidatedJson, Path};
+use crate::models::ids::AdjustmentId;
 use crate::config::database::DbPool;
 
-pub async fn list_adjustments(State(pool): State<DbPool>) -> impl IntoResponse {
-    match adjustment_repo::get_all(&pool).await {
+#[utoipa::path(
+    get,
+    path = "/api/v1/adjustments",
+    tag = "adjustments",
+    params(ListParams, AdjustmentFilter),
+    responses(
+        (status = 200, description = "OK", body = Page<AdjustmentDto>),
+        (status = 400, description = "Unknown sort field or malformed filter", body = ErrorBody),
+    )
+)]
+pub async fn list_adjustments(State(pool): State<DbPool>, user: AuthUser, ListQuery(params, filter): ListQuery<AdjustmentFilter>) -> impl IntoResponse {
+    match adjustment_repo::get_all(&pool, &params, &filter, user.warehouses.ids()).await {
         Ok(adjustments) => (StatusCode::OK, Json(adjustments)).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => AppError::from(e).into_response(),
     }
 }
 
-pub async fn create_adjustment(State(pool): State<DbPool>, Json(req): Json<CreateAdjustmentRequest>) -> impl IntoResponse {
-    match adjustment_repo::create(&pool, req).await {
+#[utoipa::path(
+    post,
+    path = "/api/v1/adjustments",
+    tag = "adjustments",
+    request_body = CreateAdjustmentRequest,
+    responses(
+        (status = 201, description = "Created", body = AdjustmentDto),
+        (status = 400, description = "Invalid request", body = ErrorBody),
+        (status = 409, description = "Insufficient stock", body = ErrorBody),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 422, description = "Invalid field", body = ErrorBody),
+    )
+)]
+pub async fn create_adjustment(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::PostAdjustments>, ValidatedJson(req): ValidatedJson<CreateAdjustmentRequest>) -> impl IntoResponse {
+    match adjustment_service::create_adjustment(&pool, req, user.id, &user.warehouses).await {
         Ok(adjustment) => (StatusCode::CREATED, Json(adjustment)).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => e.into_response(),
     }
 }
 
-pub async fn update_adjustment(State(pool): State<DbPool>, Path(id): Path<i32>, Json(req): Json<UpdateAdjustmentRequest>) -> impl IntoResponse {
-    match adjustment_repo::update(&pool, id, req).await {
+#[utoipa::path(
+    put,
+    path = "/api/v1/adjustments/{id}",
+    tag = "adjustments",
+    params(("id" = i32, Path)),
+    request_body = UpdateAdjustmentRequest,
+    responses(
+        (status = 200, description = "OK", body = AdjustmentDto),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 422, description = "Invalid field", body = ErrorBody),
+    )
+)]
+pub async fn update_adjustment(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::PostAdjustments>, Path(id): Path<AdjustmentId>, ValidatedJson(req): ValidatedJson<UpdateAdjustmentRequest>) -> impl IntoResponse {
+    match adjustment_service::update_adjustment(&pool, id, req, &user.warehouses).await {
         Ok(adjustment) => (StatusCode::OK, Json(adjustment)).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => e.into_response(),
     }
 }
 
-pub async fn delete_adjustment(State(pool): State<DbPool>, Path(id): Path<i32>) -> impl IntoResponse {
-    match adjustment_repo::delete(&pool, id).await {
-        Ok(_) => StatusCode::NO_CONTENT.into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+#[utoipa::path(
+    post,
+    path = "/api/v1/adjustments/{id}/reverse",
+    tag = "adjustments",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, description = "Reversed and cancelled", body = AdjustmentDto),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 409, description = "Not allowed in the current status", body = ErrorBody),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+    )
+)]
+pub async fn reverse_adjustment(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ReverseDocuments>, Path(id): Path<AdjustmentId>) -> impl IntoResponse {
+    match adjustment_service::reverse_adjustment(&pool, id, user.id, &user.warehouses).await {
+        Ok(adjustment) => (StatusCode::OK, Json(adjustment)).into_response(),
+        Err(e) => e.into_response(),
     }
 }
@@ -0,0 +1,60 @@
+use axum::{extract::{State, Json}, http::StatusCode, response::IntoResponse};
+use crate::dto::api_key_dto::{ApiKeyDto, CreateApiKeyRequest, CreatedApiKeyDto};
+use crate::services::api_key_service;
+use crate::models::ids::ApiKeyId;
+use crate::middleware::{perm, Authorized, ValidatedJson, Path};
+use crate::error::ErrorBody;
+use crate::config::database::DbPool;
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/api_keys",
+    tag = "api_keys",
+    responses(
+        (status = 200, description = "Every key, revoked and expired ones included", body = Vec<ApiKeyDto>),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+    )
+)]
+pub async fn list_api_keys(State(pool): State<DbPool>, _: Authorized<perm::ManageUsers>) -> impl IntoResponse {
+    match api_key_service::list(&pool).await {
+        Ok(keys) => (StatusCode::OK, Json(keys)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/api_keys",
+    tag = "api_keys",
+    request_body = CreateApiKeyRequest,
+    responses(
+        (status = 201, description = "Created; `key` is only returned here", body = CreatedApiKeyDto),
+        (status = 400, description = "Invalid request", body = ErrorBody),
+        (status = 403, description = "Missing permission, or a scope or warehouse the caller does not have", body = ErrorBody),
+        (status = 422, description = "Invalid field", body = ErrorBody),
+    )
+)]
+pub async fn create_api_key(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ManageUsers>, ValidatedJson(req): ValidatedJson<CreateApiKeyRequest>) -> impl IntoResponse {
+    match api_key_service::create(&pool, &user, req).await {
+        Ok(key) => (StatusCode::CREATED, Json(key)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/api_keys/{id}",
+    tag = "api_keys",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 204, description = "Revoked; the key stops working immediately"),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 404, description = "Not found or already revoked", body = ErrorBody),
+    )
+)]
+pub async fn revoke_api_key(State(pool): State<DbPool>, _: Authorized<perm::ManageUsers>, Path(id): Path<ApiKeyId>) -> impl IntoResponse {
+    match api_key_service::revoke(&pool, id).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => e.into_response(),
+    }
+}
@@ -0,0 +1,23 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse};
+use crate::repository::audit_repo;
+use crate::repository::audit_repo::{AuditFilter, AuditPage};
+use crate::middleware::{perm, Authorized, Query};
+use crate::error::{AppError, ErrorBody};
+use crate::config::database::DbPool;
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/audit",
+    tag = "audit",
+    params(AuditFilter),
+    responses(
+        (status = 200, description = "OK", body = AuditPage),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+    )
+)]
+pub async fn list_audit(State(pool): State<DbPool>, _: Authorized<perm::ViewAuditLog>, Query(params): Query<AuditFilter>) -> impl IntoResponse {
+    match audit_repo::get_all(&pool, params).await {
+        Ok(entries) => (StatusCode::OK, axum::Json(entries)).into_response(),
+        Err(e) => AppError::from(e).into_response(),
+    }
+}
@@ -1,18 +1,178 @@
-use axum::{extract::{State, Json}, http::StatusCode, response::IntoResponse};
+use axum::{extract::State, http::StatusCode, response::IntoResponse};
 use crate::services::auth_service;
+use crate::dto::user_dto::UserDto;
+use crate::error::{AppError, ErrorBody};
 use crate::config::database::DbPool;
-use crate::dto::auth_dto::{RegisterRequest, LoginRequest};
+use crate::dto::auth_dto::{EmailRequest, LoginRequest, RefreshRequest, RegisterRequest, ResetPasswordRequest, RevokedSessionsDto, TokenResponse, VerifyEmailRequest};
+use crate::middleware::{AuthUser, Credential, ValidatedJson};
 
-pub async fn register(State(pool): State<DbPool>, Json(req): Json<RegisterRequest>) -> impl IntoResponse {
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/register",
+    tag = "auth",
+    security(()),
+    request_body = RegisterRequest,
+    responses(
+        (status = 201, description = "Created; a verification link is mailed to the address", body = UserDto),
+        (status = 409, description = "Email already registered", body = ErrorBody),
+        (status = 422, description = "Invalid field", body = ErrorBody),
+    )
+)]
+pub async fn register(State(pool): State<DbPool>, ValidatedJson(req): ValidatedJson<RegisterRequest>) -> impl IntoResponse {
     match auth_service::register_user(&pool, req).await {
         Ok(user) => (StatusCode::CREATED, axum::Json(user)).into_response(),
-        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+        Err(e) => e.into_response(),
     }
 }
 
-pub async fn login(State(pool): State<DbPool>, Json(req): Json<LoginRequest>) -> impl IntoResponse {
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/verify_email/request",
+    tag = "auth",
+    security(()),
+    request_body = EmailRequest,
+    responses(
+        (status = 202, description = "A new link is mailed if the address belongs to an unverified account"),
+        (status = 422, description = "Invalid field", body = ErrorBody),
+    )
+)]
+pub async fn request_email_verification(State(pool): State<DbPool>, ValidatedJson(req): ValidatedJson<EmailRequest>) -> impl IntoResponse {
+    match auth_service::request_email_verification(&pool, &req.email).await {
+        Ok(()) => StatusCode::ACCEPTED.into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/verify_email",
+    tag = "auth",
+    security(()),
+    request_body = VerifyEmailRequest,
+    responses(
+        (status = 204, description = "Email address verified"),
+        (status = 400, description = "Invalid, expired or already used token", body = ErrorBody),
+        (status = 422, description = "Invalid field", body = ErrorBody),
+    )
+)]
+pub async fn verify_email(State(pool): State<DbPool>, ValidatedJson(req): ValidatedJson<VerifyEmailRequest>) -> impl IntoResponse {
+    match auth_service::verify_email(&pool, &req.token).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/password_reset/request",
+    tag = "auth",
+    security(()),
+    request_body = EmailRequest,
+    responses(
+        (status = 202, description = "A reset link is mailed if the address is registered"),
+        (status = 422, description = "Invalid field", body = ErrorBody),
+    )
+)]
+pub async fn request_password_reset(State(pool): State<DbPool>, ValidatedJson(req): ValidatedJson<EmailRequest>) -> impl IntoResponse {
+    match auth_service::request_password_reset(&pool, &req.email).await {
+        Ok(()) => StatusCode::ACCEPTED.into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/password_reset",
+    tag = "auth",
+    security(()),
+    request_body = ResetPasswordRequest,
+    responses(
+        (status = 204, description = "Password changed and every session revoked"),
+        (status = 400, description = "Invalid, expired or already used token", body = ErrorBody),
+        (status = 422, description = "Invalid field", body = ErrorBody),
+    )
+)]
+pub async fn reset_password(State(pool): State<DbPool>, ValidatedJson(req): ValidatedJson<ResetPasswordRequest>) -> impl IntoResponse {
+    match auth_service::reset_password(&pool, &req.token, &req.new_password).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/login",
+    tag = "auth",
+    security(()),
+    request_body = LoginRequest,
+    responses(
+        (status = 200, description = "Access and refresh tokens", body = TokenResponse),
+        (status = 401, description = "Invalid credentials", body = ErrorBody),
+        (status = 403, description = "Email address not verified", body = ErrorBody),
+        (status = 429, description = "Too many failed attempts for this account or address; see the Retry-After header", body = ErrorBody),
+        (status = 422, description = "Invalid field", body = ErrorBody),
+    )
+)]
+pub async fn login(State(pool): State<DbPool>, ValidatedJson(req): ValidatedJson<LoginRequest>) -> impl IntoResponse {
     match auth_service::login_user(&pool, req).await {
-        Ok(token) => (StatusCode::OK, axum::Json(token)).into_response(),
-        Err(e) => (StatusCode::UNAUTHORIZED, e.to_string()).into_response(),
+        Ok(tokens) => (StatusCode::OK, axum::Json(tokens)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/refresh",
+    tag = "auth",
+    security(()),
+    request_body = RefreshRequest,
+    responses(
+        (status = 200, description = "New access and refresh tokens; the old refresh token is spent", body = TokenResponse),
+        (status = 401, description = "Unknown, expired, revoked or already used refresh token", body = ErrorBody),
+        (status = 422, description = "Invalid field", body = ErrorBody),
+    )
+)]
+pub async fn refresh(State(pool): State<DbPool>, ValidatedJson(req): ValidatedJson<RefreshRequest>) -> impl IntoResponse {
+    match auth_service::refresh(&pool, &req.refresh_token).await {
+        Ok(tokens) => (StatusCode::OK, axum::Json(tokens)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/logout",
+    tag = "auth",
+    responses(
+        (status = 204, description = "Session revoked"),
+        (status = 400, description = "Called with an API key", body = ErrorBody),
+    )
+)]
+pub async fn logout(State(pool): State<DbPool>, user: AuthUser) -> impl IntoResponse {
+    let Credential::Session(session_id) = user.credential else {
+        return AppError::BadRequest("API keys have no session; revoke the key instead".to_string()).into_response();
+    };
+    match auth_service::logout(&pool, user.id, session_id).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/logout_all",
+    tag = "auth",
+    responses(
+        (status = 200, description = "Every session of the caller revoked, this one included", body = RevokedSessionsDto),
+        (status = 400, description = "Called with an API key", body = ErrorBody),
+    )
+)]
+pub async fn logout_all(State(pool): State<DbPool>, user: AuthUser) -> impl IntoResponse {
+    if let Credential::ApiKey { .. } = user.credential {
+        return AppError::BadRequest("API keys cannot sign out their creator; revoke the key instead".to_string()).into_response();
+    }
+    match auth_service::logout_all(&pool, user.id).await {
+        Ok(revoked) => (StatusCode::OK, axum::Json(revoked)).into_response(),
+        Err(e) => e.into_response(),
     }
 }
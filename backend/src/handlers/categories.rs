@@ -1,32 +1,84 @@
-use axum::{extract::{State, Path, Json}, http::StatusCode, response::IntoResponse};
-use crate::dto::category_dto::{CreateCategoryRequest, UpdateCategoryRequest};
+use axum::{extract::{State, Json}, http::StatusCode, response::IntoResponse};
+use crate::dto::list_dto::{ListParams, Page};
+use crate::dto::category_dto::{CategoryFilter, CreateCategoryRequest, UpdateCategoryRequest, CategoryDto};
 use crate::repository::category_repo;
+use crate::models::ids::CategoryId;
+use crate::middleware::{perm, ListQuery, Authorized, ValidatedJson, Path};
+use crate::error::{AppError, ErrorBody};
 use crate::config::database::DbPool;
 
-pub async fn list_categories(State(pool): State<DbPool>) -> impl IntoResponse {
-    match category_repo::get_all(&pool).await {
+#[utoipa::path(
+    get,
+    path = "/api/v1/categories",
+    tag = "categories",
+    params(ListParams, CategoryFilter),
+    responses(
+        (status = 200, description = "OK", body = Page<CategoryDto>),
+        (status = 400, description = "Unknown sort field or malformed filter", body = ErrorBody),
+    )
+)]
+pub async fn list_categories(State(pool): State<DbPool>, ListQuery(params, filter): ListQuery<CategoryFilter>) -> impl IntoResponse {
+    match category_repo::get_all(&pool, &params, &filter).await {
         Ok(categories) => (StatusCode::OK, Json(categories)).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => AppError::from(e).into_response(),
     }
 }
 
-pub async fn create_category(State(pool): State<DbPool>, Json(req): Json<CreateCategoryRequest>) -> impl IntoResponse {
+#[utoipa::path(
+    post,
+    path = "/api/v1/categories",
+    tag = "categories",
+    request_body = CreateCategoryRequest,
+    responses(
+        (status = 201, description = "Created", body = CategoryDto),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 422, description = "Invalid field", body = ErrorBody),
+    )
+)]
+pub async fn create_category(State(pool): State<DbPool>, _: Authorized<perm::EditCatalog>, ValidatedJson(req): ValidatedJson<CreateCategoryRequest>) -> impl IntoResponse {
     match category_repo::create(&pool, req).await {
         Ok(category) => (StatusCode::CREATED, Json(category)).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => AppError::from(e).into_response(),
     }
 }
 
-pub async fn update_category(State(pool): State<DbPool>, Path(id): Path<i32>, Json(req): Json<UpdateCategoryRequest>) -> impl IntoResponse {
+#[utoipa::path(
+    put,
+    path = "/api/v1/categories/{id}",
+    tag = "categories",
+    params(("id" = i32, Path)),
+    request_body = UpdateCategoryRequest,
+    responses(
+        (status = 200, description = "OK", body = CategoryDto),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 422, description = "Invalid field", body = ErrorBody),
+    )
+)]
+pub async fn update_category(State(pool): State<DbPool>, _: Authorized<perm::EditCatalog>, Path(id): Path<CategoryId>, ValidatedJson(req): ValidatedJson<UpdateCategoryRequest>) -> impl IntoResponse {
     match category_repo::update(&pool, id, req).await {
-        Ok(category) => (StatusCode::OK, Json(category)).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Ok(Some(category)) => (StatusCode::OK, Json(category)).into_response(),
+        Ok(None) => AppError::NotFound(format!("Category {id}")).into_response(),
+        Err(e) => AppError::from(e).into_response(),
     }
 }
 
-pub async fn delete_category(State(pool): State<DbPool>, Path(id): Path<i32>) -> impl IntoResponse {
+#[utoipa::path(
+    delete,
+    path = "/api/v1/categories/{id}",
+    tag = "categories",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 204, description = "Deleted"),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 422, description = "Still referenced by products", body = ErrorBody),
+    )
+)]
+pub async fn delete_category(State(pool): State<DbPool>, _: Authorized<perm::EditCatalog>, Path(id): Path<CategoryId>) -> impl IntoResponse {
     match category_repo::delete(&pool, id).await {
-        Ok(_) => StatusCode::NO_CONTENT.into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => AppError::NotFound(format!("Category {id}")).into_response(),
+        Err(e) => AppError::from(e).into_response(),
     }
 }
@@ -1,7 +1,23 @@
-use axum::{extract::State, http::StatusCode, response::IntoResponse};
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use crate::middleware::{perm, Authorized, Query};
+use crate::error::ErrorBody;
 use crate::config::database::DbPool;
+use crate::dto::dashboard_dto::{DashboardFilter, DashboardSummaryDto};
+use crate::services::dashboard_service;
 
-pub async fn summary(State(_pool): State<DbPool>) -> impl IntoResponse {
-    // TODO: implement dashboard summary logic
-    (StatusCode::NOT_IMPLEMENTED, "Dashboard summary endpoint not implemented yet").into_response()
+#[utoipa::path(
+    get,
+    path = "/api/v1/dashboard/summary",
+    tag = "reports",
+    params(DashboardFilter),
+    responses(
+        (status = 200, description = "OK", body = DashboardSummaryDto),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+    )
+)]
+pub async fn summary(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ViewReports>, Query(params): Query<DashboardFilter>) -> impl IntoResponse {
+    match dashboard_service::summary(&pool, params, &user.warehouses).await {
+        Ok(summary) => (StatusCode::OK, Json(summary)).into_response(),
+        Err(e) => e.into_response(),
+    }
 }
@@ -1,32 +1,274 @@
-use axum::{extract::{State, Path, Json}, http::StatusCode, response::IntoResponse};
-use crate::dto::delivery_dto::{CreateDeliveryRequest, UpdateDeliveryRequest};
+use axum::{extract::{State, Json}, http::StatusCode, response::IntoResponse};
+use crate::dto::list_dto::{ListParams, Page};
+use crate::dto::delivery_dto::{DeliveryFilter, CreateDeliveryRequest, UpdateDeliveryRequest, CreateDeliveryItemRequest, UpdateDeliveryItemRequest, DeliveryDto, DeliveryItemDto, DeliveryWithItemsDto};
 use crate::repository::delivery_repo;
+use crate::services::delivery_service;
+use crate::models::delivery::DeliveryStatus;
+use crate::error::{AppError, ErrorBody};
+use crate::middleware::{perm, ListQuery, Authorized, AuthUser, ValidatedJson, Path};
+use crate::models::ids::{DeliveryId, DeliveryItemId};
 use crate::config::database::DbPool;
 
-pub async fn list_deliveries(State(pool): State<DbPool>) -> impl IntoResponse {
-    match delivery_repo::get_all(&pool).await {
+#[utoipa::path(
+    get,
+    path = "/api/v1/deliveries",
+    tag = "deliveries",
+    params(ListParams, DeliveryFilter),
+    responses(
+        (status = 200, description = "OK", body = Page<DeliveryDto>),
+        (status = 400, description = "Unknown sort field or malformed filter", body = ErrorBody),
+    )
+)]
+pub async fn list_deliveries(State(pool): State<DbPool>, user: AuthUser, ListQuery(params, filter): ListQuery<DeliveryFilter>) -> impl IntoResponse {
+    match delivery_repo::get_all(&pool, &params, &filter, user.warehouses.ids()).await {
         Ok(deliveries) => (StatusCode::OK, Json(deliveries)).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => AppError::from(e).into_response(),
     }
 }
 
-pub async fn create_delivery(State(pool): State<DbPool>, Json(req): Json<CreateDeliveryRequest>) -> impl IntoResponse {
-    match delivery_repo::create(&pool, req).await {
+#[utoipa::path(
+    get,
+    path = "/api/v1/deliveries/{id}",
+    tag = "deliveries",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, description = "OK", body = DeliveryWithItemsDto),
+        (status = 404, description = "Not found", body = ErrorBody),
+    )
+)]
+pub async fn get_delivery(State(pool): State<DbPool>, user: AuthUser, Path(id): Path<DeliveryId>) -> impl IntoResponse {
+    match delivery_service::get_delivery(&pool, id, &user.warehouses).await {
+        Ok(delivery) => (StatusCode::OK, Json(delivery)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/deliveries",
+    tag = "deliveries",
+    request_body = CreateDeliveryRequest,
+    responses(
+        (status = 201, description = "Created", body = DeliveryDto),
+        (status = 403, description = "Missing permission, or warehouse not assigned to the caller", body = ErrorBody),
+        (status = 422, description = "Invalid field", body = ErrorBody),
+    )
+)]
+pub async fn create_delivery(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ProcessDeliveries>, ValidatedJson(req): ValidatedJson<CreateDeliveryRequest>) -> impl IntoResponse {
+    match delivery_service::create_delivery(&pool, req, user.id, &user.warehouses).await {
         Ok(delivery) => (StatusCode::CREATED, Json(delivery)).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => e.into_response(),
     }
 }
 
-pub async fn update_delivery(State(pool): State<DbPool>, Path(id): Path<i32>, Json(req): Json<UpdateDeliveryRequest>) -> impl IntoResponse {
-    match delivery_repo::update(&pool, id, req).await {
+#[utoipa::path(
+    put,
+    path = "/api/v1/deliveries/{id}",
+    tag = "deliveries",
+    params(("id" = i32, Path)),
+    request_body = UpdateDeliveryRequest,
+    responses(
+        (status = 200, description = "OK", body = DeliveryDto),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 409, description = "Not allowed in the current status", body = ErrorBody),
+        (status = 422, description = "Invalid field", body = ErrorBody),
+    )
+)]
+pub async fn update_delivery(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ProcessDeliveries>, Path(id): Path<DeliveryId>, ValidatedJson(req): ValidatedJson<UpdateDeliveryRequest>) -> impl IntoResponse {
+    match delivery_service::update_delivery(&pool, id, req, &user.warehouses).await {
         Ok(delivery) => (StatusCode::OK, Json(delivery)).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/deliveries/{id}",
+    tag = "deliveries",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 204, description = "Deleted"),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 409, description = "Not allowed in the current status", body = ErrorBody),
+    )
+)]
+pub async fn delete_delivery(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ProcessDeliveries>, Path(id): Path<DeliveryId>) -> impl IntoResponse {
+    match delivery_service::delete_delivery(&pool, id, &user.warehouses).await {
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/deliveries/{id}/items",
+    tag = "deliveries",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, description = "OK", body = Vec<DeliveryItemDto>),
+        (status = 404, description = "Not found", body = ErrorBody),
+    )
+)]
+pub async fn list_delivery_items(State(pool): State<DbPool>, user: AuthUser, Path(id): Path<DeliveryId>) -> impl IntoResponse {
+    match delivery_service::list_items(&pool, id, &user.warehouses).await {
+        Ok(items) => (StatusCode::OK, Json(items)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/deliveries/{id}/items",
+    tag = "deliveries",
+    params(("id" = i32, Path)),
+    request_body = CreateDeliveryItemRequest,
+    responses(
+        (status = 201, description = "Created", body = DeliveryItemDto),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 409, description = "Not allowed in the current status", body = ErrorBody),
+        (status = 422, description = "Invalid field", body = ErrorBody),
+    )
+)]
+pub async fn add_delivery_item(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ProcessDeliveries>, Path(id): Path<DeliveryId>, ValidatedJson(req): ValidatedJson<CreateDeliveryItemRequest>) -> impl IntoResponse {
+    match delivery_service::add_item(&pool, id, req, &user.warehouses).await {
+        Ok(item) => (StatusCode::CREATED, Json(item)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/deliveries/{id}/items/{item_id}",
+    tag = "deliveries",
+    params(("id" = i32, Path), ("item_id" = i32, Path)),
+    request_body = UpdateDeliveryItemRequest,
+    responses(
+        (status = 200, description = "OK", body = DeliveryItemDto),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 409, description = "Not allowed in the current status", body = ErrorBody),
+        (status = 422, description = "Invalid field", body = ErrorBody),
+    )
+)]
+pub async fn update_delivery_item(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ProcessDeliveries>, Path((id, item_id)): Path<(DeliveryId, DeliveryItemId)>, ValidatedJson(req): ValidatedJson<UpdateDeliveryItemRequest>) -> impl IntoResponse {
+    match delivery_service::update_item(&pool, id, item_id, req, &user.warehouses).await {
+        Ok(item) => (StatusCode::OK, Json(item)).into_response(),
+        Err(e) => e.into_response(),
     }
 }
 
-pub async fn delete_delivery(State(pool): State<DbPool>, Path(id): Path<i32>) -> impl IntoResponse {
-    match delivery_repo::delete(&pool, id).await {
+#[utoipa::path(
+    delete,
+    path = "/api/v1/deliveries/{id}/items/{item_id}",
+    tag = "deliveries",
+    params(("id" = i32, Path), ("item_id" = i32, Path)),
+    responses(
+        (status = 204, description = "Deleted"),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 409, description = "Not allowed in the current status", body = ErrorBody),
+    )
+)]
+pub async fn delete_delivery_item(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ProcessDeliveries>, Path((id, item_id)): Path<(DeliveryId, DeliveryItemId)>) -> impl IntoResponse {
+    match delivery_service::remove_item(&pool, id, item_id, &user.warehouses).await {
         Ok(_) => StatusCode::NO_CONTENT.into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/deliveries/{id}/pick",
+    tag = "deliveries",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, description = "OK", body = DeliveryDto),
+        (status = 400, description = "Invalid request", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 409, description = "Not allowed in the current status, or insufficient stock", body = ErrorBody),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+    )
+)]
+pub async fn pick_delivery(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ProcessDeliveries>, Path(id): Path<DeliveryId>) -> impl IntoResponse {
+    transition(&pool, id, DeliveryStatus::Picked, user).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/deliveries/{id}/pack",
+    tag = "deliveries",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, description = "OK", body = DeliveryDto),
+        (status = 400, description = "Invalid request", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 409, description = "Not allowed in the current status, or insufficient stock", body = ErrorBody),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+    )
+)]
+pub async fn pack_delivery(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ProcessDeliveries>, Path(id): Path<DeliveryId>) -> impl IntoResponse {
+    transition(&pool, id, DeliveryStatus::Packed, user).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/deliveries/{id}/deliver",
+    tag = "deliveries",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, description = "OK", body = DeliveryDto),
+        (status = 400, description = "Invalid request", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 409, description = "Not allowed in the current status, or insufficient stock", body = ErrorBody),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+    )
+)]
+pub async fn complete_delivery(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ProcessDeliveries>, Path(id): Path<DeliveryId>) -> impl IntoResponse {
+    transition(&pool, id, DeliveryStatus::Delivered, user).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/deliveries/{id}/cancel",
+    tag = "deliveries",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, description = "OK", body = DeliveryDto),
+        (status = 400, description = "Invalid request", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 409, description = "Not allowed in the current status, or insufficient stock", body = ErrorBody),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+    )
+)]
+pub async fn cancel_delivery(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ProcessDeliveries>, Path(id): Path<DeliveryId>) -> impl IntoResponse {
+    transition(&pool, id, DeliveryStatus::Cancelled, user).await
+}
+
+async fn transition(pool: &DbPool, id: DeliveryId, next: DeliveryStatus, user: AuthUser) -> axum::response::Response {
+    match delivery_service::transition(pool, id, next, user.id, &user.warehouses).await {
+        Ok(delivery) => (StatusCode::OK, Json(delivery)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/deliveries/{id}/reverse",
+    tag = "deliveries",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, description = "Reversed and cancelled", body = DeliveryDto),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 409, description = "Not posted, or reversing would take stock below zero", body = ErrorBody),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+    )
+)]
+pub async fn reverse_delivery(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ReverseDocuments>, Path(id): Path<DeliveryId>) -> impl IntoResponse {
+    match delivery_service::reverse_delivery(&pool, id, user.id, &user.warehouses).await {
+        Ok(delivery) => (StatusCode::OK, Json(delivery)).into_response(),
+        Err(e) => e.into_response(),
     }
 }
@@ -7,6 +7,7 @@ pub mod warehouses;
 // pub mod transfers;
 // pub mod adjustments;
 pub mod users;
+pub mod api_keys;
 pub mod products;
 pub mod receipts;
 pub mod deliveries;
@@ -18,3 +19,5 @@ pub mod auth;
 pub mod stock;
 pub mod dashboard;
 pub mod move_history;
+pub mod audit;
+pub mod openapi;
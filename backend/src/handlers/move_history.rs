@@ -1,7 +1,23 @@
-use axum::{extract::State, http::StatusCode, response::IntoResponse};
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use crate::middleware::{perm, Authorized, Query};
+use crate::error::{AppError, ErrorBody};
 use crate::config::database::DbPool;
+use crate::dto::move_history_dto::{MoveHistoryFilter, MoveHistoryPage};
+use crate::repository::move_history_repo;
 
-pub async fn list_moves(State(_pool): State<DbPool>) -> impl IntoResponse {
-    // TODO: implement move history logic
-    (StatusCode::NOT_IMPLEMENTED, "Move history endpoint not implemented yet").into_response()
+#[utoipa::path(
+    get,
+    path = "/api/v1/move_history",
+    tag = "reports",
+    params(MoveHistoryFilter),
+    responses(
+        (status = 200, description = "OK", body = MoveHistoryPage),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+    )
+)]
+pub async fn list_moves(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ViewReports>, Query(params): Query<MoveHistoryFilter>) -> impl IntoResponse {
+    match move_history_repo::get_moves(&pool, params, user.warehouses.ids()).await {
+        Ok(page) => (StatusCode::OK, Json(page)).into_response(),
+        Err(e) => AppError::from(e).into_response(),
+    }
 }
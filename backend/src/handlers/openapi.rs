@@ -1,6 +1,94 @@
-use axum::{http::StatusCode, response::IntoResponse};
+use axum::{response::{Html, IntoResponse}, Json};
+use utoipa::{
+    openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, OpenApi,
+};
+use crate::handlers;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "StockMaster API", version = "1.0.0"),
+    modifiers(&BearerAuth),
+    security(("bearer_auth" = []), ("api_key" = [])),
+    paths(
+        handlers::auth::register, handlers::auth::login, handlers::auth::refresh, handlers::auth::logout, handlers::auth::logout_all,
+        handlers::auth::request_email_verification, handlers::auth::verify_email, handlers::auth::request_password_reset, handlers::auth::reset_password,
+        handlers::users::list_users, handlers::users::create_user, handlers::users::update_user, handlers::users::delete_user,
+        handlers::users::assign_role, handlers::users::list_roles,
+        handlers::users::get_user_warehouses, handlers::users::assign_user_warehouses, handlers::users::revoke_user_sessions, handlers::users::unlock_user,
+        handlers::api_keys::list_api_keys, handlers::api_keys::create_api_key, handlers::api_keys::revoke_api_key,
+        handlers::products::list_products, handlers::products::create_product, handlers::products::update_product, handlers::products::delete_product,
+        handlers::warehouses::list_warehouses, handlers::warehouses::create_warehouse, handlers::warehouses::update_warehouse, handlers::warehouses::delete_warehouse,
+        handlers::categories::list_categories, handlers::categories::create_category, handlers::categories::update_category, handlers::categories::delete_category,
+        handlers::receipts::list_receipts, handlers::receipts::create_receipt, handlers::receipts::get_receipt, handlers::receipts::update_receipt, handlers::receipts::delete_receipt,
+        handlers::receipts::list_receipt_items, handlers::receipts::add_receipt_item, handlers::receipts::update_receipt_item, handlers::receipts::delete_receipt_item,
+        handlers::receipts::validate_receipt, handlers::receipts::reverse_receipt,
+        handlers::deliveries::list_deliveries, handlers::deliveries::create_delivery, handlers::deliveries::get_delivery, handlers::deliveries::update_delivery, handlers::deliveries::delete_delivery,
+        handlers::deliveries::list_delivery_items, handlers::deliveries::add_delivery_item, handlers::deliveries::update_delivery_item, handlers::deliveries::delete_delivery_item,
+        handlers::deliveries::pick_delivery, handlers::deliveries::pack_delivery, handlers::deliveries::complete_delivery, handlers::deliveries::cancel_delivery,
+        handlers::deliveries::reverse_delivery,
+        handlers::transfers::list_transfers, handlers::transfers::create_transfer, handlers::transfers::get_transfer, handlers::transfers::update_transfer, handlers::transfers::delete_transfer,
+        handlers::transfers::list_transfer_items, handlers::transfers::add_transfer_item, handlers::transfers::update_transfer_item, handlers::transfers::delete_transfer_item,
+        handlers::transfers::ship_transfer, handlers::transfers::receive_transfer, handlers::transfers::reverse_transfer,
+        handlers::adjustments::list_adjustments, handlers::adjustments::create_adjustment, handlers::adjustments::update_adjustment, handlers::adjustments::reverse_adjustment,
+        handlers::stock::current_stock, handlers::stock::stock_by_product, handlers::stock::stock_by_warehouse, handlers::stock::in_transit_stock,
+        handlers::stock_ledger::list_stock_ledger,
+        handlers::dashboard::summary,
+        handlers::move_history::list_moves,
+        handlers::audit::list_audit,
+    ),
+    tags(
+        (name = "auth"), (name = "users"), (name = "api_keys", description = "Keys for machine clients"), (name = "products"), (name = "warehouses"), (name = "categories"),
+        (name = "receipts"), (name = "deliveries"), (name = "transfers"), (name = "adjustments"),
+        (name = "stock", description = "Stock levels and the stock ledger"),
+        (name = "reports", description = "Dashboard and move history"),
+        (name = "audit", description = "Append-only record of changes and auth events"),
+    )
+)]
+pub struct ApiDoc;
+
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).bearer_format("JWT").build()),
+        );
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-API-Key"))),
+        );
+    }
+}
 
 pub async fn spec() -> impl IntoResponse {
-    // TODO: return OpenAPI spec JSON
-    (StatusCode::NOT_IMPLEMENTED, "OpenAPI spec endpoint not implemented yet").into_response()
+    Json(ApiDoc::openapi())
+}
+
+/// Swagger UI for the spec above. The page loads `swagger-ui.css` and
+/// `swagger-ui-bundle.js` from `SWAGGER_UI_URL`, which defaults to the public
+/// unpkg CDN; point it at a self-hosted copy of `swagger-ui-dist` where the
+/// browser can't reach the internet or a third-party script is not acceptable.
+pub async fn docs() -> impl IntoResponse {
+    let assets = std::env::var("SWAGGER_UI_URL").unwrap_or_else(|_| "https://unpkg.com/swagger-ui-dist@5".to_string());
+    let assets = assets.trim_end_matches('/');
+    Html(format!(r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="utf-8" />
+    <title>StockMaster API</title>
+    <link rel="stylesheet" href="{assets}/swagger-ui.css" />
+</head>
+<body>
+    <div id="swagger-ui"></div>
+    <script src="{assets}/swagger-ui-bundle.js" crossorigin></script>
+    <script>
+        window.onload = () => {{
+            window.ui = SwaggerUIBundle({{ url: "/api/v1/openapi.json", dom_id: "#swagger-ui" }});
+        }};
+    </script>
+</body>
+</html>"##))
 }
@@ -1,32 +1,85 @@
-use axum::{extract::{State, Path, Json}, http::StatusCode, response::IntoResponse};
-use crate::dto::product_dto::{CreateProductRequest, UpdateProductRequest};
+use axum::{extract::{State, Json}, http::StatusCode, response::IntoResponse};
+use crate::dto::list_dto::{ListParams, Page};
+use crate::dto::product_dto::{ProductFilter, CreateProductRequest, UpdateProductRequest, ProductDto};
 use crate::repository::product_repo;
+use crate::models::ids::ProductId;
+use crate::middleware::{perm, ListQuery, Authorized, ValidatedJson, Path};
+use crate::error::{AppError, ErrorBody};
 use crate::config::database::DbPool;
 
-pub async fn list_products(State(pool): State<DbPool>) -> impl IntoResponse {
-    match product_repo::get_all(&pool).await {
+#[utoipa::path(
+    get,
+    path = "/api/v1/products",
+    tag = "products",
+    params(ListParams, ProductFilter),
+    responses(
+        (status = 200, description = "OK", body = Page<ProductDto>),
+        (status = 400, description = "Unknown sort field or malformed filter", body = ErrorBody),
+    )
+)]
+pub async fn list_products(State(pool): State<DbPool>, ListQuery(params, filter): ListQuery<ProductFilter>) -> impl IntoResponse {
+    match product_repo::get_all(&pool, &params, &filter).await {
         Ok(products) => (StatusCode::OK, Json(products)).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => AppError::from(e).into_response(),
     }
 }
 
-pub async fn create_product(State(pool): State<DbPool>, Json(req): Json<CreateProductRequest>) -> impl IntoResponse {
+#[utoipa::path(
+    post,
+    path = "/api/v1/products",
+    tag = "products",
+    request_body = CreateProductRequest,
+    responses(
+        (status = 201, description = "Created", body = ProductDto),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 409, description = "SKU already taken", body = ErrorBody),
+        (status = 422, description = "Invalid field, or category does not exist", body = ErrorBody),
+    )
+)]
+pub async fn create_product(State(pool): State<DbPool>, _: Authorized<perm::EditCatalog>, ValidatedJson(req): ValidatedJson<CreateProductRequest>) -> impl IntoResponse {
     match product_repo::create(&pool, req).await {
         Ok(product) => (StatusCode::CREATED, Json(product)).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => AppError::from(e).into_response(),
     }
 }
 
-pub async fn update_product(State(pool): State<DbPool>, Path(id): Path<i32>, Json(req): Json<UpdateProductRequest>) -> impl IntoResponse {
+#[utoipa::path(
+    put,
+    path = "/api/v1/products/{id}",
+    tag = "products",
+    params(("id" = i32, Path)),
+    request_body = UpdateProductRequest,
+    responses(
+        (status = 200, description = "OK", body = ProductDto),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 422, description = "Invalid field, or category does not exist", body = ErrorBody),
+    )
+)]
+pub async fn update_product(State(pool): State<DbPool>, _: Authorized<perm::EditCatalog>, Path(id): Path<ProductId>, ValidatedJson(req): ValidatedJson<UpdateProductRequest>) -> impl IntoResponse {
     match product_repo::update(&pool, id, req).await {
-        Ok(product) => (StatusCode::OK, Json(product)).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Ok(Some(product)) => (StatusCode::OK, Json(product)).into_response(),
+        Ok(None) => AppError::NotFound(format!("Product {id}")).into_response(),
+        Err(e) => AppError::from(e).into_response(),
     }
 }
 
-pub async fn delete_product(State(pool): State<DbPool>, Path(id): Path<i32>) -> impl IntoResponse {
+#[utoipa::path(
+    delete,
+    path = "/api/v1/products/{id}",
+    tag = "products",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 204, description = "Deleted"),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 422, description = "Still referenced by stock or documents", body = ErrorBody),
+    )
+)]
+pub async fn delete_product(State(pool): State<DbPool>, _: Authorized<perm::EditCatalog>, Path(id): Path<ProductId>) -> impl IntoResponse {
     match product_repo::delete(&pool, id).await {
-        Ok(_) => StatusCode::NO_CONTENT.into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => AppError::NotFound(format!("Product {id}")).into_response(),
+        Err(e) => AppError::from(e).into_response(),
     }
 }
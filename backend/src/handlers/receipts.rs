@@ -1,32 +1,217 @@
-use axum::{extract::{State, Path, Json}, http::StatusCode, response::IntoResponse};
-use crate::dto::receipt_dto::{CreateReceiptRequest, UpdateReceiptRequest};
+use axum::{extract::{State, Json}, http::StatusCode, response::IntoResponse};
+use crate::dto::list_dto::{ListParams, Page};
+use crate::dto::receipt_dto::{ReceiptFilter, CreateReceiptRequest, UpdateReceiptRequest, CreateReceiptItemRequest, UpdateReceiptItemRequest, ReceiptDto, ReceiptItemDto, ReceiptWithItemsDto};
 use crate::repository::receipt_repo;
+use crate::services::receipt_service;
+use crate::error::{AppError, ErrorBody};
+use crate::middleware::{perm, ListQuery, Authorized, AuthUser, ValidatedJson, Path};
+use crate::models::ids::{ReceiptId, ReceiptItemId};
 use crate::config::database::DbPool;
 
-pub async fn list_receipts(State(pool): State<DbPool>) -> impl IntoResponse {
-    match receipt_repo::get_all(&pool).await {
+#[utoipa::path(
+    get,
+    path = "/api/v1/receipts",
+    tag = "receipts",
+    params(ListParams, ReceiptFilter),
+    responses(
+        (status = 200, description = "OK", body = Page<ReceiptDto>),
+        (status = 400, description = "Unknown sort field or malformed filter", body = ErrorBody),
+    )
+)]
+pub async fn list_receipts(State(pool): State<DbPool>, user: AuthUser, ListQuery(params, filter): ListQuery<ReceiptFilter>) -> impl IntoResponse {
+    match receipt_repo::get_all(&pool, &params, &filter, user.warehouses.ids()).await {
         Ok(receipts) => (StatusCode::OK, Json(receipts)).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => AppError::from(e).into_response(),
     }
 }
 
-pub async fn create_receipt(State(pool): State<DbPool>, Json(req): Json<CreateReceiptRequest>) -> impl IntoResponse {
-    match receipt_repo::create(&pool, req).await {
+#[utoipa::path(
+    get,
+    path = "/api/v1/receipts/{id}",
+    tag = "receipts",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, description = "OK", body = ReceiptWithItemsDto),
+        (status = 404, description = "Not found", body = ErrorBody),
+    )
+)]
+pub async fn get_receipt(State(pool): State<DbPool>, user: AuthUser, Path(id): Path<ReceiptId>) -> impl IntoResponse {
+    match receipt_service::get_receipt(&pool, id, &user.warehouses).await {
+        Ok(receipt) => (StatusCode::OK, Json(receipt)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/receipts",
+    tag = "receipts",
+    request_body = CreateReceiptRequest,
+    responses(
+        (status = 201, description = "Created", body = ReceiptDto),
+        (status = 403, description = "Missing permission, or warehouse not assigned to the caller", body = ErrorBody),
+        (status = 422, description = "Invalid field", body = ErrorBody),
+    )
+)]
+pub async fn create_receipt(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ValidateReceipts>, ValidatedJson(req): ValidatedJson<CreateReceiptRequest>) -> impl IntoResponse {
+    match receipt_service::create_receipt(&pool, req, user.id, &user.warehouses).await {
         Ok(receipt) => (StatusCode::CREATED, Json(receipt)).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => e.into_response(),
     }
 }
 
-pub async fn update_receipt(State(pool): State<DbPool>, Path(id): Path<i32>, Json(req): Json<UpdateReceiptRequest>) -> impl IntoResponse {
-    match receipt_repo::update(&pool, id, req).await {
+#[utoipa::path(
+    put,
+    path = "/api/v1/receipts/{id}",
+    tag = "receipts",
+    params(("id" = i32, Path)),
+    request_body = UpdateReceiptRequest,
+    responses(
+        (status = 200, description = "OK", body = ReceiptDto),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 409, description = "Not allowed in the current status", body = ErrorBody),
+        (status = 422, description = "Invalid field", body = ErrorBody),
+    )
+)]
+pub async fn update_receipt(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ValidateReceipts>, Path(id): Path<ReceiptId>, ValidatedJson(req): ValidatedJson<UpdateReceiptRequest>) -> impl IntoResponse {
+    match receipt_service::update_receipt(&pool, id, req, &user.warehouses).await {
         Ok(receipt) => (StatusCode::OK, Json(receipt)).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => e.into_response(),
     }
 }
 
-pub async fn delete_receipt(State(pool): State<DbPool>, Path(id): Path<i32>) -> impl IntoResponse {
-    match receipt_repo::delete(&pool, id).await {
+#[utoipa::path(
+    delete,
+    path = "/api/v1/receipts/{id}",
+    tag = "receipts",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 204, description = "Deleted"),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 409, description = "Not allowed in the current status", body = ErrorBody),
+    )
+)]
+pub async fn delete_receipt(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ValidateReceipts>, Path(id): Path<ReceiptId>) -> impl IntoResponse {
+    match receipt_service::delete_receipt(&pool, id, &user.warehouses).await {
         Ok(_) => StatusCode::NO_CONTENT.into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/receipts/{id}/validate",
+    tag = "receipts",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, description = "Validated", body = ReceiptDto),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 409, description = "Not allowed in the current status", body = ErrorBody),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+    )
+)]
+pub async fn validate_receipt(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ValidateReceipts>, Path(id): Path<ReceiptId>) -> impl IntoResponse {
+    match receipt_service::validate_receipt(&pool, id, user.id, &user.warehouses).await {
+        Ok(receipt) => (StatusCode::OK, Json(receipt)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/receipts/{id}/items",
+    tag = "receipts",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, description = "OK", body = Vec<ReceiptItemDto>),
+        (status = 404, description = "Not found", body = ErrorBody),
+    )
+)]
+pub async fn list_receipt_items(State(pool): State<DbPool>, user: AuthUser, Path(id): Path<ReceiptId>) -> impl IntoResponse {
+    match receipt_service::list_items(&pool, id, &user.warehouses).await {
+        Ok(items) => (StatusCode::OK, Json(items)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/receipts/{id}/items",
+    tag = "receipts",
+    params(("id" = i32, Path)),
+    request_body = CreateReceiptItemRequest,
+    responses(
+        (status = 201, description = "Created", body = ReceiptItemDto),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 409, description = "Not allowed in the current status", body = ErrorBody),
+        (status = 422, description = "Invalid field", body = ErrorBody),
+    )
+)]
+pub async fn add_receipt_item(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ValidateReceipts>, Path(id): Path<ReceiptId>, ValidatedJson(req): ValidatedJson<CreateReceiptItemRequest>) -> impl IntoResponse {
+    match receipt_service::add_item(&pool, id, req, &user.warehouses).await {
+        Ok(item) => (StatusCode::CREATED, Json(item)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/receipts/{id}/items/{item_id}",
+    tag = "receipts",
+    params(("id" = i32, Path), ("item_id" = i32, Path)),
+    request_body = UpdateReceiptItemRequest,
+    responses(
+        (status = 200, description = "OK", body = ReceiptItemDto),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 409, description = "Not allowed in the current status", body = ErrorBody),
+        (status = 422, description = "Invalid field", body = ErrorBody),
+    )
+)]
+pub async fn update_receipt_item(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ValidateReceipts>, Path((id, item_id)): Path<(ReceiptId, ReceiptItemId)>, ValidatedJson(req): ValidatedJson<UpdateReceiptItemRequest>) -> impl IntoResponse {
+    match receipt_service::update_item(&pool, id, item_id, req, &user.warehouses).await {
+        Ok(item) => (StatusCode::OK, Json(item)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/receipts/{id}/items/{item_id}",
+    tag = "receipts",
+    params(("id" = i32, Path), ("item_id" = i32, Path)),
+    responses(
+        (status = 204, description = "Deleted"),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 409, description = "Not allowed in the current status", body = ErrorBody),
+    )
+)]
+pub async fn delete_receipt_item(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ValidateReceipts>, Path((id, item_id)): Path<(ReceiptId, ReceiptItemId)>) -> impl IntoResponse {
+    match receipt_service::remove_item(&pool, id, item_id, &user.warehouses).await {
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/receipts/{id}/reverse",
+    tag = "receipts",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, description = "Reversed and cancelled", body = ReceiptDto),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 409, description = "Not posted, or reversing would take stock below zero", body = ErrorBody),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+    )
+)]
+pub async fn reverse_receipt(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ReverseDocuments>, Path(id): Path<ReceiptId>) -> impl IntoResponse {
+    match receipt_service::reverse_receipt(&pool, id, user.id, &user.warehouses).await {
+        Ok(receipt) => (StatusCode::OK, Json(receipt)).into_response(),
+        Err(e) => e.into_response(),
     }
 }
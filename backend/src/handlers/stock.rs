@@ -1,31 +1,72 @@
-use axum::{extract::{State, Path}, http::StatusCode, response::IntoResponse, Json};
-use uuid::Uuid;
+use axum::{extract::State, response::IntoResponse, Json};
 use crate::services::stock_service;
+use crate::dto::transfer_dto::{InTransitFilter, InTransitDto};
+use crate::models::product_stock::ProductStock;
+use crate::models::ids::{ProductId, WarehouseId};
+use crate::middleware::{AuthUser, Path, Query};
+use crate::error::ErrorBody;
 use crate::config::database::DbPool;
 
-pub async fn current_stock(State(pool): State<DbPool>) -> impl IntoResponse {
-    match stock_service::get_current_stock(&pool).await {
+#[utoipa::path(
+    get,
+    path = "/api/v1/stock/current",
+    tag = "stock",
+    responses(
+        (status = 200, description = "OK", body = Vec<ProductStock>),
+    )
+)]
+pub async fn current_stock(State(pool): State<DbPool>, user: AuthUser) -> impl IntoResponse {
+    match stock_service::get_current_stock(&pool, &user.warehouses).await {
         Ok(stocks) => Json::<Vec<crate::models::product_stock::ProductStock>>(stocks).into_response(),
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch current stock").into_response(),
+        Err(e) => e.into_response(),
     }
 }
 
-pub async fn stock_by_product(State(pool): State<DbPool>, Path(product_id): Path<String>) -> impl IntoResponse {
-    match Uuid::parse_str(&product_id) {
-        Ok(uuid) => match stock_service::get_stock_by_product(&pool, uuid).await {
-            Ok(stocks) => Json::<Vec<crate::models::product_stock::ProductStock>>(stocks).into_response(),
-            Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch stock by product").into_response(),
-        },
-        Err(_) => (StatusCode::BAD_REQUEST, "Invalid product_id").into_response(),
+#[utoipa::path(
+    get,
+    path = "/api/v1/stock/by_product/{product_id}",
+    tag = "stock",
+    params(("product_id" = i32, Path)),
+    responses(
+        (status = 200, description = "OK", body = Vec<ProductStock>),
+    )
+)]
+pub async fn stock_by_product(State(pool): State<DbPool>, user: AuthUser, Path(product_id): Path<ProductId>) -> impl IntoResponse {
+    match stock_service::get_stock_by_product(&pool, product_id, &user.warehouses).await {
+        Ok(stocks) => Json::<Vec<crate::models::product_stock::ProductStock>>(stocks).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/stock/by_warehouse/{warehouse_id}",
+    tag = "stock",
+    params(("warehouse_id" = i32, Path)),
+    responses(
+        (status = 200, description = "OK", body = Vec<ProductStock>),
+        (status = 403, description = "Warehouse not assigned to the caller", body = ErrorBody),
+    )
+)]
+pub async fn stock_by_warehouse(State(pool): State<DbPool>, user: AuthUser, Path(warehouse_id): Path<WarehouseId>) -> impl IntoResponse {
+    match stock_service::get_stock_by_warehouse(&pool, warehouse_id, &user.warehouses).await {
+        Ok(stocks) => Json::<Vec<crate::models::product_stock::ProductStock>>(stocks).into_response(),
+        Err(e) => e.into_response(),
     }
 }
 
-pub async fn stock_by_warehouse(State(pool): State<DbPool>, Path(warehouse_id): Path<String>) -> impl IntoResponse {
-    match Uuid::parse_str(&warehouse_id) {
-        Ok(uuid) => match stock_service::get_stock_by_warehouse(&pool, uuid).await {
-            Ok(stocks) => Json::<Vec<crate::models::product_stock::ProductStock>>(stocks).into_response(),
-            Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch stock by warehouse").into_response(),
-        },
-        Err(_) => (StatusCode::BAD_REQUEST, "Invalid warehouse_id").into_response(),
+#[utoipa::path(
+    get,
+    path = "/api/v1/stock/in_transit",
+    tag = "stock",
+    params(InTransitFilter),
+    responses(
+        (status = 200, description = "OK", body = Vec<InTransitDto>),
+    )
+)]
+pub async fn in_transit_stock(State(pool): State<DbPool>, user: AuthUser, Query(filter): Query<InTransitFilter>) -> impl IntoResponse {
+    match stock_service::get_in_transit(&pool, filter, &user.warehouses).await {
+        Ok(rows) => Json(rows).into_response(),
+        Err(e) => e.into_response(),
     }
 }
@@ -1,10 +1,23 @@
-use axum::{extract::{State, Query}, http::StatusCode, response::IntoResponse};
+use axum::{extract::State, http::StatusCode, response::IntoResponse};
 use crate::repository::stock_ledger_repo;
+use crate::repository::stock_ledger_repo::{StockLedgerFilter, StockLedgerPage};
+use crate::middleware::{perm, Authorized, Query};
+use crate::error::{AppError, ErrorBody};
 use crate::config::database::DbPool;
 
-pub async fn list_stock_ledger(State(pool): State<DbPool>, Query(params): Query<stock_ledger_repo::StockLedgerFilter>) -> impl IntoResponse {
-    match stock_ledger_repo::get_all(&pool, params).await {
+#[utoipa::path(
+    get,
+    path = "/api/v1/stock_ledger",
+    tag = "stock",
+    params(StockLedgerFilter),
+    responses(
+        (status = 200, description = "OK", body = StockLedgerPage),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+    )
+)]
+pub async fn list_stock_ledger(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ViewReports>, Query(params): Query<stock_ledger_repo::StockLedgerFilter>) -> impl IntoResponse {
+    match stock_ledger_repo::get_all(&pool, params, user.warehouses.ids()).await {
         Ok(entries) => (StatusCode::OK, axum::Json(entries)).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => AppError::from(e).into_response(),
     }
 }
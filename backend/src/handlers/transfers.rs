@@ -1,32 +1,239 @@
-use axum::{extract::{State, Path, Json}, http::StatusCode, response::IntoResponse};
-use crate::dto::transfer_dto::{CreateTransferRequest, UpdateTransferRequest};
+use axum::{extract::{State, Json}, http::StatusCode, response::IntoResponse};
+use crate::dto::list_dto::{ListParams, Page};
+use crate::dto::transfer_dto::{TransferFilter, CreateTransferRequest, UpdateTransferRequest, CreateTransferItemRequest, UpdateTransferItemRequest, ReceiveTransferRequest, TransferDto, TransferItemDto, TransferWithItemsDto};
 use crate::repository::transfer_repo;
+use crate::services::transfer_service;
+use crate::error::{AppError, ErrorBody};
+use crate::middleware::{perm, ListQuery, Authorized, AuthUser, ValidatedJson, Path};
+use crate::models::ids::{TransferId, TransferItemId};
 use crate::config::database::DbPool;
 
-pub async fn list_transfers(State(pool): State<DbPool>) -> impl IntoResponse {
-    match transfer_repo::get_all(&pool).await {
+#[utoipa::path(
+    get,
+    path = "/api/v1/transfers",
+    tag = "transfers",
+    params(ListParams, TransferFilter),
+    responses(
+        (status = 200, description = "OK", body = Page<TransferDto>),
+        (status = 400, description = "Unknown sort field or malformed filter", body = ErrorBody),
+    )
+)]
+pub async fn list_transfers(State(pool): State<DbPool>, user: AuthUser, ListQuery(params, filter): ListQuery<TransferFilter>) -> impl IntoResponse {
+    match transfer_repo::get_all(&pool, &params, &filter, user.warehouses.ids()).await {
         Ok(transfers) => (StatusCode::OK, Json(transfers)).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => AppError::from(e).into_response(),
     }
 }
 
-pub async fn create_transfer(State(pool): State<DbPool>, Json(req): Json<CreateTransferRequest>) -> impl IntoResponse {
-    match transfer_repo::create(&pool, req).await {
+#[utoipa::path(
+    get,
+    path = "/api/v1/transfers/{id}",
+    tag = "transfers",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, description = "OK", body = TransferWithItemsDto),
+        (status = 404, description = "Not found", body = ErrorBody),
+    )
+)]
+pub async fn get_transfer(State(pool): State<DbPool>, user: AuthUser, Path(id): Path<TransferId>) -> impl IntoResponse {
+    match transfer_service::get_transfer(&pool, id, &user.warehouses).await {
+        Ok(transfer) => (StatusCode::OK, Json(transfer)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/transfers",
+    tag = "transfers",
+    request_body = CreateTransferRequest,
+    responses(
+        (status = 201, description = "Created", body = TransferDto),
+        (status = 403, description = "Missing permission, or warehouse not assigned to the caller", body = ErrorBody),
+        (status = 422, description = "Invalid field", body = ErrorBody),
+    )
+)]
+pub async fn create_transfer(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ProcessTransfers>, ValidatedJson(req): ValidatedJson<CreateTransferRequest>) -> impl IntoResponse {
+    match transfer_service::create_transfer(&pool, req, user.id, &user.warehouses).await {
         Ok(transfer) => (StatusCode::CREATED, Json(transfer)).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => e.into_response(),
     }
 }
 
-pub async fn update_transfer(State(pool): State<DbPool>, Path(id): Path<i32>, Json(req): Json<UpdateTransferRequest>) -> impl IntoResponse {
-    match transfer_repo::update(&pool, id, req).await {
+#[utoipa::path(
+    put,
+    path = "/api/v1/transfers/{id}",
+    tag = "transfers",
+    params(("id" = i32, Path)),
+    request_body = UpdateTransferRequest,
+    responses(
+        (status = 200, description = "OK", body = TransferDto),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 409, description = "Not allowed in the current status", body = ErrorBody),
+        (status = 422, description = "Invalid field", body = ErrorBody),
+    )
+)]
+pub async fn update_transfer(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ProcessTransfers>, Path(id): Path<TransferId>, ValidatedJson(req): ValidatedJson<UpdateTransferRequest>) -> impl IntoResponse {
+    match transfer_service::update_transfer(&pool, id, req, &user.warehouses).await {
         Ok(transfer) => (StatusCode::OK, Json(transfer)).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => e.into_response(),
     }
 }
 
-pub async fn delete_transfer(State(pool): State<DbPool>, Path(id): Path<i32>) -> impl IntoResponse {
-    match transfer_repo::delete(&pool, id).await {
+#[utoipa::path(
+    delete,
+    path = "/api/v1/transfers/{id}",
+    tag = "transfers",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 204, description = "Deleted"),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 409, description = "Not allowed in the current status", body = ErrorBody),
+    )
+)]
+pub async fn delete_transfer(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ProcessTransfers>, Path(id): Path<TransferId>) -> impl IntoResponse {
+    match transfer_service::delete_transfer(&pool, id, &user.warehouses).await {
         Ok(_) => StatusCode::NO_CONTENT.into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/transfers/{id}/items",
+    tag = "transfers",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, description = "OK", body = Vec<TransferItemDto>),
+        (status = 404, description = "Not found", body = ErrorBody),
+    )
+)]
+pub async fn list_transfer_items(State(pool): State<DbPool>, user: AuthUser, Path(id): Path<TransferId>) -> impl IntoResponse {
+    match transfer_service::list_items(&pool, id, &user.warehouses).await {
+        Ok(items) => (StatusCode::OK, Json(items)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/transfers/{id}/items",
+    tag = "transfers",
+    params(("id" = i32, Path)),
+    request_body = CreateTransferItemRequest,
+    responses(
+        (status = 201, description = "Created", body = TransferItemDto),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 409, description = "Not allowed in the current status", body = ErrorBody),
+        (status = 422, description = "Invalid field", body = ErrorBody),
+    )
+)]
+pub async fn add_transfer_item(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ProcessTransfers>, Path(id): Path<TransferId>, ValidatedJson(req): ValidatedJson<CreateTransferItemRequest>) -> impl IntoResponse {
+    match transfer_service::add_item(&pool, id, req, &user.warehouses).await {
+        Ok(item) => (StatusCode::CREATED, Json(item)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/transfers/{id}/items/{item_id}",
+    tag = "transfers",
+    params(("id" = i32, Path), ("item_id" = i32, Path)),
+    request_body = UpdateTransferItemRequest,
+    responses(
+        (status = 200, description = "OK", body = TransferItemDto),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 409, description = "Not allowed in the current status", body = ErrorBody),
+        (status = 422, description = "Invalid field", body = ErrorBody),
+    )
+)]
+pub async fn update_transfer_item(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ProcessTransfers>, Path((id, item_id)): Path<(TransferId, TransferItemId)>, ValidatedJson(req): ValidatedJson<UpdateTransferItemRequest>) -> impl IntoResponse {
+    match transfer_service::update_item(&pool, id, item_id, req, &user.warehouses).await {
+        Ok(item) => (StatusCode::OK, Json(item)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/transfers/{id}/items/{item_id}",
+    tag = "transfers",
+    params(("id" = i32, Path), ("item_id" = i32, Path)),
+    responses(
+        (status = 204, description = "Deleted"),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 409, description = "Not allowed in the current status", body = ErrorBody),
+    )
+)]
+pub async fn delete_transfer_item(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ProcessTransfers>, Path((id, item_id)): Path<(TransferId, TransferItemId)>) -> impl IntoResponse {
+    match transfer_service::remove_item(&pool, id, item_id, &user.warehouses).await {
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/transfers/{id}/ship",
+    tag = "transfers",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, description = "Shipped", body = TransferDto),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 409, description = "Not a draft, or insufficient stock", body = ErrorBody),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+    )
+)]
+pub async fn ship_transfer(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ProcessTransfers>, Path(id): Path<TransferId>) -> impl IntoResponse {
+    match transfer_service::ship_transfer(&pool, id, user.id, &user.warehouses).await {
+        Ok(transfer) => (StatusCode::OK, Json(transfer)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/transfers/{id}/receive",
+    tag = "transfers",
+    params(("id" = i32, Path)),
+    request_body = ReceiveTransferRequest,
+    responses(
+        (status = 200, description = "Received", body = TransferWithItemsDto),
+        (status = 400, description = "Invalid request", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 409, description = "Not allowed in the current status", body = ErrorBody),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 422, description = "Invalid field", body = ErrorBody),
+    )
+)]
+pub async fn receive_transfer(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ProcessTransfers>, Path(id): Path<TransferId>, ValidatedJson(req): ValidatedJson<ReceiveTransferRequest>) -> impl IntoResponse {
+    match transfer_service::receive_transfer(&pool, id, req, user.id, &user.warehouses).await {
+        Ok(transfer) => (StatusCode::OK, Json(transfer)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/transfers/{id}/reverse",
+    tag = "transfers",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, description = "Reversed and cancelled", body = TransferDto),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 409, description = "Not posted, or reversing would take stock below zero", body = ErrorBody),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+    )
+)]
+pub async fn reverse_transfer(State(pool): State<DbPool>, Authorized(user, _): Authorized<perm::ReverseDocuments>, Path(id): Path<TransferId>) -> impl IntoResponse {
+    match transfer_service::reverse_transfer(&pool, id, user.id, &user.warehouses).await {
+        Ok(transfer) => (StatusCode::OK, Json(transfer)).into_response(),
+        Err(e) => e.into_response(),
     }
 }
@@ -1,32 +1,195 @@
-use axum::{extract::{State, Path, Json}, http::StatusCode, response::IntoResponse};
-use crate::dto::user_dto::{CreateUserRequest, UpdateUserRequest};
+use axum::{extract::{State, Json}, http::StatusCode, response::IntoResponse};
+use crate::dto::auth_dto::RevokedSessionsDto;
+use crate::dto::list_dto::{ListParams, Page};
+use crate::dto::user_dto::{UserFilter, AssignRoleRequest, AssignWarehousesRequest, CreateUserRequest, RoleDto, UpdateUserRequest, UserDto, UserWarehousesDto};
 use crate::repository::user_repo;
+use crate::services::{login_throttle_service, user_service};
+use crate::models::ids::UserId;
+use crate::middleware::{perm, ListQuery, Authorized, ValidatedJson, Path};
+use crate::error::{AppError, ErrorBody};
 use crate::config::database::DbPool;
 
-pub async fn list_users(State(pool): State<DbPool>) -> impl IntoResponse {
-    match user_repo::get_all(&pool).await {
+#[utoipa::path(
+    get,
+    path = "/api/v1/users",
+    tag = "users",
+    params(ListParams, UserFilter),
+    responses(
+        (status = 200, description = "OK", body = Page<UserDto>),
+        (status = 400, description = "Unknown sort field or malformed filter", body = ErrorBody),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+    )
+)]
+pub async fn list_users(State(pool): State<DbPool>, _: Authorized<perm::ManageUsers>, ListQuery(params, filter): ListQuery<UserFilter>) -> impl IntoResponse {
+    match user_repo::get_all(&pool, &params, &filter).await {
         Ok(users) => (StatusCode::OK, Json(users)).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => AppError::from(e).into_response(),
     }
 }
 
-pub async fn create_user(State(pool): State<DbPool>, Json(req): Json<CreateUserRequest>) -> impl IntoResponse {
-    match user_repo::create(&pool, req).await {
+#[utoipa::path(
+    post,
+    path = "/api/v1/users",
+    tag = "users",
+    request_body = CreateUserRequest,
+    responses(
+        (status = 201, description = "Created", body = UserDto),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 409, description = "Email already registered", body = ErrorBody),
+        (status = 422, description = "Invalid field", body = ErrorBody),
+    )
+)]
+pub async fn create_user(State(pool): State<DbPool>, _: Authorized<perm::ManageUsers>, ValidatedJson(req): ValidatedJson<CreateUserRequest>) -> impl IntoResponse {
+    match user_service::create_user(&pool, req).await {
         Ok(user) => (StatusCode::CREATED, Json(user)).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => e.into_response(),
     }
 }
 
-pub async fn update_user(State(pool): State<DbPool>, Path(id): Path<i32>, Json(req): Json<UpdateUserRequest>) -> impl IntoResponse {
-    match user_repo::update(&pool, id, req).await {
+#[utoipa::path(
+    put,
+    path = "/api/v1/users/{id}",
+    tag = "users",
+    params(("id" = i32, Path)),
+    request_body = UpdateUserRequest,
+    responses(
+        (status = 200, description = "OK", body = UserDto),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 409, description = "Email already registered", body = ErrorBody),
+        (status = 422, description = "Invalid field", body = ErrorBody),
+    )
+)]
+pub async fn update_user(State(pool): State<DbPool>, _: Authorized<perm::ManageUsers>, Path(id): Path<UserId>, ValidatedJson(req): ValidatedJson<UpdateUserRequest>) -> impl IntoResponse {
+    match user_service::update_user(&pool, id, req).await {
         Ok(user) => (StatusCode::OK, Json(user)).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => e.into_response(),
     }
 }
 
-pub async fn delete_user(State(pool): State<DbPool>, Path(id): Path<i32>) -> impl IntoResponse {
-    match user_repo::delete(&pool, id).await {
-        Ok(_) => StatusCode::NO_CONTENT.into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/{id}",
+    tag = "users",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 204, description = "Deleted"),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 409, description = "User is the last admin", body = ErrorBody),
+    )
+)]
+pub async fn delete_user(State(pool): State<DbPool>, _: Authorized<perm::ManageUsers>, Path(id): Path<UserId>) -> impl IntoResponse {
+    match user_service::delete_user(&pool, id).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/users/{id}/role",
+    tag = "users",
+    params(("id" = i32, Path)),
+    request_body = AssignRoleRequest,
+    responses(
+        (status = 200, description = "OK", body = UserDto),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 409, description = "User is the last admin", body = ErrorBody),
+        (status = 422, description = "Invalid field", body = ErrorBody),
+    )
+)]
+pub async fn assign_role(State(pool): State<DbPool>, _: Authorized<perm::ManageUsers>, Path(id): Path<UserId>, ValidatedJson(req): ValidatedJson<AssignRoleRequest>) -> impl IntoResponse {
+    match user_service::assign_role(&pool, id, req.role).await {
+        Ok(user) => (StatusCode::OK, Json(user)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/{id}/warehouses",
+    tag = "users",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, description = "OK", body = UserWarehousesDto),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+    )
+)]
+pub async fn get_user_warehouses(State(pool): State<DbPool>, _: Authorized<perm::ManageUsers>, Path(id): Path<UserId>) -> impl IntoResponse {
+    match user_service::get_warehouses(&pool, id).await {
+        Ok(warehouses) => (StatusCode::OK, Json(warehouses)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/users/{id}/warehouses",
+    tag = "users",
+    params(("id" = i32, Path)),
+    request_body = AssignWarehousesRequest,
+    responses(
+        (status = 200, description = "OK", body = UserWarehousesDto),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 422, description = "Invalid field", body = ErrorBody),
+    )
+)]
+pub async fn assign_user_warehouses(State(pool): State<DbPool>, _: Authorized<perm::ManageUsers>, Path(id): Path<UserId>, ValidatedJson(req): ValidatedJson<AssignWarehousesRequest>) -> impl IntoResponse {
+    match user_service::assign_warehouses(&pool, id, req.warehouse_ids).await {
+        Ok(warehouses) => (StatusCode::OK, Json(warehouses)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/roles",
+    tag = "users",
+    responses(
+        (status = 200, description = "Every role and the permissions it grants", body = Vec<RoleDto>),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+    )
+)]
+pub async fn list_roles(_: Authorized<perm::ManageUsers>) -> impl IntoResponse {
+    (StatusCode::OK, Json(user_service::list_roles())).into_response()
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/{id}/sessions",
+    tag = "users",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, description = "Every session revoked; the user's tokens stop working immediately", body = RevokedSessionsDto),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+    )
+)]
+pub async fn revoke_user_sessions(State(pool): State<DbPool>, _: Authorized<perm::ManageUsers>, Path(id): Path<UserId>) -> impl IntoResponse {
+    match user_service::revoke_sessions(&pool, id).await {
+        Ok(revoked) => (StatusCode::OK, Json(revoked)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/{id}/unlock",
+    tag = "users",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 204, description = "Failed login attempts cleared; the user can log in again right away"),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+    )
+)]
+pub async fn unlock_user(State(pool): State<DbPool>, _: Authorized<perm::ManageUsers>, Path(id): Path<UserId>) -> impl IntoResponse {
+    match login_throttle_service::unlock_user(&pool, id).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => e.into_response(),
     }
 }
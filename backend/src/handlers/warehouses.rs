@@ -1,44 +1,100 @@
-use axum::{extract::{State, Path, Json}, http::StatusCode, response::IntoResponse};
-use crate::dto::warehouse_dto::{CreateWarehouseRequest, UpdateWarehouseRequest};
+use axum::{extract::{State, Json}, http::StatusCode, response::IntoResponse};
+use crate::dto::list_dto::{ListParams, Page};
+use crate::dto::warehouse_dto::{WarehouseFilter, CreateWarehouseRequest, UpdateWarehouseRequest, WarehouseDto};
 use crate::repository::warehouse_repo;
+use crate::models::ids::WarehouseId;
+use crate::middleware::{perm, ListQuery, Authorized, ValidatedJson, Path};
+use crate::error::{AppError, ErrorBody};
 use crate::config::database::DbPool;
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/warehouses",
+    tag = "warehouses",
+    params(ListParams, WarehouseFilter),
+    responses(
+        (status = 200, description = "OK", body = Page<WarehouseDto>),
+        (status = 400, description = "Unknown sort field or malformed filter", body = ErrorBody),
+    )
+)]
 pub async fn list_warehouses(
     State(pool): State<DbPool>,
+    ListQuery(params, filter): ListQuery<WarehouseFilter>,
 ) -> impl IntoResponse {
-    match warehouse_repo::get_all(&pool).await {
+    match warehouse_repo::get_all(&pool, &params, &filter).await {
         Ok(warehouses) => (StatusCode::OK, Json(warehouses)).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => AppError::from(e).into_response(),
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/v1/warehouses",
+    tag = "warehouses",
+    request_body = CreateWarehouseRequest,
+    responses(
+        (status = 201, description = "Created", body = WarehouseDto),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 422, description = "Invalid field", body = ErrorBody),
+    )
+)]
 pub async fn create_warehouse(
     State(pool): State<DbPool>,
-    Json(req): Json<CreateWarehouseRequest>,
+    _: Authorized<perm::EditCatalog>,
+    ValidatedJson(req): ValidatedJson<CreateWarehouseRequest>,
 ) -> impl IntoResponse {
     match warehouse_repo::create(&pool, req).await {
         Ok(warehouse) => (StatusCode::CREATED, Json(warehouse)).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => AppError::from(e).into_response(),
     }
 }
 
+#[utoipa::path(
+    put,
+    path = "/api/v1/warehouses/{id}",
+    tag = "warehouses",
+    params(("id" = i32, Path)),
+    request_body = UpdateWarehouseRequest,
+    responses(
+        (status = 200, description = "OK", body = WarehouseDto),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 422, description = "Invalid field", body = ErrorBody),
+    )
+)]
 pub async fn update_warehouse(
     State(pool): State<DbPool>,
-    Path(id): Path<i32>,
-    Json(req): Json<UpdateWarehouseRequest>,
+    _: Authorized<perm::EditCatalog>,
+    Path(id): Path<WarehouseId>,
+    ValidatedJson(req): ValidatedJson<UpdateWarehouseRequest>,
 ) -> impl IntoResponse {
     match warehouse_repo::update(&pool, id, req).await {
-        Ok(warehouse) => (StatusCode::OK, Json(warehouse)).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Ok(Some(warehouse)) => (StatusCode::OK, Json(warehouse)).into_response(),
+        Ok(None) => AppError::NotFound(format!("Warehouse {id}")).into_response(),
+        Err(e) => AppError::from(e).into_response(),
     }
 }
 
+#[utoipa::path(
+    delete,
+    path = "/api/v1/warehouses/{id}",
+    tag = "warehouses",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 204, description = "Deleted"),
+        (status = 403, description = "Missing permission", body = ErrorBody),
+        (status = 404, description = "Not found", body = ErrorBody),
+        (status = 422, description = "Still referenced by stock or documents", body = ErrorBody),
+    )
+)]
 pub async fn delete_warehouse(
     State(pool): State<DbPool>,
-    Path(id): Path<i32>,
+    _: Authorized<perm::EditCatalog>,
+    Path(id): Path<WarehouseId>,
 ) -> impl IntoResponse {
     match warehouse_repo::delete(&pool, id).await {
-        Ok(_) => StatusCode::NO_CONTENT.into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => AppError::NotFound(format!("Warehouse {id}")).into_response(),
+        Err(e) => AppError::from(e).into_response(),
     }
 }
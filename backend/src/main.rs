@@ -11,6 +11,8 @@ mod handlers;
 mod repository;
 mod dto;
 mod router;
+#[cfg(test)]
+mod test_support;
 
 use tokio::net::TcpListener;
 
@@ -41,7 +43,7 @@ async fn main() {
         .await
         .unwrap_or_else(|e| panic!("Failed to bind to {addr}: {e}"));
 
-    axum::serve(listener, app.into_make_service())
+    axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>())
         .await
         .unwrap_or_else(|e| panic!("Server error: {e}"));
 }
\ No newline at end of file
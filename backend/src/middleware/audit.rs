@@ -0,0 +1,106 @@
+// src/middleware/audit.rs
+use std::net::SocketAddr;
+
+use axum::{
+    extract::{ConnectInfo, Request},
+    http::HeaderValue,
+    middleware::Next,
+    response::Response,
+};
+use sqlx::PgConnection;
+
+use crate::{models::ids::{ApiKeyId, UserId}, utils::token};
+
+/// Who is behind the current request, as recorded in the audit log.
+#[derive(Debug, Clone, Default)]
+pub struct AuditContext {
+    pub actor_id: Option<UserId>,
+    pub api_key_id: Option<ApiKeyId>,
+    pub request_id: String,
+    pub client_ip: Option<String>,
+}
+
+tokio::task_local! {
+    static AUDIT_CONTEXT: AuditContext;
+}
+
+/// The context of the request being handled on this task, if any.
+pub fn current() -> Option<AuditContext> {
+    AUDIT_CONTEXT.try_with(|ctx| ctx.clone()).ok()
+}
+
+/// Runs `fut` with `ctx` as the current context. `require_auth` uses this to
+/// add the actor once it is known.
+pub async fn scope<F: std::future::Future>(ctx: AuditContext, fut: F) -> F::Output {
+    AUDIT_CONTEXT.scope(ctx, fut).await
+}
+
+/// Copies the current context into the transaction's `app.*` settings, where
+/// the audit triggers read it; see `database::begin`. The settings end with
+/// the transaction. Work done outside a request gets empty settings.
+pub async fn apply(conn: &mut PgConnection) -> Result<(), sqlx::Error> {
+    let ctx = current().unwrap_or_default();
+    sqlx::query!(
+        "SELECT set_config('app.actor_id', $1, true) AS actor_id, set_config('app.api_key_id', $2, true) AS api_key_id,
+            set_config('app.request_id', $3, true) AS request_id, set_config('app.client_ip', $4, true) AS client_ip",
+        ctx.actor_id.map(|id| id.to_string()).unwrap_or_default(),
+        ctx.api_key_id.map(|id| id.to_string()).unwrap_or_default(),
+        ctx.request_id,
+        ctx.client_ip.unwrap_or_default(),
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(())
+}
+
+/// Gives every request an id (the caller's `X-Request-Id` if it sent one),
+/// works out the client IP and makes both current for the request. The id is
+/// echoed back in the response's `X-Request-Id`.
+///
+/// The IP is the peer address, or taken from `X-Forwarded-For` when the
+/// server sits behind reverse proxies (see [`client_ip`]).
+pub async fn audit_context(req: Request, next: Next) -> Response {
+    let request_id = req
+        .headers()
+        .get("x-request-id")
+        .and_then(|h| h.to_str().ok())
+        .filter(|id| !id.is_empty() && id.len() <= 64)
+        .map(str::to_string)
+        .unwrap_or_else(|| token::generate()[..16].to_string());
+
+    let ctx = AuditContext {
+        actor_id: None,
+        api_key_id: None,
+        request_id: request_id.clone(),
+        client_ip: client_ip(&req),
+    };
+    let mut response = scope(ctx, next.run(req)).await;
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert("x-request-id", value);
+    }
+    response
+}
+
+/// `TRUST_PROXY` is the number of reverse proxies in front of the server
+/// (`true` meaning one). Each appends the address it was reached from to
+/// `X-Forwarded-For`, so the client is that many entries from the right;
+/// anything further left was sent by the client and could be forged.
+pub fn client_ip(req: &Request) -> Option<String> {
+    let trusted_proxies = match std::env::var("TRUST_PROXY").as_deref() {
+        Ok("true") => 1,
+        Ok(n) => n.parse::<usize>().unwrap_or(0),
+        Err(_) => 0,
+    };
+    let forwarded = req
+        .headers()
+        .get("x-forwarded-for")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.rsplit(',').nth(trusted_proxies.checked_sub(1)?))
+        .map(|ip| ip.trim().to_string())
+        .filter(|ip| !ip.is_empty());
+    forwarded.or_else(|| {
+        req.extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip().to_string())
+    })
+}
@@ -1,17 +1,102 @@
 // src/middleware/auth.rs
 use axum::{
-    extract::{Request, State},
-    http::header::AUTHORIZATION,
+    async_trait,
+    extract::{FromRequestParts, Request, State},
+    http::{header::AUTHORIZATION, request::Parts},
     middleware::Next,
     response::Response,
 };
 use sqlx::PgPool;
 
-use crate::{error::Result, utils::jwt::verify_jwt};
+use crate::{
+    error::{AppError, Result},
+    middleware::audit::{self, AuditContext},
+    models::{ids::{ApiKeyId, SessionId, UserId, WarehouseId}, role::{Permission, Role}},
+    repository::api_key_repo,
+    utils::{jwt::verify_jwt, token},
+};
+
+/// API keys start with this, which is how a bearer key is told apart from a JWT.
+pub const API_KEY_PREFIX: &str = "sm_";
 
+/// The caller authenticated by `require_auth`. Handlers behind the middleware
+/// take it as an extractor to learn who is acting.
 #[derive(Clone)]
 pub struct AuthUser {
-    pub id: uuid::Uuid,
+    /// For API keys, the admin who created the key
+    pub id: UserId,
+    pub credential: Credential,
+    pub role: Role,
+    pub warehouses: WarehouseScope,
+}
+
+/// How the caller authenticated.
+#[derive(Debug, Clone)]
+pub enum Credential {
+    /// A user's access token, tied to a login session
+    Session(SessionId),
+    /// An API key, which may only use the permissions in its scopes
+    ApiKey { id: ApiKeyId, scopes: Vec<Permission> },
+}
+
+impl AuthUser {
+    /// Whether the caller may do what `permission` guards: the role must grant
+    /// it and, for API keys, so must the key's scopes.
+    pub fn can(&self, permission: Permission) -> bool {
+        self.role.grants(permission)
+            && match &self.credential {
+                Credential::Session(_) => true,
+                Credential::ApiKey { scopes, .. } => scopes.contains(&permission),
+            }
+    }
+}
+
+/// The warehouses a caller may see and post to.
+#[derive(Debug, Clone)]
+pub enum WarehouseScope {
+    /// Admins are not limited to assigned warehouses
+    All,
+    Only(Vec<WarehouseId>),
+}
+
+impl WarehouseScope {
+    /// Documents without a warehouse are only visible to unrestricted callers.
+    pub fn allows(&self, warehouse_id: Option<WarehouseId>) -> bool {
+        match self {
+            WarehouseScope::All => true,
+            WarehouseScope::Only(ids) => warehouse_id.is_some_and(|id| ids.contains(&id)),
+        }
+    }
+
+    /// Refuses to post to a warehouse the caller is not assigned to.
+    pub fn ensure(&self, warehouse_id: WarehouseId) -> Result<()> {
+        if self.allows(Some(warehouse_id)) {
+            Ok(())
+        } else {
+            Err(AppError::WarehouseNotAssigned(warehouse_id))
+        }
+    }
+
+    /// For binding as a `$n::INTEGER[]` filter, where NULL means unrestricted.
+    pub fn ids(&self) -> Option<&[WarehouseId]> {
+        match self {
+            WarehouseScope::All => None,
+            WarehouseScope::Only(ids) => Some(ids),
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AuthUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self> {
+        parts
+            .extensions
+            .get::<AuthUser>()
+            .cloned()
+            .ok_or(AppError::Unauthorized)
+    }
 }
 
 pub async fn require_auth(
@@ -19,26 +104,100 @@ pub async fn require_auth(
     mut req: Request,
     next: Next,
 ) -> Result<Response> {
-    let auth_header = req
-        .headers()
+    let headers = req.headers();
+    let api_key = headers.get("x-api-key").and_then(|h| h.to_str().ok());
+    let bearer = headers
         .get(AUTHORIZATION)
         .and_then(|h| h.to_str().ok())
-        .ok_or(crate::error::AppError::Unauthorized)?;
+        .and_then(|h| h.strip_prefix("Bearer "));
+
+    let (user_id, credential, key_warehouse) = match (api_key, bearer) {
+        (Some(key), _) | (None, Some(key)) if key.starts_with(API_KEY_PREFIX) => {
+            let grant = api_key_repo::authenticate(&pool, &token::hash(key))
+                .await?
+                .ok_or(AppError::Unauthorized)?;
+            let scopes = grant.scopes.iter().filter_map(|s| Permission::parse(s)).collect();
+            (grant.created_by, Credential::ApiKey { id: grant.id, scopes }, grant.warehouse_id)
+        }
+        (None, Some(jwt)) => {
+            let claims = verify_jwt(jwt).map_err(|_| AppError::Unauthorized)?;
+            (claims.sub, Credential::Session(claims.sid), None)
+        }
+        _ => return Err(AppError::Unauthorized),
+    };
+    let session_id = match &credential {
+        Credential::Session(id) => Some(*id),
+        Credential::ApiKey { .. } => None,
+    };
 
-    let token = auth_header.strip_prefix("Bearer ").ok_or(crate::error::AppError::Unauthorized)?;
-    let claims = verify_jwt(token)?;
+    // The user may have been deleted or logged out since the token was issued,
+    // and role and warehouses are read on every request so changes take
+    // effect immediately
+    let row = sqlx::query!(
+        r#"SELECT role, ARRAY(SELECT warehouse_id FROM user_warehouses WHERE user_id = users.id) AS "warehouses!: Vec<WarehouseId>"
+        FROM users
+        WHERE id = $1
+          AND ($2::INTEGER IS NULL OR EXISTS (
+              SELECT 1 FROM sessions WHERE sessions.id = $2 AND sessions.user_id = users.id AND sessions.revoked_at IS NULL))"#,
+        user_id as _, session_id as _
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(AppError::Sqlx)?
+    .ok_or(AppError::Unauthorized)?;
+    let role = Role::parse(&row.role).ok_or(AppError::Unauthorized)?;
+    let warehouses = match (role, key_warehouse) {
+        (Role::Admin, None) => WarehouseScope::All,
+        (Role::Admin, Some(id)) => WarehouseScope::Only(vec![id]),
+        (_, None) => WarehouseScope::Only(row.warehouses),
+        // A key limited to a warehouse its creator has since lost sees nothing
+        (_, Some(id)) => WarehouseScope::Only(row.warehouses.into_iter().filter(|w| *w == id).collect()),
+    };
+
+    let ctx = AuditContext {
+        actor_id: Some(user_id),
+        api_key_id: match &credential {
+            Credential::ApiKey { id, .. } => Some(*id),
+            Credential::Session(_) => None,
+        },
+        ..audit::current().unwrap_or_default()
+    };
+    req.extensions_mut().insert(AuthUser { id: user_id, credential, role, warehouses });
+    Ok(audit::scope(ctx, next.run(req)).await)
+}
 
-    // Optional: verify user still exists
-    let user_exists: (bool,) = sqlx::query_as("SELECT EXISTS(SELECT 1 FROM users WHERE id = $1)")
-        .bind(claims.sub)
-        .fetch_one(&pool)
-        .await
-        .map_err(crate::error::AppError::Sqlx)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    if !user_exists.0 {
-        return Err(crate::error::AppError::Unauthorized.into());
+    fn api_key(role: Role, scopes: Vec<Permission>) -> AuthUser {
+        AuthUser { id: UserId(1), credential: Credential::ApiKey { id: ApiKeyId(1), scopes }, role, warehouses: WarehouseScope::All }
     }
 
-    req.extensions_mut().insert(AuthUser { id: claims.sub });
-    Ok(next.run(req).await)
-}
\ No newline at end of file
+    #[test]
+    fn api_keys_are_limited_to_their_scopes() {
+        let key = api_key(Role::Admin, vec![Permission::ViewReports]);
+        assert!(key.can(Permission::ViewReports));
+        assert!(!key.can(Permission::EditCatalog));
+
+        let session = AuthUser { credential: Credential::Session(SessionId(1)), ..key };
+        assert!(session.can(Permission::EditCatalog));
+    }
+
+    #[test]
+    fn api_key_scopes_cannot_exceed_the_creators_role() {
+        let key = api_key(Role::Manager, vec![Permission::ManageUsers, Permission::EditCatalog]);
+        assert!(!key.can(Permission::ManageUsers));
+        assert!(key.can(Permission::EditCatalog));
+    }
+
+    #[test]
+    fn restricted_scopes_only_allow_their_warehouses() {
+        let scope = WarehouseScope::Only(vec![WarehouseId(1)]);
+        assert!(scope.allows(Some(WarehouseId(1))));
+        assert!(!scope.allows(Some(WarehouseId(2))));
+        assert!(!scope.allows(None));
+        assert!(matches!(scope.ensure(WarehouseId(2)), Err(AppError::WarehouseNotAssigned(WarehouseId(2)))));
+        assert!(WarehouseScope::All.allows(None));
+    }
+}
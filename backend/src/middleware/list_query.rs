@@ -0,0 +1,21 @@
+// src/middleware/list_query.rs
+use axum::{async_trait, extract::{FromRequestParts, Query}, http::request::Parts};
+use serde::de::DeserializeOwned;
+
+use crate::dto::list_dto::ListParams;
+use crate::error::AppError;
+
+/// Query string of a list endpoint: the shared paging parameters plus the
+/// endpoint's filters and sort field `F`, read from the same query.
+pub struct ListQuery<F>(pub ListParams, pub F);
+
+#[async_trait]
+impl<S: Send + Sync, F: DeserializeOwned + Send> FromRequestParts<S> for ListQuery<F> {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, AppError> {
+        let Query(params) = Query::<ListParams>::from_request_parts(parts, state).await?;
+        let Query(filter) = Query::<F>::from_request_parts(parts, state).await?;
+        Ok(ListQuery(params, filter))
+    }
+}
@@ -1,3 +1,13 @@
 // src/middleware/mod.rs
+pub mod audit;
 pub mod auth;
-pub use auth::{require_auth, AuthUser};
\ No newline at end of file
+pub mod list_query;
+pub mod params;
+pub mod permission;
+pub mod validation;
+pub use audit::audit_context;
+pub use auth::{require_auth, AuthUser, Credential, WarehouseScope};
+pub use list_query::ListQuery;
+pub use params::{Path, Query};
+pub use permission::{perm, Authorized};
+pub use validation::ValidatedJson;
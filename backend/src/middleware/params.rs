@@ -0,0 +1,36 @@
+// src/middleware/params.rs
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+use serde::de::DeserializeOwned;
+
+use crate::error::AppError;
+
+// axum's own `Query` and `Path` answer a malformed parameter with a plain-text
+// 400. These wrap them so the rejection goes through `AppError` and comes
+// back as the usual JSON error body.
+
+/// Query string deserialized into `T`; a malformed one is a 400 `invalid_query`.
+pub struct Query<T>(pub T);
+
+#[async_trait]
+impl<S: Send + Sync, T: DeserializeOwned + Send> FromRequestParts<S> for Query<T> {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, AppError> {
+        let axum::extract::Query(value) = axum::extract::Query::<T>::from_request_parts(parts, state).await?;
+        Ok(Query(value))
+    }
+}
+
+/// Path parameters deserialized into `T`; one that does not parse, such as a
+/// non-numeric id, is a 400 `invalid_path`.
+pub struct Path<T>(pub T);
+
+#[async_trait]
+impl<S: Send + Sync, T: DeserializeOwned + Send> FromRequestParts<S> for Path<T> {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, AppError> {
+        let axum::extract::Path(value) = axum::extract::Path::<T>::from_request_parts(parts, state).await?;
+        Ok(Path(value))
+    }
+}
@@ -0,0 +1,56 @@
+// src/middleware/permission.rs
+use std::marker::PhantomData;
+
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+
+use crate::{error::{AppError, Result}, middleware::AuthUser, models::role::Permission};
+
+/// Marker for a permission a handler can demand through [`Authorized`].
+pub trait RequiredPermission {
+    const PERMISSION: Permission;
+}
+
+macro_rules! permission_markers {
+    ($($name:ident),* $(,)?) => {
+        /// One marker type per [`Permission`], for use as `Authorized<perm::EditCatalog>`.
+        pub mod perm {
+            $(
+                pub struct $name;
+
+                impl super::RequiredPermission for $name {
+                    const PERMISSION: super::Permission = super::Permission::$name;
+                }
+            )*
+        }
+    };
+}
+
+permission_markers!(
+    ManageUsers,
+    EditCatalog,
+    ValidateReceipts,
+    ProcessDeliveries,
+    ProcessTransfers,
+    PostAdjustments,
+    ReverseDocuments,
+    ViewReports,
+    ViewAuditLog,
+);
+
+/// The authenticated caller, provided they may use `P` (see [`AuthUser::can`]). Handlers declare
+/// what they need by taking this instead of [`AuthUser`]; callers without the
+/// permission get 403.
+pub struct Authorized<P>(pub AuthUser, pub PhantomData<P>);
+
+#[async_trait]
+impl<S: Send + Sync, P: RequiredPermission> FromRequestParts<S> for Authorized<P> {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self> {
+        let user = AuthUser::from_request_parts(parts, state).await?;
+        if !user.can(P::PERMISSION) {
+            return Err(AppError::Forbidden(P::PERMISSION.as_str().to_string()));
+        }
+        Ok(Authorized(user, PhantomData))
+    }
+}
@@ -0,0 +1,22 @@
+// src/middleware/validation.rs
+use axum::{async_trait, extract::{FromRequest, Request}, Json};
+use serde::de::DeserializeOwned;
+use validator::Validate;
+
+use crate::error::AppError;
+
+/// `Json<T>` that also runs `T`'s validation rules. A malformed body or a
+/// body that breaks a rule is answered with an [`AppError`], the latter as
+/// 422 listing each offending field, before the handler runs.
+pub struct ValidatedJson<T>(pub T);
+
+#[async_trait]
+impl<S: Send + Sync, T: DeserializeOwned + Validate> FromRequest<S> for ValidatedJson<T> {
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, AppError> {
+        let Json(value) = Json::<T>::from_request(req, state).await?;
+        value.validate()?;
+        Ok(ValidatedJson(value))
+    }
+}
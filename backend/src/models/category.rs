@@ -1,9 +0,0 @@
-// src/models/category.rs
-use serde::{Deserialize, Serialize};
-use uuid::Uuid;
-
-#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
-pub struct Category {
-    pub id: Uuid,
-    pub name: String,
-}
\ No newline at end of file
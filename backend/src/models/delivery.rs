@@ -1,21 +1,50 @@
 // src/models/delivery.rs
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
 
-#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
-pub struct Delivery {
-    pub id: Uuid,
-    pub customer_name: String,
-    pub warehouse_id: Uuid,
-    pub status: String,                     // draft / picked / packed / delivered / cancelled
-    pub created_by: Uuid,
-    pub created_at: chrono::DateTime<chrono::Utc>,
+/// Lifecycle of a delivery. Picking reserves stock, delivering consumes it,
+/// cancelling releases whatever is still reserved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    Draft,
+    Picked,
+    Packed,
+    Delivered,
+    Cancelled,
 }
 
-#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
-pub struct DeliveryItem {
-    pub id: Uuid,
-    pub delivery_id: Uuid,
-    pub product_id: Uuid,
-    pub quantity: rust_decimal::Decimal,
-}
\ No newline at end of file
+impl DeliveryStatus {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            DeliveryStatus::Draft => "draft",
+            DeliveryStatus::Picked => "picked",
+            DeliveryStatus::Packed => "packed",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(status: &str) -> Option<Self> {
+        match status {
+            "draft" => Some(DeliveryStatus::Draft),
+            "picked" => Some(DeliveryStatus::Picked),
+            "packed" => Some(DeliveryStatus::Packed),
+            "delivered" => Some(DeliveryStatus::Delivered),
+            "cancelled" => Some(DeliveryStatus::Cancelled),
+            _ => None,
+        }
+    }
+
+    pub fn can_transition_to(self, next: DeliveryStatus) -> bool {
+        use DeliveryStatus::*;
+        matches!(
+            (self, next),
+            (Draft, Picked) | (Picked, Packed) | (Packed, Delivered) | (Draft | Picked | Packed, Cancelled)
+        )
+    }
+
+    /// Whether the delivery's items are currently reserved in `product_stock`.
+    pub fn holds_reservation(self) -> bool {
+        matches!(self, DeliveryStatus::Picked | DeliveryStatus::Packed)
+    }
+}
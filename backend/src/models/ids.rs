@@ -0,0 +1,44 @@
+//! Typed identifiers. Every table keys on a SERIAL `INTEGER`; wrapping the
+//! value per entity stops a warehouse id being passed where a product id is
+//! expected, while staying a plain integer in JSON, SQL and JWTs.
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+macro_rules! id_type {
+    ($($name:ident),* $(,)?) => {$(
+        #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, sqlx::Type, ToSchema)]
+        #[serde(transparent)]
+        #[sqlx(transparent)]
+        #[schema(value_type = i32)]
+        pub struct $name(pub i32);
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+
+        impl From<i32> for $name {
+            fn from(id: i32) -> Self {
+                $name(id)
+            }
+        }
+    )*};
+}
+
+id_type!(
+    UserId,
+    WarehouseId,
+    CategoryId,
+    ProductId,
+    ReceiptId,
+    ReceiptItemId,
+    DeliveryId,
+    DeliveryItemId,
+    TransferId,
+    TransferItemId,
+    AdjustmentId,
+    StockLedgerId,
+    SessionId,
+    ApiKeyId,
+);
@@ -1,18 +1,8 @@
 // src/models/mod.rs
-pub mod user;
-pub mod warehouse;
-pub mod category;
-pub mod product;
+pub mod ids;
+pub mod role;
+pub mod user_token;
 pub mod product_stock;
-pub mod stock_ledger;
 pub mod receipt;
 pub mod delivery;
 pub mod transfer;
-pub mod adjustment;
-
-pub use user::User;
-pub use warehouse::Warehouse;
-pub use category::Category;
-pub use product::Product;
-pub use product_stock::ProductStock;
-pub use stock_ledger::StockLedger;
\ No newline at end of file
@@ -1,15 +0,0 @@
-// src/models/product.rs
-use serde::{Deserialize, Serialize};
-use uuid::Uuid;
-
-#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
-pub struct Product {
-    pub id: Uuid,
-    pub sku: String,
-    pub name: String,
-    pub category_id: Uuid,
-    pub uom: String,                    // Unit of Measure: Kg, Pcs, Liters, etc.
-    pub reorder_threshold: Option<i32>,
-    pub created_at: chrono::DateTime<chrono::Utc>,
-    pub updated_at: chrono::DateTime<chrono::Utc>,
-}
\ No newline at end of file
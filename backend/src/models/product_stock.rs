@@ -1,10 +1,33 @@
 // src/models/product_stock.rs
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
+use utoipa::ToSchema;
+use super::ids::{ProductId, WarehouseId};
 
-#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
 pub struct ProductStock {
-    pub product_id: Uuid,
-    pub warehouse_id: Uuid,
-    pub quantity: rust_decimal::Decimal,  // use Decimal for precision (Kg, Liters)
-}
\ No newline at end of file
+    pub product_id: ProductId,
+    pub warehouse_id: WarehouseId,
+    pub quantity: i32,
+    /// Held by picked deliveries; not available to other documents.
+    pub reserved_quantity: i32,
+}
+
+/// How stock-decrementing operations behave when they would take more than is available.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NegativeStockPolicy {
+    Forbid,
+    Warn,
+    Allow,
+}
+
+impl NegativeStockPolicy {
+    pub fn parse(policy: &str) -> Option<Self> {
+        match policy {
+            "forbid" => Some(NegativeStockPolicy::Forbid),
+            "warn" => Some(NegativeStockPolicy::Warn),
+            "allow" => Some(NegativeStockPolicy::Allow),
+            _ => None,
+        }
+    }
+}
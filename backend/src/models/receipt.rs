@@ -1,22 +1,22 @@
 // src/models/receipt.rs
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
 
-#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
-pub struct Receipt {
-    pub id: Uuid,
-    pub supplier_name: String,
-    pub warehouse_id: Uuid,
-    pub status: String,                     // draft / validated / cancelled
-    pub created_by: Uuid,
-    pub created_at: chrono::DateTime<chrono::Utc>,
+/// Validating a draft posts its lines to stock; reversing a validated receipt
+/// takes them back out and cancels it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiptStatus {
+    Draft,
+    Validated,
+    Cancelled,
 }
 
-#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
-pub struct ReceiptItem {
-    pub id: Uuid,
-    pub receipt_id: Uuid,
-    pub product_id: Uuid,
-    pub quantity: rust_decimal::Decimal,
-    pub expiry_date: Option<chrono::NaiveDate>,
-}
\ No newline at end of file
+impl ReceiptStatus {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            ReceiptStatus::Draft => "draft",
+            ReceiptStatus::Validated => "validated",
+            ReceiptStatus::Cancelled => "cancelled",
+        }
+    }
+}
@@ -0,0 +1,150 @@
+// src/models/role.rs
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// A user's role. Stored as lowercase text in `users.role`; what each role
+/// may do is decided here rather than in the database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Admin,
+    Manager,
+    Operator,
+}
+
+/// Something a handler can require of its caller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    /// Create, edit and delete users and assign their roles
+    ManageUsers,
+    /// Create, edit and delete products, categories and warehouses
+    EditCatalog,
+    ValidateReceipts,
+    /// Pick, pack, deliver and cancel deliveries
+    ProcessDeliveries,
+    /// Ship and receive transfers
+    ProcessTransfers,
+    PostAdjustments,
+    /// Reverse posted receipts, deliveries, transfers and adjustments
+    ReverseDocuments,
+    /// Dashboard, move history and the stock ledger
+    ViewReports,
+    ViewAuditLog,
+}
+
+impl Role {
+    pub const ALL: [Role; 3] = [Role::Admin, Role::Manager, Role::Operator];
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Role::Admin => "admin",
+            Role::Manager => "manager",
+            Role::Operator => "operator",
+        }
+    }
+
+    pub fn parse(role: &str) -> Option<Self> {
+        match role {
+            "admin" => Some(Role::Admin),
+            "manager" => Some(Role::Manager),
+            "operator" => Some(Role::Operator),
+            _ => None,
+        }
+    }
+
+    pub fn permissions(self) -> &'static [Permission] {
+        use Permission::*;
+        match self {
+            Role::Admin => &[
+                ManageUsers,
+                EditCatalog,
+                ValidateReceipts,
+                ProcessDeliveries,
+                ProcessTransfers,
+                PostAdjustments,
+                ReverseDocuments,
+                ViewReports,
+                ViewAuditLog,
+            ],
+            Role::Manager => &[
+                EditCatalog,
+                ValidateReceipts,
+                ProcessDeliveries,
+                ProcessTransfers,
+                PostAdjustments,
+                ReverseDocuments,
+                ViewReports,
+            ],
+            Role::Operator => &[ValidateReceipts, ProcessDeliveries, ProcessTransfers],
+        }
+    }
+
+    pub fn grants(self, permission: Permission) -> bool {
+        self.permissions().contains(&permission)
+    }
+}
+
+impl Permission {
+    pub const ALL: [Permission; 9] = [
+        Permission::ManageUsers,
+        Permission::EditCatalog,
+        Permission::ValidateReceipts,
+        Permission::ProcessDeliveries,
+        Permission::ProcessTransfers,
+        Permission::PostAdjustments,
+        Permission::ReverseDocuments,
+        Permission::ViewReports,
+        Permission::ViewAuditLog,
+    ];
+
+    pub fn parse(permission: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.as_str() == permission)
+    }
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Permission::ManageUsers => "manage_users",
+            Permission::EditCatalog => "edit_catalog",
+            Permission::ValidateReceipts => "validate_receipts",
+            Permission::ProcessDeliveries => "process_deliveries",
+            Permission::ProcessTransfers => "process_transfers",
+            Permission::PostAdjustments => "post_adjustments",
+            Permission::ReverseDocuments => "reverse_documents",
+            Permission::ViewReports => "view_reports",
+            Permission::ViewAuditLog => "view_audit_log",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operators_only_work_documents() {
+        let granted: Vec<_> = Permission::ALL.into_iter().filter(|&p| Role::Operator.grants(p)).collect();
+        assert_eq!(granted, [Permission::ValidateReceipts, Permission::ProcessDeliveries, Permission::ProcessTransfers]);
+    }
+
+    #[test]
+    fn only_admins_manage_users_and_read_the_audit_log() {
+        for permission in [Permission::ManageUsers, Permission::ViewAuditLog] {
+            assert!(Role::Admin.grants(permission));
+            assert!(!Role::Manager.grants(permission));
+            assert!(!Role::Operator.grants(permission));
+        }
+        assert!(Permission::ALL.into_iter().all(|p| Role::Admin.grants(p)));
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for role in Role::ALL {
+            assert_eq!(Role::parse(role.as_str()), Some(role));
+        }
+        for permission in Permission::ALL {
+            assert_eq!(Permission::parse(permission.as_str()), Some(permission));
+        }
+        assert_eq!(Role::parse("Admin"), None);
+    }
+}
@@ -1,21 +1,24 @@
 // src/models/transfer.rs
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
 
-#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
-pub struct Transfer {
-    pub id: Uuid,
-    pub from_warehouse_id: Uuid,
-    pub to_warehouse_id: Uuid,
-    pub status: String,                     // draft / executed / cancelled
-    pub created_by: Uuid,
-    pub created_at: chrono::DateTime<chrono::Utc>,
+/// Shipping takes the stock out of the source warehouse; it stays in transit
+/// until every shipped unit has been received or written off as a discrepancy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferStatus {
+    Draft,
+    InTransit,
+    Received,
+    Cancelled,
 }
 
-#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
-pub struct TransferItem {
-    pub id: Uuid,
-    pub transfer_id: Uuid,
-    pub product_id: Uuid,
-    pub quantity: rust_decimal::Decimal,
-}
\ No newline at end of file
+impl TransferStatus {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            TransferStatus::Draft => "draft",
+            TransferStatus::InTransit => "in_transit",
+            TransferStatus::Received => "received",
+            TransferStatus::Cancelled => "cancelled",
+        }
+    }
+}
@@ -1,13 +0,0 @@
-// src/models/user.rs
-use serde::{Deserialize, Serialize};
-use uuid::Uuid;
-
-#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
-pub struct User {
-    pub id: Uuid,
-    pub name: String,
-    pub email: String,
-    pub password_hash: String,
-    pub role: String,
-    pub created_at: chrono::DateTime<chrono::Utc>,
-}
\ No newline at end of file
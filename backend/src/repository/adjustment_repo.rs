@@ -1,41 +1,95 @@
-use crate::dto::adjustment_dto::{AdjustmentDto, CreateAdjustmentRequest, UpdateAdjustmentRequest};
+use crate::dto::adjustment_dto::{AdjustmentDto, AdjustmentFilter, CreateAdjustmentRequest, UpdateAdjustmentRequest};
 use crate::config::database::DbPool;
-use sqlx::Error;
+use crate::dto::list_dto::{sort_column, ListParams, Page};
+use crate::models::ids::{AdjustmentId, UserId, WarehouseId};
+use sqlx::{Error, PgConnection};
 
-pub async fn get_all(pool: &DbPool) -> Result<Vec<AdjustmentDto>, Error> {
+/// One page of adjustments in the given warehouses (all when `None`),
+/// filtered and sorted as asked.
+pub async fn get_all(pool: &DbPool, params: &ListParams, filter: &AdjustmentFilter, warehouses: Option<&[WarehouseId]>) -> Result<Page<AdjustmentDto>, Error> {
+    let date_to = filter.date_to.and_then(|date| date.succ_opt());
     let adjustments = sqlx::query_as!(AdjustmentDto,
-        r#"SELECT id, product_id, warehouse_id, user_id, reason, quantity_change, notes, created_at, updated_at FROM adjustments ORDER BY id"#
+        r#"SELECT id AS "id: _", product_id AS "product_id: _", warehouse_id AS "warehouse_id: _", user_id AS "user_id: _", reason, quantity_change, counted_quantity, notes, status, created_at, updated_at
+        FROM adjustments
+        WHERE ($1::INTEGER[] IS NULL OR warehouse_id = ANY($1))
+            AND ($2::TEXT IS NULL OR status = $2)
+            AND ($3::TEXT IS NULL OR reason = $3)
+            AND ($4::INTEGER IS NULL OR product_id = $4)
+            AND ($5::INTEGER IS NULL OR warehouse_id = $5)
+            AND ($6::DATE IS NULL OR created_at >= $6)
+            AND ($7::DATE IS NULL OR created_at < $7)
+        ORDER BY
+            CASE WHEN NOT $9 THEN CASE $8 WHEN 'reason' THEN reason WHEN 'status' THEN status END END,
+            CASE WHEN $9 THEN CASE $8 WHEN 'reason' THEN reason WHEN 'status' THEN status END END DESC,
+            CASE WHEN NOT $9 THEN CASE $8 WHEN 'quantity_change' THEN quantity_change END END,
+            CASE WHEN $9 THEN CASE $8 WHEN 'quantity_change' THEN quantity_change END END DESC,
+            CASE WHEN NOT $9 THEN CASE $8 WHEN 'created_at' THEN created_at END END,
+            CASE WHEN $9 THEN CASE $8 WHEN 'created_at' THEN created_at END END DESC,
+            CASE WHEN $9 THEN id END DESC,
+            id
+        LIMIT $10 OFFSET $11"#,
+        warehouses as _, filter.status, filter.reason, filter.product_id as _, filter.warehouse_id as _, filter.date_from, date_to, sort_column(filter.sort), params.descending(), params.limit(), params.offset()
     )
     .fetch_all(pool)
     .await?;
-    Ok(adjustments)
+    let total = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM adjustments
+        WHERE ($1::INTEGER[] IS NULL OR warehouse_id = ANY($1))
+            AND ($2::TEXT IS NULL OR status = $2)
+            AND ($3::TEXT IS NULL OR reason = $3)
+            AND ($4::INTEGER IS NULL OR product_id = $4)
+            AND ($5::INTEGER IS NULL OR warehouse_id = $5)
+            AND ($6::DATE IS NULL OR created_at >= $6)
+            AND ($7::DATE IS NULL OR created_at < $7)"#,
+        warehouses as _, filter.status, filter.reason, filter.product_id as _, filter.warehouse_id as _, filter.date_from, date_to
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(params.page_of(adjustments, total))
 }
 
-pub async fn create(pool: &DbPool, req: CreateAdjustmentRequest) -> Result<AdjustmentDto, Error> {
+/// Inserts the adjustment with the delta actually applied to stock.
+pub async fn create(conn: &mut PgConnection, req: &CreateAdjustmentRequest, user_id: UserId, quantity_change: i32) -> Result<AdjustmentDto, Error> {
     let rec = sqlx::query_as!(AdjustmentDto,
-        r#"INSERT INTO adjustments (product_id, warehouse_id, user_id, reason, quantity_change, notes) VALUES ($1, $2, $3, $4, $5, $6)
-        RETURNING id, product_id, warehouse_id, user_id, reason, quantity_change, notes, created_at, updated_at"#,
-        req.product_id, req.warehouse_id, req.user_id, req.reason, req.quantity_change, req.notes
+        r#"INSERT INTO adjustments (product_id, warehouse_id, user_id, reason, quantity_change, counted_quantity, notes) VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id AS "id: _", product_id AS "product_id: _", warehouse_id AS "warehouse_id: _", user_id AS "user_id: _", reason, quantity_change, counted_quantity, notes, status, created_at, updated_at"#,
+        req.product_id as _, req.warehouse_id as _, user_id as _, req.reason, quantity_change, req.counted_quantity, req.notes
     )
-    .fetch_one(pool)
+    .fetch_one(conn)
     .await?;
     Ok(rec)
 }
 
-pub async fn update(pool: &DbPool, id: i32, req: UpdateAdjustmentRequest) -> Result<AdjustmentDto, Error> {
+pub async fn update(conn: &mut PgConnection, id: AdjustmentId, req: UpdateAdjustmentRequest) -> Result<AdjustmentDto, Error> {
     let rec = sqlx::query_as!(AdjustmentDto,
-        r#"UPDATE adjustments SET reason = COALESCE($1, reason), quantity_change = COALESCE($2, quantity_change), notes = COALESCE($3, notes), updated_at = NOW()
-        WHERE id = $4 RETURNING id, product_id, warehouse_id, user_id, reason, quantity_change, notes, created_at, updated_at"#,
-        req.reason, req.quantity_change, req.notes, id
+        r#"UPDATE adjustments SET reason = COALESCE($1, reason), notes = COALESCE($2, notes), updated_at = NOW()
+        WHERE id = $3 RETURNING id AS "id: _", product_id AS "product_id: _", warehouse_id AS "warehouse_id: _", user_id AS "user_id: _", reason, quantity_change, counted_quantity, notes, status, created_at, updated_at"#,
+        req.reason, req.notes, id as _
     )
-    .fetch_one(pool)
+    .fetch_one(conn)
     .await?;
     Ok(rec)
 }
 
-pub async fn delete(pool: &DbPool, id: i32) -> Result<(), Error> {
-    sqlx::query!("DELETE FROM adjustments WHERE id = $1", id)
-        .execute(pool)
-        .await?;
-    Ok(())
+/// Loads an adjustment and locks its row until the surrounding transaction ends.
+pub async fn find_for_update(conn: &mut PgConnection, id: AdjustmentId) -> Result<Option<AdjustmentDto>, Error> {
+    let rec = sqlx::query_as!(AdjustmentDto,
+        r#"SELECT id AS "id: _", product_id AS "product_id: _", warehouse_id AS "warehouse_id: _", user_id AS "user_id: _", reason, quantity_change, counted_quantity, notes, status, created_at, updated_at
+        FROM adjustments WHERE id = $1 FOR UPDATE"#,
+        id as _
+    )
+    .fetch_optional(conn)
+    .await?;
+    Ok(rec)
+}
+
+pub async fn set_status(conn: &mut PgConnection, id: AdjustmentId, status: &str) -> Result<AdjustmentDto, Error> {
+    let rec = sqlx::query_as!(AdjustmentDto,
+        r#"UPDATE adjustments SET status = $1, updated_at = NOW()
+        WHERE id = $2 RETURNING id AS "id: _", product_id AS "product_id: _", warehouse_id AS "warehouse_id: _", user_id AS "user_id: _", reason, quantity_change, counted_quantity, notes, status, created_at, updated_at"#,
+        status, id as _
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(rec)
 }
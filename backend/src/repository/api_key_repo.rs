@@ -0,0 +1,72 @@
+use crate::config::database::DbPool;
+use crate::dto::api_key_dto::ApiKeyDto;
+use crate::models::ids::{ApiKeyId, UserId, WarehouseId};
+use chrono::NaiveDateTime;
+use sqlx::{Error, PgExecutor};
+
+/// What the auth middleware needs to know about a presented key.
+#[derive(Debug, sqlx::FromRow)]
+pub struct ApiKeyGrant {
+    pub id: ApiKeyId,
+    pub created_by: UserId,
+    pub scopes: Vec<String>,
+    pub warehouse_id: Option<WarehouseId>,
+}
+
+pub async fn get_all(pool: &DbPool) -> Result<Vec<ApiKeyDto>, Error> {
+    let keys = sqlx::query_as!(ApiKeyDto,
+        r#"SELECT id AS "id: _", name, key_prefix, scopes, warehouse_id AS "warehouse_id: _", created_by AS "created_by: _",
+            expires_at, last_used_at, revoked_at, created_at
+        FROM api_keys ORDER BY id"#
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(keys)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn create<'e, E: PgExecutor<'e>>(
+    executor: E,
+    name: &str,
+    key_prefix: &str,
+    key_hash: &str,
+    scopes: &[String],
+    warehouse_id: Option<WarehouseId>,
+    created_by: UserId,
+    expires_at: Option<NaiveDateTime>,
+) -> Result<ApiKeyDto, Error> {
+    let rec = sqlx::query_as!(ApiKeyDto,
+        r#"INSERT INTO api_keys (name, key_prefix, key_hash, scopes, warehouse_id, created_by, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id AS "id: _", name, key_prefix, scopes, warehouse_id AS "warehouse_id: _", created_by AS "created_by: _",
+            expires_at, last_used_at, revoked_at, created_at"#,
+        name, key_prefix, key_hash, scopes, warehouse_id as _, created_by as _, expires_at
+    )
+    .fetch_one(executor)
+    .await?;
+    Ok(rec)
+}
+
+/// Revokes the key; false if it does not exist or was already revoked.
+pub async fn revoke<'e, E: PgExecutor<'e>>(executor: E, id: ApiKeyId) -> Result<bool, Error> {
+    let result = sqlx::query!(
+        "UPDATE api_keys SET revoked_at = NOW() WHERE id = $1 AND revoked_at IS NULL",
+        id as _
+    )
+    .execute(executor)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Looks up a live (unrevoked, unexpired) key by hash and records the use.
+pub async fn authenticate<'e, E: PgExecutor<'e>>(executor: E, key_hash: &str) -> Result<Option<ApiKeyGrant>, Error> {
+    let rec = sqlx::query_as!(ApiKeyGrant,
+        r#"UPDATE api_keys SET last_used_at = NOW()
+        WHERE key_hash = $1 AND revoked_at IS NULL AND (expires_at IS NULL OR expires_at > NOW())
+        RETURNING id AS "id: _", created_by AS "created_by: _", scopes, warehouse_id AS "warehouse_id: _""#,
+        key_hash
+    )
+    .fetch_optional(executor)
+    .await?;
+    Ok(rec)
+}
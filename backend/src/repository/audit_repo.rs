@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use crate::models::ids::{ApiKeyId, UserId};
+use crate::config::database::DbPool;
+use crate::dto::list_dto::{DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+use sqlx::{Error, PgExecutor};
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AuditEntryDto {
+    pub id: i64,
+    pub occurred_at: chrono::NaiveDateTime,
+    pub actor_id: Option<UserId>,
+    pub api_key_id: Option<ApiKeyId>,
+    /// `create`, `update` or `delete` for row changes; `auth.*` for auth events
+    pub action: String,
+    /// Table name for row changes, e.g. `products`
+    pub entity_type: String,
+    pub entity_id: Option<i32>,
+    #[schema(value_type = Option<Object>)]
+    pub before: Option<serde_json::Value>,
+    #[schema(value_type = Option<Object>)]
+    pub after: Option<serde_json::Value>,
+    pub request_id: Option<String>,
+    pub client_ip: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditFilter {
+    pub actor_id: Option<UserId>,
+    pub action: Option<String>,
+    pub entity_type: Option<String>,
+    pub entity_id: Option<i32>,
+    pub request_id: Option<String>,
+    pub date_from: Option<chrono::NaiveDate>,
+    /// Inclusive: entries from anywhere on this day are returned.
+    pub date_to: Option<chrono::NaiveDate>,
+    /// `next_cursor` from the previous page; omit for the newest entries.
+    pub cursor: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AuditPage {
+    pub entries: Vec<AuditEntryDto>,
+    /// Pass back as `cursor` to fetch the next (older) page; null on the last page.
+    pub next_cursor: Option<i64>,
+}
+
+/// Newest entries first, paged by id like the stock ledger.
+pub async fn get_all(pool: &DbPool, filter: AuditFilter) -> Result<AuditPage, Error> {
+    let limit = filter.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let date_to = filter.date_to.and_then(|date| date.succ_opt());
+    // Fetch one extra row to learn whether another page exists
+    let mut entries = sqlx::query_as!(AuditEntryDto,
+        r#"SELECT id, occurred_at, actor_id AS "actor_id: _", api_key_id AS "api_key_id: _", action, entity_type, entity_id,
+            before, after, request_id, client_ip
+        FROM audit_log
+        WHERE ($1::BIGINT IS NULL OR id < $1)
+            AND ($2::INTEGER IS NULL OR actor_id = $2)
+            AND ($3::TEXT IS NULL OR action = $3)
+            AND ($4::TEXT IS NULL OR entity_type = $4)
+            AND ($5::INTEGER IS NULL OR entity_id = $5)
+            AND ($6::TEXT IS NULL OR request_id = $6)
+            AND ($7::DATE IS NULL OR occurred_at >= $7)
+            AND ($8::DATE IS NULL OR occurred_at < $8)
+        ORDER BY id DESC
+        LIMIT $9"#,
+        filter.cursor, filter.actor_id as _, filter.action, filter.entity_type, filter.entity_id,
+        filter.request_id, filter.date_from, date_to, limit + 1
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let next_cursor = if entries.len() as i64 > limit {
+        entries.truncate(limit as usize);
+        entries.last().map(|entry| entry.id)
+    } else {
+        None
+    };
+    Ok(AuditPage { entries, next_cursor })
+}
+
+/// An entry written by the application rather than a row trigger.
+pub struct AuditEvent<'a> {
+    pub action: &'a str,
+    pub entity_type: &'a str,
+    pub entity_id: Option<i32>,
+    /// Stored as `after`
+    pub details: Option<serde_json::Value>,
+    /// For events before authentication, such as a login, whose actor the
+    /// connection's context does not know yet
+    pub actor_id: Option<UserId>,
+}
+
+/// Appends an event. Request id, client IP and, unless given, the actor come
+/// from the connection's audit context, as for row changes.
+pub async fn record<'e, E: PgExecutor<'e>>(executor: E, event: AuditEvent<'_>) -> Result<(), Error> {
+    sqlx::query!(
+        "INSERT INTO audit_log (actor_id, api_key_id, action, entity_type, entity_id, after, request_id, client_ip)
+        VALUES (
+            COALESCE($1, NULLIF(current_setting('app.actor_id', true), '')::INTEGER),
+            NULLIF(current_setting('app.api_key_id', true), '')::INTEGER,
+            $2, $3, $4, $5,
+            NULLIF(current_setting('app.request_id', true), ''),
+            NULLIF(current_setting('app.client_ip', true), '')
+        )",
+        event.actor_id as _, event.action, event.entity_type, event.entity_id, event.details
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
@@ -1,41 +1,69 @@
-use crate::dto::category_dto::{CategoryDto, CreateCategoryRequest, UpdateCategoryRequest};
-use crate::config::database::DbPool;
+use crate::dto::category_dto::{CategoryDto, CategoryFilter, CreateCategoryRequest, UpdateCategoryRequest};
+use crate::config::database::{self, DbPool};
+use crate::dto::list_dto::{sort_column, search_pattern, ListParams, Page};
+use crate::models::ids::CategoryId;
 use sqlx::Error;
 
-pub async fn get_all(pool: &DbPool) -> Result<Vec<CategoryDto>, Error> {
+/// One page of categories, filtered and sorted as asked.
+pub async fn get_all(pool: &DbPool, params: &ListParams, filter: &CategoryFilter) -> Result<Page<CategoryDto>, Error> {
+    let pattern = search_pattern(filter.q.as_deref());
     let categories = sqlx::query_as!(CategoryDto,
-        r#"SELECT id, name, created_at, updated_at FROM categories ORDER BY id"#
+        r#"SELECT id AS "id: _", name, created_at, updated_at
+        FROM categories
+        WHERE ($1::TEXT IS NULL OR name ILIKE $1)
+        ORDER BY
+            CASE WHEN NOT $3 THEN CASE $2 WHEN 'name' THEN name END END,
+            CASE WHEN $3 THEN CASE $2 WHEN 'name' THEN name END END DESC,
+            CASE WHEN NOT $3 THEN CASE $2 WHEN 'created_at' THEN created_at END END,
+            CASE WHEN $3 THEN CASE $2 WHEN 'created_at' THEN created_at END END DESC,
+            CASE WHEN $3 THEN id END DESC,
+            id
+        LIMIT $4 OFFSET $5"#,
+        pattern, sort_column(filter.sort), params.descending(), params.limit(), params.offset()
     )
     .fetch_all(pool)
     .await?;
-    Ok(categories)
+    let total = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM categories
+        WHERE ($1::TEXT IS NULL OR name ILIKE $1)"#,
+        pattern
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(params.page_of(categories, total))
 }
 
 pub async fn create(pool: &DbPool, req: CreateCategoryRequest) -> Result<CategoryDto, Error> {
+    let mut tx = database::begin(pool).await?;
     let rec = sqlx::query_as!(CategoryDto,
         r#"INSERT INTO categories (name) VALUES ($1)
-        RETURNING id, name, created_at, updated_at"#,
+        RETURNING id AS "id: _", name, created_at, updated_at"#,
         req.name
     )
-    .fetch_one(pool)
+    .fetch_one(&mut *tx)
     .await?;
+    tx.commit().await?;
     Ok(rec)
 }
 
-pub async fn update(pool: &DbPool, id: i32, req: UpdateCategoryRequest) -> Result<CategoryDto, Error> {
+pub async fn update(pool: &DbPool, id: CategoryId, req: UpdateCategoryRequest) -> Result<Option<CategoryDto>, Error> {
+    let mut tx = database::begin(pool).await?;
     let rec = sqlx::query_as!(CategoryDto,
         r#"UPDATE categories SET name = COALESCE($1, name), updated_at = NOW()
-        WHERE id = $2 RETURNING id, name, created_at, updated_at"#,
-        req.name, id
+        WHERE id = $2 RETURNING id AS "id: _", name, created_at, updated_at"#,
+        req.name, id as _
     )
-    .fetch_one(pool)
+    .fetch_optional(&mut *tx)
     .await?;
+    tx.commit().await?;
     Ok(rec)
 }
 
-pub async fn delete(pool: &DbPool, id: i32) -> Result<(), Error> {
-    sqlx::query!("DELETE FROM categories WHERE id = $1", id)
-        .execute(pool)
+pub async fn delete(pool: &DbPool, id: CategoryId) -> Result<bool, Error> {
+    let mut tx = database::begin(pool).await?;
+    let result = sqlx::query!("DELETE FROM categories WHERE id = $1", id as _)
+        .execute(&mut *tx)
         .await?;
-    Ok(())
+    tx.commit().await?;
+    Ok(result.rows_affected() > 0)
 }
@@ -0,0 +1,81 @@
+use crate::config::database::DbPool;
+use crate::dto::dashboard_dto::{MovementVolumeDto, StatusCountDto, StockCountsDto};
+use crate::models::ids::WarehouseId;
+use sqlx::Error;
+
+/// Classifies every product by its on-hand total, either across all
+/// warehouses or in `warehouses` alone.
+pub async fn stock_counts(pool: &DbPool, warehouses: Option<&[WarehouseId]>) -> Result<StockCountsDto, Error> {
+    let counts = sqlx::query_as!(StockCountsDto,
+        r#"SELECT
+            COUNT(*) FILTER (WHERE s.on_hand > 0) AS products_in_stock,
+            COUNT(*) FILTER (WHERE s.on_hand > 0 AND s.on_hand <= p.reorder_threshold) AS low_stock,
+            COUNT(*) FILTER (WHERE s.on_hand <= 0) AS out_of_stock
+        FROM products p
+        CROSS JOIN LATERAL (
+            SELECT COALESCE(SUM(ps.quantity), 0) AS on_hand
+            FROM product_stock ps
+            WHERE ps.product_id = p.id AND ($1::INTEGER[] IS NULL OR ps.warehouse_id = ANY($1))
+        ) s"#,
+        warehouses as _
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(counts)
+}
+
+pub async fn receipts_by_status(pool: &DbPool, warehouses: Option<&[WarehouseId]>, statuses: &[&str]) -> Result<Vec<StatusCountDto>, Error> {
+    let statuses: Vec<String> = statuses.iter().map(|s| s.to_string()).collect();
+    let rows = sqlx::query_as!(StatusCountDto,
+        r#"SELECT status, COUNT(*) AS count FROM receipts
+        WHERE status = ANY($1) AND ($2::INTEGER[] IS NULL OR warehouse_id = ANY($2))
+        GROUP BY status"#,
+        &statuses, warehouses as _
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+pub async fn deliveries_by_status(pool: &DbPool, warehouses: Option<&[WarehouseId]>, statuses: &[&str]) -> Result<Vec<StatusCountDto>, Error> {
+    let statuses: Vec<String> = statuses.iter().map(|s| s.to_string()).collect();
+    let rows = sqlx::query_as!(StatusCountDto,
+        r#"SELECT status, COUNT(*) AS count FROM deliveries
+        WHERE status = ANY($1) AND ($2::INTEGER[] IS NULL OR warehouse_id = ANY($2))
+        GROUP BY status"#,
+        &statuses, warehouses as _
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// A transfer counts for the warehouses on either end.
+pub async fn transfers_by_status(pool: &DbPool, warehouses: Option<&[WarehouseId]>, statuses: &[&str]) -> Result<Vec<StatusCountDto>, Error> {
+    let statuses: Vec<String> = statuses.iter().map(|s| s.to_string()).collect();
+    let rows = sqlx::query_as!(StatusCountDto,
+        r#"SELECT status, COUNT(*) AS count FROM transfers
+        WHERE status = ANY($1)
+            AND ($2::INTEGER[] IS NULL OR from_warehouse_id = ANY($2) OR to_warehouse_id = ANY($2))
+        GROUP BY status"#,
+        &statuses, warehouses as _
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+pub async fn movement_volume_today(pool: &DbPool, warehouses: Option<&[WarehouseId]>) -> Result<MovementVolumeDto, Error> {
+    let rec = sqlx::query!(
+        r#"SELECT
+            COUNT(*) AS "moves!",
+            COALESCE(SUM(quantity_change) FILTER (WHERE quantity_change > 0), 0) AS "units_in!",
+            COALESCE(-SUM(quantity_change) FILTER (WHERE quantity_change < 0), 0) AS "units_out!"
+        FROM stock_ledger
+        WHERE created_at >= CURRENT_DATE AND ($1::INTEGER[] IS NULL OR warehouse_id = ANY($1))"#,
+        warehouses as _
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(MovementVolumeDto { moves: rec.moves, units_in: rec.units_in, units_out: rec.units_out })
+}
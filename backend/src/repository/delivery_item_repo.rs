@@ -0,0 +1,42 @@
+use crate::dto::delivery_dto::{DeliveryItemDto, CreateDeliveryItemRequest, UpdateDeliveryItemRequest};
+use crate::models::ids::{DeliveryId, DeliveryItemId};
+use sqlx::{Error, PgConnection, PgExecutor};
+
+pub async fn get_by_delivery<'e, E: PgExecutor<'e>>(executor: E, delivery_id: DeliveryId) -> Result<Vec<DeliveryItemDto>, Error> {
+    let items = sqlx::query_as!(DeliveryItemDto,
+        r#"SELECT id AS "id: _", delivery_id AS "delivery_id: _", product_id AS "product_id: _", quantity, created_at FROM delivery_items WHERE delivery_id = $1 ORDER BY id"#,
+        delivery_id as _
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(items)
+}
+
+pub async fn create(conn: &mut PgConnection, delivery_id: DeliveryId, req: CreateDeliveryItemRequest) -> Result<DeliveryItemDto, Error> {
+    let rec = sqlx::query_as!(DeliveryItemDto,
+        r#"INSERT INTO delivery_items (delivery_id, product_id, quantity) VALUES ($1, $2, $3)
+        RETURNING id AS "id: _", delivery_id AS "delivery_id: _", product_id AS "product_id: _", quantity, created_at"#,
+        delivery_id as _, req.product_id as _, req.quantity
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(rec)
+}
+
+pub async fn update(conn: &mut PgConnection, delivery_id: DeliveryId, id: DeliveryItemId, req: UpdateDeliveryItemRequest) -> Result<Option<DeliveryItemDto>, Error> {
+    let rec = sqlx::query_as!(DeliveryItemDto,
+        r#"UPDATE delivery_items SET product_id = COALESCE($1, product_id), quantity = COALESCE($2, quantity)
+        WHERE id = $3 AND delivery_id = $4 RETURNING id AS "id: _", delivery_id AS "delivery_id: _", product_id AS "product_id: _", quantity, created_at"#,
+        req.product_id as _, req.quantity, id as _, delivery_id as _
+    )
+    .fetch_optional(conn)
+    .await?;
+    Ok(rec)
+}
+
+pub async fn delete(conn: &mut PgConnection, delivery_id: DeliveryId, id: DeliveryItemId) -> Result<bool, Error> {
+    let result = sqlx::query!("DELETE FROM delivery_items WHERE id = $1 AND delivery_id = $2", id as _, delivery_id as _)
+        .execute(conn)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
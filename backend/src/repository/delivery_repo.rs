@@ -1,41 +1,109 @@
-use crate::dto::delivery_dto::{DeliveryDto, CreateDeliveryRequest, UpdateDeliveryRequest};
-use crate::config::database::DbPool;
-use sqlx::Error;
+use crate::dto::delivery_dto::{DeliveryDto, DeliveryFilter, CreateDeliveryRequest, UpdateDeliveryRequest};
+use crate::config::database::{self, DbPool};
+use crate::dto::list_dto::{sort_column, search_pattern, ListParams, Page};
+use crate::models::ids::{DeliveryId, UserId, WarehouseId};
+use sqlx::{Error, PgConnection, PgExecutor};
 
-pub async fn get_all(pool: &DbPool) -> Result<Vec<DeliveryDto>, Error> {
+/// One page of deliveries in the given warehouses (all when `None`), filtered
+/// and sorted as asked.
+pub async fn get_all(pool: &DbPool, params: &ListParams, filter: &DeliveryFilter, warehouses: Option<&[WarehouseId]>) -> Result<Page<DeliveryDto>, Error> {
+    let date_to = filter.date_to.and_then(|date| date.succ_opt());
+    let pattern = search_pattern(filter.q.as_deref());
     let deliveries = sqlx::query_as!(DeliveryDto,
-        r#"SELECT id, customer_name, warehouse_id, user_id, status, created_at, updated_at FROM deliveries ORDER BY id"#
+        r#"SELECT id AS "id: _", customer_name, warehouse_id AS "warehouse_id: _", user_id AS "user_id: _", status, created_at, updated_at
+        FROM deliveries
+        WHERE ($1::INTEGER[] IS NULL OR warehouse_id = ANY($1))
+            AND ($2::TEXT IS NULL OR status = $2)
+            AND ($3::INTEGER IS NULL OR warehouse_id = $3)
+            AND ($4::DATE IS NULL OR created_at >= $4)
+            AND ($5::DATE IS NULL OR created_at < $5)
+            AND ($6::TEXT IS NULL OR customer_name ILIKE $6)
+        ORDER BY
+            CASE WHEN NOT $8 THEN CASE $7 WHEN 'customer_name' THEN customer_name WHEN 'status' THEN status END END,
+            CASE WHEN $8 THEN CASE $7 WHEN 'customer_name' THEN customer_name WHEN 'status' THEN status END END DESC,
+            CASE WHEN NOT $8 THEN CASE $7 WHEN 'created_at' THEN created_at WHEN 'updated_at' THEN updated_at END END,
+            CASE WHEN $8 THEN CASE $7 WHEN 'created_at' THEN created_at WHEN 'updated_at' THEN updated_at END END DESC,
+            CASE WHEN $8 THEN id END DESC,
+            id
+        LIMIT $9 OFFSET $10"#,
+        warehouses as _, filter.status, filter.warehouse_id as _, filter.date_from, date_to, pattern, sort_column(filter.sort), params.descending(), params.limit(), params.offset()
     )
     .fetch_all(pool)
     .await?;
-    Ok(deliveries)
+    let total = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM deliveries
+        WHERE ($1::INTEGER[] IS NULL OR warehouse_id = ANY($1))
+            AND ($2::TEXT IS NULL OR status = $2)
+            AND ($3::INTEGER IS NULL OR warehouse_id = $3)
+            AND ($4::DATE IS NULL OR created_at >= $4)
+            AND ($5::DATE IS NULL OR created_at < $5)
+            AND ($6::TEXT IS NULL OR customer_name ILIKE $6)"#,
+        warehouses as _, filter.status, filter.warehouse_id as _, filter.date_from, date_to, pattern
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(params.page_of(deliveries, total))
 }
 
-pub async fn create(pool: &DbPool, req: CreateDeliveryRequest) -> Result<DeliveryDto, Error> {
+pub async fn get_by_id(pool: &DbPool, id: DeliveryId) -> Result<Option<DeliveryDto>, Error> {
     let rec = sqlx::query_as!(DeliveryDto,
-        r#"INSERT INTO deliveries (customer_name, warehouse_id, user_id, status) VALUES ($1, $2, $3, $4)
-        RETURNING id, customer_name, warehouse_id, user_id, status, created_at, updated_at"#,
-        req.customer_name, req.warehouse_id, req.user_id, req.status.unwrap_or("".to_string())
+        r#"SELECT id AS "id: _", customer_name, warehouse_id AS "warehouse_id: _", user_id AS "user_id: _", status, created_at, updated_at FROM deliveries WHERE id = $1"#,
+        id as _
     )
-    .fetch_one(pool)
+    .fetch_optional(pool)
     .await?;
     Ok(rec)
 }
 
-pub async fn update(pool: &DbPool, id: i32, req: UpdateDeliveryRequest) -> Result<DeliveryDto, Error> {
+pub async fn create(pool: &DbPool, req: CreateDeliveryRequest, user_id: UserId) -> Result<DeliveryDto, Error> {
+    let mut tx = database::begin(pool).await?;
     let rec = sqlx::query_as!(DeliveryDto,
-        r#"UPDATE deliveries SET customer_name = COALESCE($1, customer_name), warehouse_id = COALESCE($2, warehouse_id), user_id = COALESCE($3, user_id), status = COALESCE($4, status), updated_at = NOW()
-        WHERE id = $5 RETURNING id, customer_name, warehouse_id, user_id, status, created_at, updated_at"#,
-        req.customer_name, req.warehouse_id, req.user_id, req.status.unwrap_or("".to_string()), id
+        r#"INSERT INTO deliveries (customer_name, warehouse_id, user_id, status) VALUES ($1, $2, $3, 'draft')
+        RETURNING id AS "id: _", customer_name, warehouse_id AS "warehouse_id: _", user_id AS "user_id: _", status, created_at, updated_at"#,
+        req.customer_name, req.warehouse_id as _, user_id as _
     )
-    .fetch_one(pool)
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(rec)
+}
+
+pub async fn update(conn: &mut PgConnection, id: DeliveryId, req: UpdateDeliveryRequest) -> Result<DeliveryDto, Error> {
+    let rec = sqlx::query_as!(DeliveryDto,
+        r#"UPDATE deliveries SET customer_name = COALESCE($1, customer_name), warehouse_id = COALESCE($2, warehouse_id), updated_at = NOW()
+        WHERE id = $3 RETURNING id AS "id: _", customer_name, warehouse_id AS "warehouse_id: _", user_id AS "user_id: _", status, created_at, updated_at"#,
+        req.customer_name, req.warehouse_id as _, id as _
+    )
+    .fetch_one(conn)
     .await?;
     Ok(rec)
 }
 
-pub async fn delete(pool: &DbPool, id: i32) -> Result<(), Error> {
-    sqlx::query!("DELETE FROM deliveries WHERE id = $1", id)
-        .execute(pool)
+pub async fn delete(conn: &mut PgConnection, id: DeliveryId) -> Result<(), Error> {
+    sqlx::query!("DELETE FROM deliveries WHERE id = $1", id as _)
+        .execute(conn)
         .await?;
     Ok(())
 }
+
+/// Loads a delivery and locks its row until the surrounding transaction ends.
+pub async fn find_for_update(conn: &mut PgConnection, id: DeliveryId) -> Result<Option<DeliveryDto>, Error> {
+    let rec = sqlx::query_as!(DeliveryDto,
+        r#"SELECT id AS "id: _", customer_name, warehouse_id AS "warehouse_id: _", user_id AS "user_id: _", status, created_at, updated_at FROM deliveries WHERE id = $1 FOR UPDATE"#,
+        id as _
+    )
+    .fetch_optional(conn)
+    .await?;
+    Ok(rec)
+}
+
+pub async fn set_status<'e, E: PgExecutor<'e>>(executor: E, id: DeliveryId, status: &str) -> Result<DeliveryDto, Error> {
+    let rec = sqlx::query_as!(DeliveryDto,
+        r#"UPDATE deliveries SET status = $1, updated_at = NOW()
+        WHERE id = $2 RETURNING id AS "id: _", customer_name, warehouse_id AS "warehouse_id: _", user_id AS "user_id: _", status, created_at, updated_at"#,
+        status, id as _
+    )
+    .fetch_one(executor)
+    .await?;
+    Ok(rec)
+}
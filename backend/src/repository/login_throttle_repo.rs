@@ -0,0 +1,55 @@
+use chrono::NaiveDateTime;
+use sqlx::{Error, PgExecutor};
+
+// Rows are keyed by `kind` (`account` or `ip`) and `subject` (the lowercased
+// email or the client address).
+
+/// Seconds until the account or the address is let in again, if either is
+/// locked right now.
+pub async fn retry_after<'e, E: PgExecutor<'e>>(executor: E, email: &str, ip: Option<&str>) -> Result<Option<i64>, Error> {
+    let secs = sqlx::query_scalar!(
+        "SELECT CEIL(EXTRACT(EPOCH FROM MAX(locked_until) - NOW()))::BIGINT FROM login_throttle
+        WHERE locked_until > NOW()
+            AND ((kind = 'account' AND subject = $1) OR (kind = 'ip' AND subject = $2))",
+        email, ip
+    )
+    .fetch_one(executor)
+    .await?;
+    Ok(secs)
+}
+
+/// Counts a failure and returns the new count. A failure more than
+/// `window_secs` after the previous one starts the count again.
+pub async fn record_failure<'e, E: PgExecutor<'e>>(executor: E, kind: &str, subject: &str, window_secs: i64) -> Result<i32, Error> {
+    let failures = sqlx::query_scalar!(
+        "INSERT INTO login_throttle (kind, subject, failures) VALUES ($1, $2, 1)
+        ON CONFLICT (kind, subject) DO UPDATE SET
+            failures = CASE WHEN login_throttle.last_failure_at < NOW() - make_interval(secs => $3) THEN 1
+                ELSE login_throttle.failures + 1 END,
+            last_failure_at = NOW()
+        RETURNING failures",
+        kind, subject, window_secs as f64
+    )
+    .fetch_one(executor)
+    .await?;
+    Ok(failures)
+}
+
+pub async fn lock<'e, E: PgExecutor<'e>>(executor: E, kind: &str, subject: &str, secs: i64) -> Result<NaiveDateTime, Error> {
+    let until = sqlx::query_scalar!(
+        r#"UPDATE login_throttle SET locked_until = NOW() + make_interval(secs => $3)
+        WHERE kind = $1 AND subject = $2 RETURNING locked_until AS "locked_until!""#,
+        kind, subject, secs as f64
+    )
+    .fetch_one(executor)
+    .await?;
+    Ok(until)
+}
+
+/// Forgets the failures and any lock; returns whether there was a row.
+pub async fn clear<'e, E: PgExecutor<'e>>(executor: E, kind: &str, subject: &str) -> Result<bool, Error> {
+    let result = sqlx::query!("DELETE FROM login_throttle WHERE kind = $1 AND subject = $2", kind, subject)
+        .execute(executor)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
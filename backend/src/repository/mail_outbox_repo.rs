@@ -0,0 +1,62 @@
+use sqlx::{Error, PgConnection, PgExecutor};
+
+/// Messages are given up on after this many failed attempts.
+pub const MAX_ATTEMPTS: i32 = 5;
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct OutboxMail {
+    pub id: i32,
+    pub recipient: String,
+    pub subject: String,
+    pub body: String,
+    pub attempts: i32,
+}
+
+pub async fn enqueue<'e, E: PgExecutor<'e>>(executor: E, recipient: &str, subject: &str, body: &str) -> Result<i32, Error> {
+    let id = sqlx::query_scalar!(
+        "INSERT INTO mail_outbox (recipient, subject, body) VALUES ($1, $2, $3) RETURNING id",
+        recipient, subject, body
+    )
+    .fetch_one(executor)
+    .await?;
+    Ok(id)
+}
+
+/// Locks up to `limit` messages that are due, skipping any another worker
+/// already holds, so each message is sent by one worker only.
+pub async fn lock_due(conn: &mut PgConnection, limit: i64) -> Result<Vec<OutboxMail>, Error> {
+    let mails = sqlx::query_as!(OutboxMail,
+        "SELECT id, recipient, subject, body, attempts FROM mail_outbox
+        WHERE status = 'pending' AND next_attempt_at <= NOW()
+        ORDER BY id LIMIT $1 FOR UPDATE SKIP LOCKED",
+        limit
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(mails)
+}
+
+pub async fn mark_sent(conn: &mut PgConnection, id: i32) -> Result<(), Error> {
+    sqlx::query!(
+        "UPDATE mail_outbox SET status = 'sent', attempts = attempts + 1, last_error = NULL, sent_at = NOW() WHERE id = $1",
+        id
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Schedules another attempt with quadratic backoff, or marks the message
+/// failed once it has used up its attempts.
+pub async fn mark_attempt_failed(conn: &mut PgConnection, id: i32, error: &str) -> Result<(), Error> {
+    sqlx::query!(
+        "UPDATE mail_outbox SET attempts = attempts + 1, last_error = $2,
+            status = CASE WHEN attempts + 1 >= $3 THEN 'failed' ELSE 'pending' END,
+            next_attempt_at = NOW() + (attempts + 1) * (attempts + 1) * INTERVAL '1 minute'
+        WHERE id = $1",
+        id, error, MAX_ATTEMPTS
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
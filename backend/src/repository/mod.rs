@@ -4,8 +4,11 @@ pub mod warehouse_repo;
 // pub mod stock_repo;
 // pub mod category_repo;
 // pub mod receipt_repo;
+pub mod receipt_item_repo;
 // pub mod delivery_repo;
+pub mod delivery_item_repo;
 // pub mod transfer_repo;
+pub mod transfer_item_repo;
 // pub mod adjustment_repo;
 pub mod user_repo;
 pub mod product_repo;
@@ -16,3 +19,12 @@ pub mod adjustment_repo;
 pub mod category_repo;
 pub mod stock_ledger_repo;
 pub mod product_stock_repo;
+pub mod move_history_repo;
+pub mod dashboard_repo;
+pub mod user_warehouse_repo;
+pub mod session_repo;
+pub mod user_token_repo;
+pub mod mail_outbox_repo;
+pub mod api_key_repo;
+pub mod audit_repo;
+pub mod login_throttle_repo;
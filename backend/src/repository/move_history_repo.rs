@@ -0,0 +1,72 @@
+use crate::config::database::DbPool;
+use crate::dto::list_dto::search_pattern;
+use crate::dto::move_history_dto::{MoveDto, MoveHistoryFilter, MoveHistoryPage};
+use crate::dto::list_dto::{DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+use crate::models::ids::WarehouseId;
+use sqlx::Error;
+
+/// Pages through the ledger newest first, like `stock_ledger_repo::get_all`,
+/// joining in everything needed to display a move.
+pub async fn get_moves(pool: &DbPool, filter: MoveHistoryFilter, warehouses: Option<&[WarehouseId]>) -> Result<MoveHistoryPage, Error> {
+    let limit = filter.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let date_to = filter.date_to.and_then(|date| date.succ_opt());
+    let pattern = search_pattern(filter.q.as_deref());
+
+    // Joined columns are nullable: each row matches at most one document table
+    let mut moves = sqlx::query_as!(MoveDto,
+        r#"SELECT m.id AS "id: _", m.created_at, m.movement_type, m.document_type, m.document_id,
+            m.document_status AS "document_status?", m.counterparty AS "counterparty?",
+            m.product_id AS "product_id: _", m.product_sku AS "product_sku?", m.product_name AS "product_name?",
+            m.warehouse_id AS "warehouse_id: _", m.warehouse_name AS "warehouse_name?",
+            m.from_warehouse_name AS "from_warehouse_name?", m.to_warehouse_name AS "to_warehouse_name?",
+            m.user_id AS "user_id: _", m.user_name AS "user_name?",
+            m.quantity_change, m.new_balance, m.notes, m.reversal_of AS "reversal_of: _"
+        FROM (
+            SELECT l.id, l.created_at, l.movement_type,
+                l.reference_type AS document_type, l.reference_id AS document_id,
+                COALESCE(r.status, d.status, t.status, a.status) AS document_status,
+                COALESCE(r.supplier_name, d.customer_name, a.reason,
+                    CASE WHEN l.warehouse_id = t.from_warehouse_id THEN tw.name ELSE fw.name END) AS counterparty,
+                l.product_id, p.sku AS product_sku, p.name AS product_name,
+                l.warehouse_id, w.name AS warehouse_name,
+                fw.name AS from_warehouse_name, tw.name AS to_warehouse_name,
+                l.user_id, u.name AS user_name,
+                l.quantity_change, l.new_balance, l.notes, l.reversal_of
+            FROM stock_ledger l
+            LEFT JOIN products p ON p.id = l.product_id
+            LEFT JOIN warehouses w ON w.id = l.warehouse_id
+            LEFT JOIN users u ON u.id = l.user_id
+            LEFT JOIN receipts r ON l.reference_type = 'receipt' AND r.id = l.reference_id
+            LEFT JOIN deliveries d ON l.reference_type = 'delivery' AND d.id = l.reference_id
+            LEFT JOIN transfers t ON l.reference_type = 'transfer' AND t.id = l.reference_id
+            LEFT JOIN warehouses fw ON fw.id = t.from_warehouse_id
+            LEFT JOIN warehouses tw ON tw.id = t.to_warehouse_id
+            LEFT JOIN adjustments a ON l.reference_type = 'adjustment' AND a.id = l.reference_id
+            WHERE ($1::INTEGER IS NULL OR l.id < $1)
+                AND ($2::TEXT IS NULL OR l.reference_type = $2)
+                AND ($3::INTEGER IS NULL OR l.product_id = $3)
+                AND ($4::INTEGER IS NULL OR l.warehouse_id = $4)
+                AND ($5::DATE IS NULL OR l.created_at >= $5)
+                AND ($6::DATE IS NULL OR l.created_at < $6)
+                AND ($9::INTEGER[] IS NULL OR l.warehouse_id = ANY($9))
+        ) m
+        WHERE $7::TEXT IS NULL
+            OR m.product_sku ILIKE $7 OR m.product_name ILIKE $7
+            OR m.warehouse_name ILIKE $7 OR m.user_name ILIKE $7
+            OR m.counterparty ILIKE $7 OR m.notes ILIKE $7
+        ORDER BY m.id DESC
+        LIMIT $8"#,
+        filter.cursor, filter.document_type, filter.product_id as _, filter.warehouse_id as _,
+        filter.date_from, date_to, pattern, limit + 1, warehouses as _
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let next_cursor = if moves.len() as i64 > limit {
+        moves.truncate(limit as usize);
+        moves.last().and_then(|m| m.id).map(|id| id.0)
+    } else {
+        None
+    };
+    Ok(MoveHistoryPage { moves, next_cursor })
+}
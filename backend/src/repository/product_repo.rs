@@ -1,41 +1,73 @@
-use crate::dto::product_dto::{ProductDto, CreateProductRequest, UpdateProductRequest};
-use crate::config::database::DbPool;
+use crate::dto::product_dto::{ProductDto, ProductFilter, CreateProductRequest, UpdateProductRequest};
+use crate::config::database::{self, DbPool};
+use crate::dto::list_dto::{sort_column, search_pattern, ListParams, Page};
+use crate::models::ids::ProductId;
 use sqlx::Error;
 
-pub async fn get_all(pool: &DbPool) -> Result<Vec<ProductDto>, Error> {
+/// One page of products, filtered and sorted as asked.
+pub async fn get_all(pool: &DbPool, params: &ListParams, filter: &ProductFilter) -> Result<Page<ProductDto>, Error> {
+    let pattern = search_pattern(filter.q.as_deref());
     let products = sqlx::query_as!(ProductDto,
-        r#"SELECT id, sku, name, category_id, unit_of_measure, reorder_threshold, created_at, updated_at FROM products ORDER BY id"#
+        r#"SELECT id AS "id: _", sku, name, category_id AS "category_id: _", unit_of_measure, reorder_threshold, negative_stock_policy, created_at, updated_at
+        FROM products
+        WHERE ($1::INTEGER IS NULL OR category_id = $1)
+            AND ($2::TEXT IS NULL OR sku ILIKE $2 OR name ILIKE $2)
+        ORDER BY
+            CASE WHEN NOT $4 THEN CASE $3 WHEN 'sku' THEN sku WHEN 'name' THEN name END END,
+            CASE WHEN $4 THEN CASE $3 WHEN 'sku' THEN sku WHEN 'name' THEN name END END DESC,
+            CASE WHEN NOT $4 THEN CASE $3 WHEN 'reorder_threshold' THEN reorder_threshold END END,
+            CASE WHEN $4 THEN CASE $3 WHEN 'reorder_threshold' THEN reorder_threshold END END DESC,
+            CASE WHEN NOT $4 THEN CASE $3 WHEN 'created_at' THEN created_at END END,
+            CASE WHEN $4 THEN CASE $3 WHEN 'created_at' THEN created_at END END DESC,
+            CASE WHEN $4 THEN id END DESC,
+            id
+        LIMIT $5 OFFSET $6"#,
+        filter.category_id as _, pattern, sort_column(filter.sort), params.descending(), params.limit(), params.offset()
     )
     .fetch_all(pool)
     .await?;
-    Ok(products)
+    let total = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM products
+        WHERE ($1::INTEGER IS NULL OR category_id = $1)
+            AND ($2::TEXT IS NULL OR sku ILIKE $2 OR name ILIKE $2)"#,
+        filter.category_id as _, pattern
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(params.page_of(products, total))
 }
 
 pub async fn create(pool: &DbPool, req: CreateProductRequest) -> Result<ProductDto, Error> {
+    let mut tx = database::begin(pool).await?;
     let rec = sqlx::query_as!(ProductDto,
-        r#"INSERT INTO products (sku, name, category_id, unit_of_measure, reorder_threshold) VALUES ($1, $2, $3, $4, $5)
-        RETURNING id, sku, name, category_id, unit_of_measure, reorder_threshold, created_at, updated_at"#,
-        req.sku, req.name, req.category_id, req.unit_of_measure, req.reorder_threshold
+        r#"INSERT INTO products (sku, name, category_id, unit_of_measure, reorder_threshold, negative_stock_policy) VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id AS "id: _", sku, name, category_id AS "category_id: _", unit_of_measure, reorder_threshold, negative_stock_policy, created_at, updated_at"#,
+        req.sku, req.name, req.category_id as _, req.unit_of_measure, req.reorder_threshold, req.negative_stock_policy
     )
-    .fetch_one(pool)
+    .fetch_one(&mut *tx)
     .await?;
+    tx.commit().await?;
     Ok(rec)
 }
 
-pub async fn update(pool: &DbPool, id: i32, req: UpdateProductRequest) -> Result<ProductDto, Error> {
+pub async fn update(pool: &DbPool, id: ProductId, req: UpdateProductRequest) -> Result<Option<ProductDto>, Error> {
+    let mut tx = database::begin(pool).await?;
     let rec = sqlx::query_as!(ProductDto,
-        r#"UPDATE products SET name = COALESCE($1, name), category_id = COALESCE($2, category_id), unit_of_measure = COALESCE($3, unit_of_measure), reorder_threshold = COALESCE($4, reorder_threshold), updated_at = NOW()
-        WHERE id = $5 RETURNING id, sku, name, category_id, unit_of_measure, reorder_threshold, created_at, updated_at"#,
-        req.name, req.category_id, req.unit_of_measure, req.reorder_threshold, id
+        r#"UPDATE products SET name = COALESCE($1, name), category_id = COALESCE($2, category_id), unit_of_measure = COALESCE($3, unit_of_measure), reorder_threshold = COALESCE($4, reorder_threshold), negative_stock_policy = COALESCE($5, negative_stock_policy), updated_at = NOW()
+        WHERE id = $6 RETURNING id AS "id: _", sku, name, category_id AS "category_id: _", unit_of_measure, reorder_threshold, negative_stock_policy, created_at, updated_at"#,
+        req.name, req.category_id as _, req.unit_of_measure, req.reorder_threshold, req.negative_stock_policy, id as _
     )
-    .fetch_one(pool)
+    .fetch_optional(&mut *tx)
     .await?;
+    tx.commit().await?;
     Ok(rec)
 }
 
-pub async fn delete(pool: &DbPool, id: i32) -> Result<(), Error> {
-    sqlx::query!("DELETE FROM products WHERE id = $1", id)
-        .execute(pool)
+pub async fn delete(pool: &DbPool, id: ProductId) -> Result<bool, Error> {
+    let mut tx = database::begin(pool).await?;
+    let result = sqlx::query!("DELETE FROM products WHERE id = $1", id as _)
+        .execute(&mut *tx)
         .await?;
-    Ok(())
+    tx.commit().await?;
+    Ok(result.rows_affected() > 0)
 }
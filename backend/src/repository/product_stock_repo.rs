@@ -1,36 +1,127 @@
 use crate::config::database::DbPool;
 use crate::models::product_stock::ProductStock;
-use sqlx::Error;
-use uuid::Uuid;
+use crate::models::ids::{ProductId, WarehouseId};
+use sqlx::{Error, PgConnection};
 
 
-pub async fn get_all(pool: &DbPool) -> Result<Vec<ProductStock>, Error> {
+pub async fn get_all(pool: &DbPool, warehouses: Option<&[WarehouseId]>) -> Result<Vec<ProductStock>, Error> {
     let stocks = sqlx::query_as::<_, ProductStock>(
-        r#"SELECT product_id, warehouse_id, quantity FROM product_stock"#
+        r#"SELECT product_id, warehouse_id, quantity, reserved_quantity FROM product_stock
+        WHERE $1::INTEGER[] IS NULL OR warehouse_id = ANY($1) ORDER BY product_id, warehouse_id"#
     )
+    .bind(warehouses)
     .fetch_all(pool)
     .await?;
     Ok(stocks)
 }
 
 
-pub async fn get_by_product(pool: &DbPool, product_id: Uuid) -> Result<Vec<ProductStock>, Error> {
+pub async fn get_by_product(pool: &DbPool, product_id: ProductId, warehouses: Option<&[WarehouseId]>) -> Result<Vec<ProductStock>, Error> {
     let stocks = sqlx::query_as::<_, ProductStock>(
-        r#"SELECT product_id, warehouse_id, quantity FROM product_stock WHERE product_id = $1"#
+        r#"SELECT product_id, warehouse_id, quantity, reserved_quantity FROM product_stock
+        WHERE product_id = $1 AND ($2::INTEGER[] IS NULL OR warehouse_id = ANY($2)) ORDER BY warehouse_id"#
     )
     .bind(product_id)
+    .bind(warehouses)
     .fetch_all(pool)
     .await?;
     Ok(stocks)
 }
 
 
-pub async fn get_by_warehouse(pool: &DbPool, warehouse_id: Uuid) -> Result<Vec<ProductStock>, Error> {
+pub async fn get_by_warehouse(pool: &DbPool, warehouse_id: WarehouseId) -> Result<Vec<ProductStock>, Error> {
     let stocks = sqlx::query_as::<_, ProductStock>(
-        r#"SELECT product_id, warehouse_id, quantity FROM product_stock WHERE warehouse_id = $1"#
+        r#"SELECT product_id, warehouse_id, quantity, reserved_quantity FROM product_stock WHERE warehouse_id = $1 ORDER BY product_id"#
     )
     .bind(warehouse_id)
     .fetch_all(pool)
     .await?;
     Ok(stocks)
 }
+
+/// Adds `quantity` (negative to remove stock) to a product's balance in a warehouse,
+/// creating the row on first receipt, and returns the new balance.
+pub async fn add_quantity(conn: &mut PgConnection, product_id: ProductId, warehouse_id: WarehouseId, quantity: i32) -> Result<i32, Error> {
+    let new_balance = sqlx::query_scalar!(
+        r#"INSERT INTO product_stock (product_id, warehouse_id, quantity) VALUES ($1, $2, $3)
+        ON CONFLICT (product_id, warehouse_id)
+        DO UPDATE SET quantity = product_stock.quantity + EXCLUDED.quantity, updated_at = NOW()
+        RETURNING quantity"#,
+        product_id as _, warehouse_id as _, quantity
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(new_balance)
+}
+
+/// Adds to the reserved quantity. Callers check availability under [`lock`] first.
+pub async fn reserve(conn: &mut PgConnection, product_id: ProductId, warehouse_id: WarehouseId, quantity: i32) -> Result<(), Error> {
+    sqlx::query!(
+        r#"UPDATE product_stock SET reserved_quantity = reserved_quantity + $3, updated_at = NOW()
+        WHERE product_id = $1 AND warehouse_id = $2"#,
+        product_id as _, warehouse_id as _, quantity
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn release(conn: &mut PgConnection, product_id: ProductId, warehouse_id: WarehouseId, quantity: i32) -> Result<(), Error> {
+    sqlx::query!(
+        r#"UPDATE product_stock SET reserved_quantity = GREATEST(reserved_quantity - $3, 0), updated_at = NOW()
+        WHERE product_id = $1 AND warehouse_id = $2"#,
+        product_id as _, warehouse_id as _, quantity
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Removes previously reserved stock from the warehouse and returns the new balance.
+pub async fn consume_reserved(conn: &mut PgConnection, product_id: ProductId, warehouse_id: WarehouseId, quantity: i32) -> Result<i32, Error> {
+    let new_balance = sqlx::query_scalar!(
+        r#"UPDATE product_stock SET quantity = quantity - $3, reserved_quantity = GREATEST(reserved_quantity - $3, 0), updated_at = NOW()
+        WHERE product_id = $1 AND warehouse_id = $2 RETURNING quantity"#,
+        product_id as _, warehouse_id as _, quantity
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(new_balance)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StockLevel {
+    pub quantity: i32,
+    pub reserved_quantity: i32,
+}
+
+/// Locks a product's stock row in a warehouse with `FOR UPDATE`, creating an
+/// empty one if it does not exist yet, and returns its current level.
+pub async fn lock(conn: &mut PgConnection, product_id: ProductId, warehouse_id: WarehouseId) -> Result<StockLevel, Error> {
+    sqlx::query!(
+        r#"INSERT INTO product_stock (product_id, warehouse_id, quantity) VALUES ($1, $2, 0)
+        ON CONFLICT (product_id, warehouse_id) DO NOTHING"#,
+        product_id as _, warehouse_id as _
+    )
+    .execute(&mut *conn)
+    .await?;
+    let level = sqlx::query_as!(StockLevel,
+        r#"SELECT quantity, reserved_quantity FROM product_stock WHERE product_id = $1 AND warehouse_id = $2 FOR UPDATE"#,
+        product_id as _, warehouse_id as _
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(level)
+}
+
+/// The negative-stock policy configured on the product, falling back to the warehouse's.
+pub async fn negative_stock_policy(conn: &mut PgConnection, product_id: ProductId, warehouse_id: WarehouseId) -> Result<Option<String>, Error> {
+    let policy = sqlx::query_scalar!(
+        r#"SELECT COALESCE(p.negative_stock_policy, w.negative_stock_policy) AS policy
+        FROM products p, warehouses w WHERE p.id = $1 AND w.id = $2"#,
+        product_id as _, warehouse_id as _
+    )
+    .fetch_optional(conn)
+    .await?;
+    Ok(policy.flatten())
+}
@@ -0,0 +1,42 @@
+use crate::dto::receipt_dto::{ReceiptItemDto, CreateReceiptItemRequest, UpdateReceiptItemRequest};
+use crate::models::ids::{ReceiptId, ReceiptItemId};
+use sqlx::{Error, PgConnection, PgExecutor};
+
+pub async fn get_by_receipt<'e, E: PgExecutor<'e>>(executor: E, receipt_id: ReceiptId) -> Result<Vec<ReceiptItemDto>, Error> {
+    let items = sqlx::query_as!(ReceiptItemDto,
+        r#"SELECT id AS "id: _", receipt_id AS "receipt_id: _", product_id AS "product_id: _", quantity, expiry_date, created_at FROM receipt_items WHERE receipt_id = $1 ORDER BY id"#,
+        receipt_id as _
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(items)
+}
+
+pub async fn create(conn: &mut PgConnection, receipt_id: ReceiptId, req: CreateReceiptItemRequest) -> Result<ReceiptItemDto, Error> {
+    let rec = sqlx::query_as!(ReceiptItemDto,
+        r#"INSERT INTO receipt_items (receipt_id, product_id, quantity, expiry_date) VALUES ($1, $2, $3, $4)
+        RETURNING id AS "id: _", receipt_id AS "receipt_id: _", product_id AS "product_id: _", quantity, expiry_date, created_at"#,
+        receipt_id as _, req.product_id as _, req.quantity, req.expiry_date
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(rec)
+}
+
+pub async fn update(conn: &mut PgConnection, receipt_id: ReceiptId, id: ReceiptItemId, req: UpdateReceiptItemRequest) -> Result<Option<ReceiptItemDto>, Error> {
+    let rec = sqlx::query_as!(ReceiptItemDto,
+        r#"UPDATE receipt_items SET product_id = COALESCE($1, product_id), quantity = COALESCE($2, quantity), expiry_date = COALESCE($3, expiry_date)
+        WHERE id = $4 AND receipt_id = $5 RETURNING id AS "id: _", receipt_id AS "receipt_id: _", product_id AS "product_id: _", quantity, expiry_date, created_at"#,
+        req.product_id as _, req.quantity, req.expiry_date, id as _, receipt_id as _
+    )
+    .fetch_optional(conn)
+    .await?;
+    Ok(rec)
+}
+
+pub async fn delete(conn: &mut PgConnection, receipt_id: ReceiptId, id: ReceiptItemId) -> Result<bool, Error> {
+    let result = sqlx::query!("DELETE FROM receipt_items WHERE id = $1 AND receipt_id = $2", id as _, receipt_id as _)
+        .execute(conn)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
@@ -1,41 +1,109 @@
-use crate::dto::receipt_dto::{ReceiptDto, CreateReceiptRequest, UpdateReceiptRequest};
-use crate::config::database::DbPool;
-use sqlx::Error;
+use crate::dto::receipt_dto::{ReceiptDto, ReceiptFilter, CreateReceiptRequest, UpdateReceiptRequest};
+use crate::config::database::{self, DbPool};
+use crate::dto::list_dto::{sort_column, search_pattern, ListParams, Page};
+use crate::models::ids::{ReceiptId, UserId, WarehouseId};
+use sqlx::{Error, PgConnection, PgExecutor};
 
-pub async fn get_all(pool: &DbPool) -> Result<Vec<ReceiptDto>, Error> {
+/// One page of receipts in the given warehouses (all when `None`), filtered
+/// and sorted as asked.
+pub async fn get_all(pool: &DbPool, params: &ListParams, filter: &ReceiptFilter, warehouses: Option<&[WarehouseId]>) -> Result<Page<ReceiptDto>, Error> {
+    let date_to = filter.date_to.and_then(|date| date.succ_opt());
+    let pattern = search_pattern(filter.q.as_deref());
     let receipts = sqlx::query_as!(ReceiptDto,
-        r#"SELECT id, supplier_name, warehouse_id, user_id, status, created_at, updated_at FROM receipts ORDER BY id"#
+        r#"SELECT id AS "id: _", supplier_name, warehouse_id AS "warehouse_id: _", user_id AS "user_id: _", status, created_at, updated_at
+        FROM receipts
+        WHERE ($1::INTEGER[] IS NULL OR warehouse_id = ANY($1))
+            AND ($2::TEXT IS NULL OR status = $2)
+            AND ($3::INTEGER IS NULL OR warehouse_id = $3)
+            AND ($4::DATE IS NULL OR created_at >= $4)
+            AND ($5::DATE IS NULL OR created_at < $5)
+            AND ($6::TEXT IS NULL OR supplier_name ILIKE $6)
+        ORDER BY
+            CASE WHEN NOT $8 THEN CASE $7 WHEN 'supplier_name' THEN supplier_name WHEN 'status' THEN status END END,
+            CASE WHEN $8 THEN CASE $7 WHEN 'supplier_name' THEN supplier_name WHEN 'status' THEN status END END DESC,
+            CASE WHEN NOT $8 THEN CASE $7 WHEN 'created_at' THEN created_at WHEN 'updated_at' THEN updated_at END END,
+            CASE WHEN $8 THEN CASE $7 WHEN 'created_at' THEN created_at WHEN 'updated_at' THEN updated_at END END DESC,
+            CASE WHEN $8 THEN id END DESC,
+            id
+        LIMIT $9 OFFSET $10"#,
+        warehouses as _, filter.status, filter.warehouse_id as _, filter.date_from, date_to, pattern, sort_column(filter.sort), params.descending(), params.limit(), params.offset()
     )
     .fetch_all(pool)
     .await?;
-    Ok(receipts)
+    let total = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM receipts
+        WHERE ($1::INTEGER[] IS NULL OR warehouse_id = ANY($1))
+            AND ($2::TEXT IS NULL OR status = $2)
+            AND ($3::INTEGER IS NULL OR warehouse_id = $3)
+            AND ($4::DATE IS NULL OR created_at >= $4)
+            AND ($5::DATE IS NULL OR created_at < $5)
+            AND ($6::TEXT IS NULL OR supplier_name ILIKE $6)"#,
+        warehouses as _, filter.status, filter.warehouse_id as _, filter.date_from, date_to, pattern
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(params.page_of(receipts, total))
 }
 
-pub async fn create(pool: &DbPool, req: CreateReceiptRequest) -> Result<ReceiptDto, Error> {
+pub async fn get_by_id(pool: &DbPool, id: ReceiptId) -> Result<Option<ReceiptDto>, Error> {
     let rec = sqlx::query_as!(ReceiptDto,
-        r#"INSERT INTO receipts (supplier_name, warehouse_id, user_id, status) VALUES ($1, $2, $3, $4)
-        RETURNING id, supplier_name, warehouse_id, user_id, status, created_at, updated_at"#,
-        req.supplier_name, req.warehouse_id, req.user_id, req.status.unwrap_or("".to_string())
+        r#"SELECT id AS "id: _", supplier_name, warehouse_id AS "warehouse_id: _", user_id AS "user_id: _", status, created_at, updated_at FROM receipts WHERE id = $1"#,
+        id as _
     )
-    .fetch_one(pool)
+    .fetch_optional(pool)
     .await?;
     Ok(rec)
 }
 
-pub async fn update(pool: &DbPool, id: i32, req: UpdateReceiptRequest) -> Result<ReceiptDto, Error> {
+pub async fn create(pool: &DbPool, req: CreateReceiptRequest, user_id: UserId) -> Result<ReceiptDto, Error> {
+    let mut tx = database::begin(pool).await?;
     let rec = sqlx::query_as!(ReceiptDto,
-        r#"UPDATE receipts SET supplier_name = COALESCE($1, supplier_name), warehouse_id = COALESCE($2, warehouse_id), user_id = COALESCE($3, user_id), status = COALESCE($4, status), updated_at = NOW()
-        WHERE id = $5 RETURNING id, supplier_name, warehouse_id, user_id, status, created_at, updated_at"#,
-        req.supplier_name, req.warehouse_id, req.user_id, req.status.unwrap_or("".to_string()), id
+        r#"INSERT INTO receipts (supplier_name, warehouse_id, user_id, status) VALUES ($1, $2, $3, 'draft')
+        RETURNING id AS "id: _", supplier_name, warehouse_id AS "warehouse_id: _", user_id AS "user_id: _", status, created_at, updated_at"#,
+        req.supplier_name, req.warehouse_id as _, user_id as _
     )
-    .fetch_one(pool)
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(rec)
+}
+
+pub async fn update(conn: &mut PgConnection, id: ReceiptId, req: UpdateReceiptRequest) -> Result<ReceiptDto, Error> {
+    let rec = sqlx::query_as!(ReceiptDto,
+        r#"UPDATE receipts SET supplier_name = COALESCE($1, supplier_name), warehouse_id = COALESCE($2, warehouse_id), updated_at = NOW()
+        WHERE id = $3 RETURNING id AS "id: _", supplier_name, warehouse_id AS "warehouse_id: _", user_id AS "user_id: _", status, created_at, updated_at"#,
+        req.supplier_name, req.warehouse_id as _, id as _
+    )
+    .fetch_one(conn)
     .await?;
     Ok(rec)
 }
 
-pub async fn delete(pool: &DbPool, id: i32) -> Result<(), Error> {
-    sqlx::query!("DELETE FROM receipts WHERE id = $1", id)
-        .execute(pool)
+pub async fn delete(conn: &mut PgConnection, id: ReceiptId) -> Result<(), Error> {
+    sqlx::query!("DELETE FROM receipts WHERE id = $1", id as _)
+        .execute(conn)
         .await?;
     Ok(())
 }
+
+/// Loads a receipt and locks its row until the surrounding transaction ends.
+pub async fn find_for_update(conn: &mut PgConnection, id: ReceiptId) -> Result<Option<ReceiptDto>, Error> {
+    let rec = sqlx::query_as!(ReceiptDto,
+        r#"SELECT id AS "id: _", supplier_name, warehouse_id AS "warehouse_id: _", user_id AS "user_id: _", status, created_at, updated_at FROM receipts WHERE id = $1 FOR UPDATE"#,
+        id as _
+    )
+    .fetch_optional(conn)
+    .await?;
+    Ok(rec)
+}
+
+pub async fn set_status<'e, E: PgExecutor<'e>>(executor: E, id: ReceiptId, status: &str) -> Result<ReceiptDto, Error> {
+    let rec = sqlx::query_as!(ReceiptDto,
+        r#"UPDATE receipts SET status = $1, updated_at = NOW()
+        WHERE id = $2 RETURNING id AS "id: _", supplier_name, warehouse_id AS "warehouse_id: _", user_id AS "user_id: _", status, created_at, updated_at"#,
+        status, id as _
+    )
+    .fetch_one(executor)
+    .await?;
+    Ok(rec)
+}
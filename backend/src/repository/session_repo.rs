@@ -0,0 +1,98 @@
+use crate::models::ids::{SessionId, UserId};
+use chrono::NaiveDateTime;
+use sqlx::{Error, PgConnection, PgExecutor};
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct Session {
+    pub id: SessionId,
+    pub user_id: UserId,
+    pub expires_at: NaiveDateTime,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+pub async fn create<'e, E: PgExecutor<'e>>(
+    executor: E,
+    user_id: UserId,
+    refresh_token_hash: &str,
+    expires_at: NaiveDateTime,
+) -> Result<SessionId, Error> {
+    let id = sqlx::query_scalar!(
+        r#"INSERT INTO sessions (user_id, refresh_token_hash, expires_at) VALUES ($1, $2, $3)
+        RETURNING id AS "id: SessionId""#,
+        user_id as _, refresh_token_hash, expires_at
+    )
+    .fetch_one(executor)
+    .await?;
+    Ok(id)
+}
+
+/// Locks the session currently holding this refresh token so two refreshes
+/// with the same token cannot both succeed.
+pub async fn lock_by_token(conn: &mut PgConnection, refresh_token_hash: &str) -> Result<Option<Session>, Error> {
+    let rec = sqlx::query_as!(Session,
+        r#"SELECT id AS "id: _", user_id AS "user_id: _", expires_at, revoked_at
+        FROM sessions WHERE refresh_token_hash = $1 FOR UPDATE"#,
+        refresh_token_hash
+    )
+    .fetch_optional(conn)
+    .await?;
+    Ok(rec)
+}
+
+/// The live session that last rotated away from this token, if any.
+pub async fn find_by_previous_token<'e, E: PgExecutor<'e>>(executor: E, token_hash: &str) -> Result<Option<Session>, Error> {
+    let rec = sqlx::query_as!(Session,
+        r#"SELECT id AS "id: _", user_id AS "user_id: _", expires_at, revoked_at
+        FROM sessions WHERE previous_token_hash = $1 AND revoked_at IS NULL"#,
+        token_hash
+    )
+    .fetch_optional(executor)
+    .await?;
+    Ok(rec)
+}
+
+pub async fn rotate(
+    conn: &mut PgConnection,
+    id: SessionId,
+    refresh_token_hash: &str,
+    expires_at: NaiveDateTime,
+) -> Result<(), Error> {
+    sqlx::query!(
+        "UPDATE sessions SET previous_token_hash = refresh_token_hash, refresh_token_hash = $2, expires_at = $3, last_used_at = NOW()
+        WHERE id = $1",
+        id as _, refresh_token_hash, expires_at
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Revokes one of the user's sessions; false if it was not theirs or already revoked.
+pub async fn revoke<'e, E: PgExecutor<'e>>(executor: E, user_id: UserId, id: SessionId) -> Result<bool, Error> {
+    let result = sqlx::query!(
+        "UPDATE sessions SET revoked_at = NOW() WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL",
+        id as _, user_id as _
+    )
+    .execute(executor)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Returns how many sessions were revoked.
+pub async fn revoke_all<'e, E: PgExecutor<'e>>(executor: E, user_id: UserId) -> Result<u64, Error> {
+    let result = sqlx::query!(
+        "UPDATE sessions SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL",
+        user_id as _
+    )
+    .execute(executor)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Drops the user's sessions that can no longer be refreshed.
+pub async fn delete_expired<'e, E: PgExecutor<'e>>(executor: E, user_id: UserId) -> Result<(), Error> {
+    sqlx::query!("DELETE FROM sessions WHERE user_id = $1 AND expires_at < NOW()", user_id as _)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
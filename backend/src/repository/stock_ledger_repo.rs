@@ -1,37 +1,126 @@
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use crate::models::ids::{ProductId, StockLedgerId, UserId, WarehouseId};
 use crate::config::database::DbPool;
-use sqlx::Error;
+use crate::dto::list_dto::{DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+use sqlx::{Error, PgConnection};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct StockLedgerDto {
-    pub id: Option<i32>,
-    pub product_id: Option<i32>,
-    pub warehouse_id: Option<i32>,
-    pub user_id: Option<i32>,
+    pub id: Option<StockLedgerId>,
+    pub product_id: Option<ProductId>,
+    pub warehouse_id: Option<WarehouseId>,
+    pub user_id: Option<UserId>,
     pub movement_type: Option<String>,
     pub reference_id: Option<i32>,
     pub reference_type: Option<String>,
     pub quantity_change: Option<i32>,
     pub new_balance: Option<i32>,
     pub notes: Option<String>,
+    pub reversal_of: Option<StockLedgerId>,
     pub created_at: Option<chrono::NaiveDateTime>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct StockLedgerFilter {
-    pub product_id: Option<i32>,
-    pub warehouse_id: Option<i32>,
+    pub product_id: Option<ProductId>,
+    pub warehouse_id: Option<WarehouseId>,
+    pub user_id: Option<UserId>,
     pub movement_type: Option<String>,
+    pub reference_type: Option<String>,
+    pub reference_id: Option<i32>,
     pub date_from: Option<chrono::NaiveDate>,
+    /// Inclusive: entries from anywhere on this day are returned.
     pub date_to: Option<chrono::NaiveDate>,
+    /// `next_cursor` from the previous page; omit for the newest entries.
+    pub cursor: Option<i32>,
+    pub limit: Option<i64>,
 }
 
-pub async fn get_all(pool: &DbPool, filter: StockLedgerFilter) -> Result<Vec<StockLedgerDto>, Error> {
-    // For simplicity, this is a basic query. You can expand it for more advanced filtering.
-    let entries = sqlx::query_as!(StockLedgerDto,
-        r#"SELECT id, product_id, warehouse_id, user_id, movement_type, reference_id, reference_type, quantity_change, new_balance, notes, created_at FROM stock_ledger ORDER BY created_at DESC"#
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StockLedgerPage {
+    pub entries: Vec<StockLedgerDto>,
+    /// Pass back as `cursor` to fetch the next (older) page; null on the last page.
+    pub next_cursor: Option<i32>,
+}
+
+/// Newest entries first, paged by id so that deep pages cost the same as the
+/// first one. Ids only ever grow, which makes them a stable keyset cursor.
+pub async fn get_all(pool: &DbPool, filter: StockLedgerFilter, warehouses: Option<&[WarehouseId]>) -> Result<StockLedgerPage, Error> {
+    let limit = filter.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let date_to = filter.date_to.and_then(|date| date.succ_opt());
+    // Fetch one extra row to learn whether another page exists
+    let mut entries = sqlx::query_as!(StockLedgerDto,
+        r#"SELECT id AS "id: _", product_id AS "product_id: _", warehouse_id AS "warehouse_id: _", user_id AS "user_id: _", movement_type, reference_id, reference_type, quantity_change, new_balance, notes, reversal_of AS "reversal_of: _", created_at
+        FROM stock_ledger
+        WHERE ($1::INTEGER IS NULL OR id < $1)
+            AND ($2::INTEGER IS NULL OR product_id = $2)
+            AND ($3::INTEGER IS NULL OR warehouse_id = $3)
+            AND ($4::INTEGER IS NULL OR user_id = $4)
+            AND ($5::TEXT IS NULL OR movement_type = $5)
+            AND ($6::TEXT IS NULL OR reference_type = $6)
+            AND ($7::INTEGER IS NULL OR reference_id = $7)
+            AND ($8::DATE IS NULL OR created_at >= $8)
+            AND ($9::DATE IS NULL OR created_at < $9)
+            AND ($11::INTEGER[] IS NULL OR warehouse_id = ANY($11))
+        ORDER BY id DESC
+        LIMIT $10"#,
+        filter.cursor, filter.product_id as _, filter.warehouse_id as _, filter.user_id as _,
+        filter.movement_type, filter.reference_type, filter.reference_id,
+        filter.date_from, date_to, limit + 1, warehouses as _
     )
     .fetch_all(pool)
     .await?;
+
+    let next_cursor = if entries.len() as i64 > limit {
+        entries.truncate(limit as usize);
+        entries.last().and_then(|entry| entry.id).map(|id| id.0)
+    } else {
+        None
+    };
+    Ok(StockLedgerPage { entries, next_cursor })
+}
+
+/// A ledger row written alongside a change to `product_stock`.
+pub struct NewStockLedgerEntry<'a> {
+    pub product_id: ProductId,
+    pub warehouse_id: WarehouseId,
+    pub user_id: Option<UserId>,
+    pub movement_type: &'a str,
+    pub reference_id: i32,
+    pub reference_type: &'a str,
+    pub quantity_change: i32,
+    pub new_balance: i32,
+    pub notes: Option<&'a str>,
+    /// The ledger entry this one compensates, for reversals.
+    pub reversal_of: Option<StockLedgerId>,
+}
+
+pub async fn insert(conn: &mut PgConnection, entry: NewStockLedgerEntry<'_>) -> Result<StockLedgerDto, Error> {
+    let rec = sqlx::query_as!(StockLedgerDto,
+        r#"INSERT INTO stock_ledger (product_id, warehouse_id, user_id, movement_type, reference_id, reference_type, quantity_change, new_balance, notes, reversal_of)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        RETURNING id AS "id: _", product_id AS "product_id: _", warehouse_id AS "warehouse_id: _", user_id AS "user_id: _", movement_type, reference_id, reference_type, quantity_change, new_balance, notes, reversal_of AS "reversal_of: _", created_at"#,
+        entry.product_id as _, entry.warehouse_id as _, entry.user_id as _, entry.movement_type, entry.reference_id,
+        entry.reference_type, entry.quantity_change, entry.new_balance, entry.notes, entry.reversal_of as _
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(rec)
+}
+
+/// Ledger entries a document posted that have not been reversed yet.
+pub async fn get_unreversed_by_reference(conn: &mut PgConnection, reference_type: &str, reference_id: i32) -> Result<Vec<StockLedgerDto>, Error> {
+    let entries = sqlx::query_as!(StockLedgerDto,
+        r#"SELECT id AS "id: _", product_id AS "product_id: _", warehouse_id AS "warehouse_id: _", user_id AS "user_id: _", movement_type, reference_id, reference_type, quantity_change, new_balance, notes, reversal_of AS "reversal_of: _", created_at
+        FROM stock_ledger l
+        WHERE reference_type = $1 AND reference_id = $2 AND reversal_of IS NULL
+            AND NOT EXISTS (SELECT 1 FROM stock_ledger r WHERE r.reversal_of = l.id)
+        ORDER BY id"#,
+        reference_type, reference_id
+    )
+    .fetch_all(conn)
+    .await?;
     Ok(entries)
 }
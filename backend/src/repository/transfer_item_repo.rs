@@ -0,0 +1,94 @@
+use crate::dto::transfer_dto::{TransferItemDto, InTransitDto, InTransitFilter, CreateTransferItemRequest, UpdateTransferItemRequest};
+use crate::config::database::DbPool;
+use crate::models::ids::{TransferId, TransferItemId, WarehouseId};
+use sqlx::{Error, PgConnection, PgExecutor};
+
+pub async fn get_by_transfer<'e, E: PgExecutor<'e>>(executor: E, transfer_id: TransferId) -> Result<Vec<TransferItemDto>, Error> {
+    let items = sqlx::query_as!(TransferItemDto,
+        r#"SELECT id AS "id: _", transfer_id AS "transfer_id: _", product_id AS "product_id: _", quantity, shipped_quantity, received_quantity, discrepancy_quantity, discrepancy_notes, created_at FROM transfer_items WHERE transfer_id = $1 ORDER BY id"#,
+        transfer_id as _
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(items)
+}
+
+pub async fn create(conn: &mut PgConnection, transfer_id: TransferId, req: CreateTransferItemRequest) -> Result<TransferItemDto, Error> {
+    let rec = sqlx::query_as!(TransferItemDto,
+        r#"INSERT INTO transfer_items (transfer_id, product_id, quantity) VALUES ($1, $2, $3)
+        RETURNING id AS "id: _", transfer_id AS "transfer_id: _", product_id AS "product_id: _", quantity, shipped_quantity, received_quantity, discrepancy_quantity, discrepancy_notes, created_at"#,
+        transfer_id as _, req.product_id as _, req.quantity
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(rec)
+}
+
+pub async fn update(conn: &mut PgConnection, transfer_id: TransferId, id: TransferItemId, req: UpdateTransferItemRequest) -> Result<Option<TransferItemDto>, Error> {
+    let rec = sqlx::query_as!(TransferItemDto,
+        r#"UPDATE transfer_items SET product_id = COALESCE($1, product_id), quantity = COALESCE($2, quantity)
+        WHERE id = $3 AND transfer_id = $4 RETURNING id AS "id: _", transfer_id AS "transfer_id: _", product_id AS "product_id: _", quantity, shipped_quantity, received_quantity, discrepancy_quantity, discrepancy_notes, created_at"#,
+        req.product_id as _, req.quantity, id as _, transfer_id as _
+    )
+    .fetch_optional(conn)
+    .await?;
+    Ok(rec)
+}
+
+pub async fn delete(conn: &mut PgConnection, transfer_id: TransferId, id: TransferItemId) -> Result<bool, Error> {
+    let result = sqlx::query!("DELETE FROM transfer_items WHERE id = $1 AND transfer_id = $2", id as _, transfer_id as _)
+        .execute(conn)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn mark_shipped(conn: &mut PgConnection, transfer_id: TransferId, id: TransferItemId) -> Result<(), Error> {
+    sqlx::query!(
+        "UPDATE transfer_items SET shipped_quantity = quantity WHERE id = $1 AND transfer_id = $2",
+        id as _, transfer_id as _
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Books received and written-off units against a shipped line. Returns `None`
+/// if the line does not exist or the quantities exceed what is still in transit.
+pub async fn receive(
+    conn: &mut PgConnection,
+    transfer_id: TransferId,
+    id: TransferItemId,
+    received: i32,
+    discrepancy: i32,
+    notes: Option<&str>,
+) -> Result<Option<TransferItemDto>, Error> {
+    let rec = sqlx::query_as!(TransferItemDto,
+        r#"UPDATE transfer_items SET received_quantity = received_quantity + $3, discrepancy_quantity = discrepancy_quantity + $4,
+            discrepancy_notes = COALESCE($5, discrepancy_notes)
+        WHERE id = $1 AND transfer_id = $2 AND shipped_quantity - received_quantity - discrepancy_quantity >= $3::INTEGER + $4::INTEGER
+        RETURNING id AS "id: _", transfer_id AS "transfer_id: _", product_id AS "product_id: _", quantity, shipped_quantity, received_quantity, discrepancy_quantity, discrepancy_notes, created_at"#,
+        id as _, transfer_id as _, received, discrepancy, notes
+    )
+    .fetch_optional(conn)
+    .await?;
+    Ok(rec)
+}
+
+pub async fn get_in_transit(pool: &DbPool, filter: InTransitFilter, warehouses: Option<&[WarehouseId]>) -> Result<Vec<InTransitDto>, Error> {
+    let rows = sqlx::query_as!(InTransitDto,
+        r#"SELECT ti.transfer_id AS "transfer_id: _", ti.id AS "item_id: _", ti.product_id AS "product_id: _", t.from_warehouse_id AS "from_warehouse_id: _", t.to_warehouse_id AS "to_warehouse_id: _",
+            ti.shipped_quantity - ti.received_quantity - ti.discrepancy_quantity AS quantity, t.shipped_at
+        FROM transfer_items ti
+        JOIN transfers t ON t.id = ti.transfer_id
+        WHERE t.status = 'in_transit'
+            AND ti.shipped_quantity - ti.received_quantity - ti.discrepancy_quantity > 0
+            AND ($1::INTEGER IS NULL OR ti.product_id = $1)
+            AND ($2::INTEGER IS NULL OR t.from_warehouse_id = $2 OR t.to_warehouse_id = $2)
+            AND ($3::INTEGER[] IS NULL OR t.from_warehouse_id = ANY($3) OR t.to_warehouse_id = ANY($3))
+        ORDER BY ti.transfer_id, ti.id"#,
+        filter.product_id as _, filter.warehouse_id as _, warehouses as _
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
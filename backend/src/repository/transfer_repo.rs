@@ -1,41 +1,117 @@
-use crate::dto::transfer_dto::{TransferDto, CreateTransferRequest, UpdateTransferRequest};
-use crate::config::database::DbPool;
-use sqlx::Error;
+use crate::dto::transfer_dto::{TransferDto, TransferFilter, CreateTransferRequest, UpdateTransferRequest};
+use crate::config::database::{self, DbPool};
+use crate::dto::list_dto::{sort_column, ListParams, Page};
+use crate::models::ids::{TransferId, UserId, WarehouseId};
+use sqlx::{Error, PgConnection, PgExecutor};
 
-pub async fn get_all(pool: &DbPool) -> Result<Vec<TransferDto>, Error> {
+/// One page of transfers touching the given warehouses (all when `None`)
+/// on either end, filtered and sorted as asked.
+pub async fn get_all(pool: &DbPool, params: &ListParams, filter: &TransferFilter, warehouses: Option<&[WarehouseId]>) -> Result<Page<TransferDto>, Error> {
+    let date_to = filter.date_to.and_then(|date| date.succ_opt());
     let transfers = sqlx::query_as!(TransferDto,
-        r#"SELECT id, from_warehouse_id, to_warehouse_id, user_id, status, created_at, updated_at FROM transfers ORDER BY id"#
+        r#"SELECT id AS "id: _", from_warehouse_id AS "from_warehouse_id: _", to_warehouse_id AS "to_warehouse_id: _", user_id AS "user_id: _", status, created_at, updated_at
+        FROM transfers
+        WHERE ($1::INTEGER[] IS NULL OR from_warehouse_id = ANY($1) OR to_warehouse_id = ANY($1))
+            AND ($2::TEXT IS NULL OR status = $2)
+            AND ($3::INTEGER IS NULL OR from_warehouse_id = $3 OR to_warehouse_id = $3)
+            AND ($4::DATE IS NULL OR created_at >= $4)
+            AND ($5::DATE IS NULL OR created_at < $5)
+        ORDER BY
+            CASE WHEN NOT $7 THEN CASE $6 WHEN 'status' THEN status END END,
+            CASE WHEN $7 THEN CASE $6 WHEN 'status' THEN status END END DESC,
+            CASE WHEN NOT $7 THEN CASE $6 WHEN 'created_at' THEN created_at WHEN 'updated_at' THEN updated_at END END,
+            CASE WHEN $7 THEN CASE $6 WHEN 'created_at' THEN created_at WHEN 'updated_at' THEN updated_at END END DESC,
+            CASE WHEN $7 THEN id END DESC,
+            id
+        LIMIT $8 OFFSET $9"#,
+        warehouses as _, filter.status, filter.warehouse_id as _, filter.date_from, date_to, sort_column(filter.sort), params.descending(), params.limit(), params.offset()
     )
     .fetch_all(pool)
     .await?;
-    Ok(transfers)
+    let total = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM transfers
+        WHERE ($1::INTEGER[] IS NULL OR from_warehouse_id = ANY($1) OR to_warehouse_id = ANY($1))
+            AND ($2::TEXT IS NULL OR status = $2)
+            AND ($3::INTEGER IS NULL OR from_warehouse_id = $3 OR to_warehouse_id = $3)
+            AND ($4::DATE IS NULL OR created_at >= $4)
+            AND ($5::DATE IS NULL OR created_at < $5)"#,
+        warehouses as _, filter.status, filter.warehouse_id as _, filter.date_from, date_to
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(params.page_of(transfers, total))
 }
 
-pub async fn create(pool: &DbPool, req: CreateTransferRequest) -> Result<TransferDto, Error> {
+pub async fn get_by_id(pool: &DbPool, id: TransferId) -> Result<Option<TransferDto>, Error> {
     let rec = sqlx::query_as!(TransferDto,
-        r#"INSERT INTO transfers (from_warehouse_id, to_warehouse_id, user_id, status) VALUES ($1, $2, $3, $4)
-        RETURNING id, from_warehouse_id, to_warehouse_id, user_id, status, created_at, updated_at"#,
-        req.from_warehouse_id, req.to_warehouse_id, req.user_id, req.status.unwrap_or("".to_string())
+        r#"SELECT id AS "id: _", from_warehouse_id AS "from_warehouse_id: _", to_warehouse_id AS "to_warehouse_id: _", user_id AS "user_id: _", status, created_at, updated_at FROM transfers WHERE id = $1"#,
+        id as _
     )
-    .fetch_one(pool)
+    .fetch_optional(pool)
     .await?;
     Ok(rec)
 }
 
-pub async fn update(pool: &DbPool, id: i32, req: UpdateTransferRequest) -> Result<TransferDto, Error> {
+pub async fn create(pool: &DbPool, req: CreateTransferRequest, user_id: UserId) -> Result<TransferDto, Error> {
+    let mut tx = database::begin(pool).await?;
     let rec = sqlx::query_as!(TransferDto,
-        r#"UPDATE transfers SET from_warehouse_id = COALESCE($1, from_warehouse_id), to_warehouse_id = COALESCE($2, to_warehouse_id), user_id = COALESCE($3, user_id), status = COALESCE($4, status), updated_at = NOW()
-        WHERE id = $5 RETURNING id, from_warehouse_id, to_warehouse_id, user_id, status, created_at, updated_at"#,
-        req.from_warehouse_id, req.to_warehouse_id, req.user_id, req.status.unwrap_or("".to_string()), id
+        r#"INSERT INTO transfers (from_warehouse_id, to_warehouse_id, user_id, status) VALUES ($1, $2, $3, 'draft')
+        RETURNING id AS "id: _", from_warehouse_id AS "from_warehouse_id: _", to_warehouse_id AS "to_warehouse_id: _", user_id AS "user_id: _", status, created_at, updated_at"#,
+        req.from_warehouse_id as _, req.to_warehouse_id as _, user_id as _
     )
-    .fetch_one(pool)
+    .fetch_one(&mut *tx)
     .await?;
+    tx.commit().await?;
     Ok(rec)
 }
 
-pub async fn delete(pool: &DbPool, id: i32) -> Result<(), Error> {
-    sqlx::query!("DELETE FROM transfers WHERE id = $1", id)
-        .execute(pool)
+pub async fn update(conn: &mut PgConnection, id: TransferId, req: UpdateTransferRequest) -> Result<TransferDto, Error> {
+    let rec = sqlx::query_as!(TransferDto,
+        r#"UPDATE transfers SET from_warehouse_id = COALESCE($1, from_warehouse_id), to_warehouse_id = COALESCE($2, to_warehouse_id), updated_at = NOW()
+        WHERE id = $3 RETURNING id AS "id: _", from_warehouse_id AS "from_warehouse_id: _", to_warehouse_id AS "to_warehouse_id: _", user_id AS "user_id: _", status, created_at, updated_at"#,
+        req.from_warehouse_id as _, req.to_warehouse_id as _, id as _
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(rec)
+}
+
+pub async fn delete(conn: &mut PgConnection, id: TransferId) -> Result<(), Error> {
+    sqlx::query!("DELETE FROM transfers WHERE id = $1", id as _)
+        .execute(conn)
         .await?;
     Ok(())
 }
+
+/// Loads a transfer and locks its row until the surrounding transaction ends.
+pub async fn find_for_update(conn: &mut PgConnection, id: TransferId) -> Result<Option<TransferDto>, Error> {
+    let rec = sqlx::query_as!(TransferDto,
+        r#"SELECT id AS "id: _", from_warehouse_id AS "from_warehouse_id: _", to_warehouse_id AS "to_warehouse_id: _", user_id AS "user_id: _", status, created_at, updated_at FROM transfers WHERE id = $1 FOR UPDATE"#,
+        id as _
+    )
+    .fetch_optional(conn)
+    .await?;
+    Ok(rec)
+}
+
+pub async fn set_status<'e, E: PgExecutor<'e>>(executor: E, id: TransferId, status: &str) -> Result<TransferDto, Error> {
+    let rec = sqlx::query_as!(TransferDto,
+        r#"UPDATE transfers SET status = $1, updated_at = NOW()
+        WHERE id = $2 RETURNING id AS "id: _", from_warehouse_id AS "from_warehouse_id: _", to_warehouse_id AS "to_warehouse_id: _", user_id AS "user_id: _", status, created_at, updated_at"#,
+        status, id as _
+    )
+    .fetch_one(executor)
+    .await?;
+    Ok(rec)
+}
+
+pub async fn mark_shipped(conn: &mut PgConnection, id: TransferId) -> Result<TransferDto, Error> {
+    let rec = sqlx::query_as!(TransferDto,
+        r#"UPDATE transfers SET status = 'in_transit', shipped_at = NOW(), updated_at = NOW()
+        WHERE id = $1 RETURNING id AS "id: _", from_warehouse_id AS "from_warehouse_id: _", to_warehouse_id AS "to_warehouse_id: _", user_id AS "user_id: _", status, created_at, updated_at"#,
+        id as _
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(rec)
+}
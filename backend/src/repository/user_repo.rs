@@ -1,6 +1,8 @@
+/// Matches the address case-insensitively, as login throttling keys it.
 pub async fn find_by_email(pool: &DbPool, email: &str) -> Result<Option<UserWithPassword>, Error> {
     let rec = sqlx::query_as!(UserWithPassword,
-        r#"SELECT id, name, email, password_hash, is_admin, created_at, updated_at FROM users WHERE email = $1"#,
+        r#"SELECT id AS "id: _", name, email, password_hash, email_verified_at FROM users
+        WHERE LOWER(email) = LOWER($1) ORDER BY id LIMIT 1"#,
         email
     )
     .fetch_optional(pool)
@@ -8,76 +10,163 @@ pub async fn find_by_email(pool: &DbPool, email: &str) -> Result<Option<UserWith
     Ok(rec)
 }
 
-pub async fn create_with_password(pool: &DbPool, name: &str, email: &str, password_hash: &str) -> Result<UserDto, Error> {
+/// Self-registration always creates an operator; the first admin comes from
+/// `create_first_admin`. The address is unverified until the user follows
+/// the mailed link.
+pub async fn create_with_password<'e, E: PgExecutor<'e>>(executor: E, name: &str, email: &str, password_hash: &str) -> Result<UserDto, Error> {
     let rec = sqlx::query_as!(UserDto,
-        r#"INSERT INTO users (name, email, password_hash) VALUES ($1, $2, $3)
-        RETURNING id, name, email, is_admin, created_at, updated_at"#,
+        r#"INSERT INTO users (name, email, password_hash, role)
+        VALUES ($1, $2, $3, 'operator')
+        RETURNING id AS "id: _", name, email, role, created_at, updated_at"#,
         name, email, password_hash
     )
-    .fetch_one(pool)
+    .fetch_one(executor)
+    .await?;
+    Ok(rec)
+}
+
+/// Creates a verified admin, unless an admin already exists or the email is
+/// taken; `None` when nothing was inserted.
+pub async fn create_first_admin(pool: &DbPool, name: &str, email: &str, password_hash: &str) -> Result<Option<UserDto>, Error> {
+    let rec = sqlx::query_as!(UserDto,
+        r#"INSERT INTO users (name, email, password_hash, role, email_verified_at)
+        SELECT $1, $2, $3, 'admin', NOW() WHERE NOT EXISTS (SELECT 1 FROM users WHERE role = 'admin')
+        ON CONFLICT (email) DO NOTHING
+        RETURNING id AS "id: _", name, email, role, created_at, updated_at"#,
+        name, email, password_hash
+    )
+    .fetch_optional(pool)
     .await?;
     Ok(rec)
 }
 
 #[derive(Debug, sqlx::FromRow)]
 pub struct UserWithPassword {
-    pub id: Option<i32>,
+    pub id: Option<UserId>,
     pub name: String,
     pub email: String,
     pub password_hash: String,
-    pub is_admin: Option<bool>,
-    pub created_at: Option<chrono::NaiveDateTime>,
-    pub updated_at: Option<chrono::NaiveDateTime>,
+    pub email_verified_at: Option<chrono::NaiveDateTime>,
 }
-use crate::dto::user_dto::{UserDto, CreateUserRequest, UpdateUserRequest};
-use crate::config::database::DbPool;
-use sqlx::Error;
-use argon2::{Argon2, PasswordHasher};
-use password_hash::{SaltString, PasswordHash, rand_core::OsRng};
+use crate::dto::user_dto::{UserDto, UserFilter, CreateUserRequest, UpdateUserRequest};
+use crate::config::database::{self, DbPool};
+use crate::dto::list_dto::{sort_column, search_pattern, ListParams, Page};
+use crate::models::ids::UserId;
+use crate::models::role::Role;
+use sqlx::{Error, PgConnection, PgExecutor};
 
-pub async fn get_all(pool: &DbPool) -> Result<Vec<UserDto>, Error> {
+/// One page of users, filtered and sorted as asked.
+pub async fn get_all(pool: &DbPool, params: &ListParams, filter: &UserFilter) -> Result<Page<UserDto>, Error> {
+    let pattern = search_pattern(filter.q.as_deref());
     let users = sqlx::query_as!(UserDto,
-        r#"SELECT id, name, email, is_admin, created_at, updated_at FROM users ORDER BY id"#
+        r#"SELECT id AS "id: _", name, email, role, created_at, updated_at
+        FROM users
+        WHERE ($1::TEXT IS NULL OR role = $1)
+            AND ($2::TEXT IS NULL OR name ILIKE $2 OR email ILIKE $2)
+        ORDER BY
+            CASE WHEN NOT $4 THEN CASE $3 WHEN 'name' THEN name WHEN 'email' THEN email WHEN 'role' THEN role END END,
+            CASE WHEN $4 THEN CASE $3 WHEN 'name' THEN name WHEN 'email' THEN email WHEN 'role' THEN role END END DESC,
+            CASE WHEN NOT $4 THEN CASE $3 WHEN 'created_at' THEN created_at END END,
+            CASE WHEN $4 THEN CASE $3 WHEN 'created_at' THEN created_at END END DESC,
+            CASE WHEN $4 THEN id END DESC,
+            id
+        LIMIT $5 OFFSET $6"#,
+        filter.role.map(Role::as_str), pattern, sort_column(filter.sort), params.descending(), params.limit(), params.offset()
     )
     .fetch_all(pool)
     .await?;
-    Ok(users)
+    let total = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM users
+        WHERE ($1::TEXT IS NULL OR role = $1)
+            AND ($2::TEXT IS NULL OR name ILIKE $2 OR email ILIKE $2)"#,
+        filter.role.map(Role::as_str), pattern
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(params.page_of(users, total))
 }
 
-pub async fn create(pool: &DbPool, req: CreateUserRequest) -> Result<UserDto, Error> {
-    let password_hash = hash_password(&req.password);
+pub async fn get_by_id<'e, E: PgExecutor<'e>>(executor: E, id: UserId) -> Result<Option<UserDto>, Error> {
     let rec = sqlx::query_as!(UserDto,
-        r#"INSERT INTO users (name, email, password_hash) VALUES ($1, $2, $3)
-        RETURNING id, name, email, is_admin, created_at, updated_at"#,
-        req.name, req.email, password_hash
+        r#"SELECT id AS "id: _", name, email, role, created_at, updated_at FROM users WHERE id = $1"#,
+        id as _
     )
-    .fetch_one(pool)
+    .fetch_optional(executor)
+    .await?;
+    Ok(rec)
+}
+
+/// Accounts created by an admin are taken as verified.
+pub async fn create(pool: &DbPool, req: CreateUserRequest, password_hash: &str) -> Result<UserDto, Error> {
+    let mut tx = database::begin(pool).await?;
+    let rec = sqlx::query_as!(UserDto,
+        r#"INSERT INTO users (name, email, password_hash, role, email_verified_at) VALUES ($1, $2, $3, COALESCE($4, 'operator'), NOW())
+        RETURNING id AS "id: _", name, email, role, created_at, updated_at"#,
+        req.name, req.email, password_hash, req.role.map(Role::as_str)
+    )
+    .fetch_one(&mut *tx)
     .await?;
+    tx.commit().await?;
     Ok(rec)
 }
 
-pub async fn update(pool: &DbPool, id: i32, req: UpdateUserRequest) -> Result<UserDto, Error> {
-    let password_hash = req.password.as_ref().map(|p| hash_password(p));
+pub async fn update(conn: &mut PgConnection, id: UserId, req: UpdateUserRequest, password_hash: Option<&str>) -> Result<Option<UserDto>, Error> {
     let rec = sqlx::query_as!(UserDto,
         r#"UPDATE users SET name = COALESCE($1, name), email = COALESCE($2, email), password_hash = COALESCE($3, password_hash), updated_at = NOW()
-        WHERE id = $4 RETURNING id, name, email, is_admin, created_at, updated_at"#,
-        req.name, req.email, password_hash, id
+        WHERE id = $4 RETURNING id AS "id: _", name, email, role, created_at, updated_at"#,
+        req.name, req.email, password_hash, id as _
     )
-    .fetch_one(pool)
+    .fetch_optional(conn)
     .await?;
     Ok(rec)
 }
 
-pub async fn delete(pool: &DbPool, id: i32) -> Result<(), Error> {
-    sqlx::query!("DELETE FROM users WHERE id = $1", id)
-        .execute(pool)
+pub async fn delete(conn: &mut PgConnection, id: UserId) -> Result<bool, Error> {
+    let result = sqlx::query!("DELETE FROM users WHERE id = $1", id as _)
+        .execute(conn)
         .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn set_role(conn: &mut PgConnection, id: UserId, role: Role) -> Result<Option<UserDto>, Error> {
+    let rec = sqlx::query_as!(UserDto,
+        r#"UPDATE users SET role = $1, updated_at = NOW()
+        WHERE id = $2 RETURNING id AS "id: _", name, email, role, created_at, updated_at"#,
+        role.as_str(), id as _
+    )
+    .fetch_optional(conn)
+    .await?;
+    Ok(rec)
+}
+
+pub async fn mark_email_verified(conn: &mut PgConnection, id: UserId) -> Result<(), Error> {
+    sqlx::query!(
+        "UPDATE users SET email_verified_at = COALESCE(email_verified_at, NOW()), updated_at = NOW() WHERE id = $1",
+        id as _
+    )
+    .execute(conn)
+    .await?;
     Ok(())
 }
 
-fn hash_password(password: &str) -> String {
-    let salt = SaltString::generate(&mut OsRng);
-    let argon2 = Argon2::default();
-    let password_hash = argon2.hash_password(password.as_bytes(), &salt).unwrap().to_string();
-    password_hash
+pub async fn set_password_hash<'e, E: PgExecutor<'e>>(executor: E, id: UserId, password_hash: &str) -> Result<(), Error> {
+    sqlx::query!(
+        "UPDATE users SET password_hash = $1, updated_at = NOW() WHERE id = $2",
+        password_hash, id as _
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
 }
+
+/// Locks every admin row and returns their ids, so a concurrent demotion or
+/// delete cannot leave the install without an admin.
+pub async fn lock_admins(conn: &mut PgConnection) -> Result<Vec<UserId>, Error> {
+    let ids = sqlx::query_scalar!(
+        r#"SELECT id AS "id: UserId" FROM users WHERE role = 'admin' ORDER BY id FOR UPDATE"#
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(ids)
+}
+
@@ -0,0 +1,37 @@
+use crate::models::ids::UserId;
+use crate::models::user_token::TokenPurpose;
+use chrono::{NaiveDateTime, Utc};
+use sqlx::{Error, PgConnection};
+
+/// Stores a new token for the user, discarding any earlier unused one for the
+/// same purpose so only the most recent link works.
+pub async fn create(conn: &mut PgConnection, user_id: UserId, purpose: TokenPurpose, token_hash: &str) -> Result<NaiveDateTime, Error> {
+    sqlx::query!(
+        "DELETE FROM user_tokens WHERE user_id = $1 AND purpose = $2 AND used_at IS NULL",
+        user_id as _, purpose.as_str()
+    )
+    .execute(&mut *conn)
+    .await?;
+    let expires_at = (Utc::now() + purpose.lifetime()).naive_utc();
+    sqlx::query!(
+        "INSERT INTO user_tokens (user_id, purpose, token_hash, expires_at) VALUES ($1, $2, $3, $4)",
+        user_id as _, purpose.as_str(), token_hash, expires_at
+    )
+    .execute(conn)
+    .await?;
+    Ok(expires_at)
+}
+
+/// Marks the token used and returns its user, or None when it is unknown,
+/// expired or already used. A single UPDATE, so a token cannot be spent twice.
+pub async fn consume(conn: &mut PgConnection, purpose: TokenPurpose, token_hash: &str) -> Result<Option<UserId>, Error> {
+    let user_id = sqlx::query_scalar!(
+        r#"UPDATE user_tokens SET used_at = NOW()
+        WHERE token_hash = $1 AND purpose = $2 AND used_at IS NULL AND expires_at > NOW()
+        RETURNING user_id AS "user_id: UserId""#,
+        token_hash, purpose.as_str()
+    )
+    .fetch_optional(conn)
+    .await?;
+    Ok(user_id)
+}
@@ -0,0 +1,26 @@
+use crate::models::ids::{UserId, WarehouseId};
+use sqlx::{Error, PgConnection, PgExecutor};
+
+pub async fn get_by_user<'e, E: PgExecutor<'e>>(executor: E, user_id: UserId) -> Result<Vec<WarehouseId>, Error> {
+    let ids = sqlx::query_scalar!(
+        r#"SELECT warehouse_id AS "warehouse_id: WarehouseId" FROM user_warehouses WHERE user_id = $1 ORDER BY warehouse_id"#,
+        user_id as _
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(ids)
+}
+
+/// Replaces the user's assignments with exactly `warehouse_ids`.
+pub async fn replace(conn: &mut PgConnection, user_id: UserId, warehouse_ids: &[WarehouseId]) -> Result<(), Error> {
+    sqlx::query!("DELETE FROM user_warehouses WHERE user_id = $1", user_id as _)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!(
+        "INSERT INTO user_warehouses (user_id, warehouse_id) SELECT $1, UNNEST($2::INTEGER[]) ON CONFLICT DO NOTHING",
+        user_id as _, warehouse_ids as _
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
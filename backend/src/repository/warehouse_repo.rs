@@ -1,41 +1,69 @@
-use crate::dto::warehouse_dto::{WarehouseDto, CreateWarehouseRequest, UpdateWarehouseRequest};
-use crate::config::database::DbPool;
+use crate::dto::warehouse_dto::{WarehouseDto, WarehouseFilter, CreateWarehouseRequest, UpdateWarehouseRequest};
+use crate::config::database::{self, DbPool};
+use crate::dto::list_dto::{sort_column, search_pattern, ListParams, Page};
+use crate::models::ids::WarehouseId;
 use sqlx::Error;
 
-pub async fn get_all(pool: &DbPool) -> Result<Vec<WarehouseDto>, Error> {
+/// One page of warehouses, filtered and sorted as asked.
+pub async fn get_all(pool: &DbPool, params: &ListParams, filter: &WarehouseFilter) -> Result<Page<WarehouseDto>, Error> {
+    let pattern = search_pattern(filter.q.as_deref());
     let warehouses = sqlx::query_as!(WarehouseDto,
-        r#"SELECT id, name, location, created_at, updated_at FROM warehouses ORDER BY id"#
+        r#"SELECT id AS "id: _", name, location, negative_stock_policy, created_at, updated_at
+        FROM warehouses
+        WHERE ($1::TEXT IS NULL OR name ILIKE $1 OR location ILIKE $1)
+        ORDER BY
+            CASE WHEN NOT $3 THEN CASE $2 WHEN 'name' THEN name END END,
+            CASE WHEN $3 THEN CASE $2 WHEN 'name' THEN name END END DESC,
+            CASE WHEN NOT $3 THEN CASE $2 WHEN 'created_at' THEN created_at END END,
+            CASE WHEN $3 THEN CASE $2 WHEN 'created_at' THEN created_at END END DESC,
+            CASE WHEN $3 THEN id END DESC,
+            id
+        LIMIT $4 OFFSET $5"#,
+        pattern, sort_column(filter.sort), params.descending(), params.limit(), params.offset()
     )
     .fetch_all(pool)
     .await?;
-    Ok(warehouses)
+    let total = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM warehouses
+        WHERE ($1::TEXT IS NULL OR name ILIKE $1 OR location ILIKE $1)"#,
+        pattern
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(params.page_of(warehouses, total))
 }
 
 pub async fn create(pool: &DbPool, req: CreateWarehouseRequest) -> Result<WarehouseDto, Error> {
+    let mut tx = database::begin(pool).await?;
     let rec = sqlx::query_as!(WarehouseDto,
-        r#"INSERT INTO warehouses (name, location) VALUES ($1, $2)
-        RETURNING id, name, location, created_at, updated_at"#,
-        req.name, req.location
+        r#"INSERT INTO warehouses (name, location, negative_stock_policy) VALUES ($1, $2, $3)
+        RETURNING id AS "id: _", name, location, negative_stock_policy, created_at, updated_at"#,
+        req.name, req.location, req.negative_stock_policy
     )
-    .fetch_one(pool)
+    .fetch_one(&mut *tx)
     .await?;
+    tx.commit().await?;
     Ok(rec)
 }
 
-pub async fn update(pool: &DbPool, id: i32, req: UpdateWarehouseRequest) -> Result<WarehouseDto, Error> {
+pub async fn update(pool: &DbPool, id: WarehouseId, req: UpdateWarehouseRequest) -> Result<Option<WarehouseDto>, Error> {
+    let mut tx = database::begin(pool).await?;
     let rec = sqlx::query_as!(WarehouseDto,
-        r#"UPDATE warehouses SET name = COALESCE($1, name), location = COALESCE($2, location), updated_at = NOW()
-        WHERE id = $3 RETURNING id, name, location, created_at, updated_at"#,
-        req.name, req.location, id
+        r#"UPDATE warehouses SET name = COALESCE($1, name), location = COALESCE($2, location), negative_stock_policy = COALESCE($3, negative_stock_policy), updated_at = NOW()
+        WHERE id = $4 RETURNING id AS "id: _", name, location, negative_stock_policy, created_at, updated_at"#,
+        req.name, req.location, req.negative_stock_policy, id as _
     )
-    .fetch_one(pool)
+    .fetch_optional(&mut *tx)
     .await?;
+    tx.commit().await?;
     Ok(rec)
 }
 
-pub async fn delete(pool: &DbPool, id: i32) -> Result<(), Error> {
-    sqlx::query!("DELETE FROM warehouses WHERE id = $1", id)
-        .execute(pool)
+pub async fn delete(pool: &DbPool, id: WarehouseId) -> Result<bool, Error> {
+    let mut tx = database::begin(pool).await?;
+    let result = sqlx::query!("DELETE FROM warehouses WHERE id = $1", id as _)
+        .execute(&mut *tx)
         .await?;
-    Ok(())
+    tx.commit().await?;
+    Ok(result.rows_affected() > 0)
 }
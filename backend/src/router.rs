@@ -1,17 +1,38 @@
-use axum::{Router, routing::{get, post, put, delete}};
+use axum::{Router, middleware::{from_fn, from_fn_with_state}, routing::{delete, get, post, put}};
 use crate::handlers;
 use crate::config::database::DbPool;
-use axum::extract::State;
+use crate::middleware::{audit_context, require_auth};
 
 pub fn create_router(pool: DbPool) -> Router {
-    Router::new()
+    let public = Router::new()
         // Auth
         .route("/api/v1/auth/register", post(handlers::auth::register))
         .route("/api/v1/auth/login", post(handlers::auth::login))
+        .route("/api/v1/auth/refresh", post(handlers::auth::refresh))
+        .route("/api/v1/auth/verify_email", post(handlers::auth::verify_email))
+        .route("/api/v1/auth/verify_email/request", post(handlers::auth::request_email_verification))
+        .route("/api/v1/auth/password_reset", post(handlers::auth::reset_password))
+        .route("/api/v1/auth/password_reset/request", post(handlers::auth::request_password_reset))
 
-        // Users, Products, Warehouses, Categories
+        // API docs
+        .route("/api/v1/openapi.json", get(handlers::openapi::spec))
+        .route("/api/v1/docs", get(handlers::openapi::docs));
+
+    // Everything else needs a valid bearer token
+    let protected = Router::new()
+        .route("/api/v1/auth/logout", post(handlers::auth::logout))
+        .route("/api/v1/auth/logout_all", post(handlers::auth::logout_all))
+
+        // Users & Roles, Products, Warehouses, Categories
         .route("/api/v1/users", get(handlers::users::list_users).post(handlers::users::create_user))
         .route("/api/v1/users/:id", put(handlers::users::update_user).delete(handlers::users::delete_user))
+        .route("/api/v1/users/:id/role", put(handlers::users::assign_role))
+        .route("/api/v1/users/:id/warehouses", get(handlers::users::get_user_warehouses).put(handlers::users::assign_user_warehouses))
+        .route("/api/v1/users/:id/sessions", delete(handlers::users::revoke_user_sessions))
+        .route("/api/v1/users/:id/unlock", post(handlers::users::unlock_user))
+        .route("/api/v1/api_keys", get(handlers::api_keys::list_api_keys).post(handlers::api_keys::create_api_key))
+        .route("/api/v1/api_keys/:id", delete(handlers::api_keys::revoke_api_key))
+        .route("/api/v1/roles", get(handlers::users::list_roles))
         .route("/api/v1/products", get(handlers::products::list_products).post(handlers::products::create_product))
         .route("/api/v1/products/:id", put(handlers::products::update_product).delete(handlers::products::delete_product))
         .route("/api/v1/warehouses", get(handlers::warehouses::list_warehouses).post(handlers::warehouses::create_warehouse))
@@ -21,24 +42,82 @@ pub fn create_router(pool: DbPool) -> Router {
 
         // Receipts, Deliveries, Transfers, Adjustments
         .route("/api/v1/receipts", get(handlers::receipts::list_receipts).post(handlers::receipts::create_receipt))
-        .route("/api/v1/receipts/:id", put(handlers::receipts::update_receipt).delete(handlers::receipts::delete_receipt))
+        .route("/api/v1/receipts/:id", get(handlers::receipts::get_receipt).put(handlers::receipts::update_receipt).delete(handlers::receipts::delete_receipt))
+        .route("/api/v1/receipts/:id/items", get(handlers::receipts::list_receipt_items).post(handlers::receipts::add_receipt_item))
+        .route("/api/v1/receipts/:id/items/:item_id", put(handlers::receipts::update_receipt_item).delete(handlers::receipts::delete_receipt_item))
+        .route("/api/v1/receipts/:id/validate", post(handlers::receipts::validate_receipt))
+        .route("/api/v1/receipts/:id/reverse", post(handlers::receipts::reverse_receipt))
         .route("/api/v1/deliveries", get(handlers::deliveries::list_deliveries).post(handlers::deliveries::create_delivery))
-        .route("/api/v1/deliveries/:id", put(handlers::deliveries::update_delivery).delete(handlers::deliveries::delete_delivery))
+        .route("/api/v1/deliveries/:id", get(handlers::deliveries::get_delivery).put(handlers::deliveries::update_delivery).delete(handlers::deliveries::delete_delivery))
+        .route("/api/v1/deliveries/:id/items", get(handlers::deliveries::list_delivery_items).post(handlers::deliveries::add_delivery_item))
+        .route("/api/v1/deliveries/:id/items/:item_id", put(handlers::deliveries::update_delivery_item).delete(handlers::deliveries::delete_delivery_item))
+        .route("/api/v1/deliveries/:id/pick", post(handlers::deliveries::pick_delivery))
+        .route("/api/v1/deliveries/:id/pack", post(handlers::deliveries::pack_delivery))
+        .route("/api/v1/deliveries/:id/deliver", post(handlers::deliveries::complete_delivery))
+        .route("/api/v1/deliveries/:id/cancel", post(handlers::deliveries::cancel_delivery))
+        .route("/api/v1/deliveries/:id/reverse", post(handlers::deliveries::reverse_delivery))
         .route("/api/v1/transfers", get(handlers::transfers::list_transfers).post(handlers::transfers::create_transfer))
-        .route("/api/v1/transfers/:id", put(handlers::transfers::update_transfer).delete(handlers::transfers::delete_transfer))
+        .route("/api/v1/transfers/:id", get(handlers::transfers::get_transfer).put(handlers::transfers::update_transfer).delete(handlers::transfers::delete_transfer))
+        .route("/api/v1/transfers/:id/items", get(handlers::transfers::list_transfer_items).post(handlers::transfers::add_transfer_item))
+        .route("/api/v1/transfers/:id/items/:item_id", put(handlers::transfers::update_transfer_item).delete(handlers::transfers::delete_transfer_item))
+        .route("/api/v1/transfers/:id/ship", post(handlers::transfers::ship_transfer))
+        .route("/api/v1/transfers/:id/receive", post(handlers::transfers::receive_transfer))
+        .route("/api/v1/transfers/:id/reverse", post(handlers::transfers::reverse_transfer))
         .route("/api/v1/adjustments", get(handlers::adjustments::list_adjustments).post(handlers::adjustments::create_adjustment))
-        .route("/api/v1/adjustments/:id", put(handlers::adjustments::update_adjustment).delete(handlers::adjustments::delete_adjustment))
+        .route("/api/v1/adjustments/:id", put(handlers::adjustments::update_adjustment))
+        .route("/api/v1/adjustments/:id/reverse", post(handlers::adjustments::reverse_adjustment))
 
         // Stock & Ledger
         .route("/api/v1/stock_ledger", get(handlers::stock_ledger::list_stock_ledger))
         .route("/api/v1/stock/current", get(handlers::stock::current_stock))
         .route("/api/v1/stock/by_product/:product_id", get(handlers::stock::stock_by_product))
         .route("/api/v1/stock/by_warehouse/:warehouse_id", get(handlers::stock::stock_by_warehouse))
+        .route("/api/v1/stock/in_transit", get(handlers::stock::in_transit_stock))
 
         // Dashboard & Move History
         .route("/api/v1/dashboard/summary", get(handlers::dashboard::summary))
         .route("/api/v1/move_history", get(handlers::move_history::list_moves))
 
+        // Audit
+        .route("/api/v1/audit", get(handlers::audit::list_audit))
+        .route_layer(from_fn_with_state(pool.clone(), require_auth));
 
+    public
+        .merge(protected)
+        .layer(from_fn(audit_context))
         .with_state(pool)
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+    use axum::{body::{self, Body}, http::{header, Request, StatusCode}};
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    async fn get_json(pool: &DbPool, key: &str, uri: &str) -> (StatusCode, Option<String>, Value) {
+        let request = Request::get(uri).header("x-api-key", key).body(Body::empty()).unwrap();
+        let response = create_router(pool.clone()).oneshot(request).await.unwrap();
+        let status = response.status();
+        let content_type = response.headers().get(header::CONTENT_TYPE).map(|v| v.to_str().unwrap().to_string());
+        let bytes = body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, content_type, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn malformed_path_and_query_parameters_get_a_json_400(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let user_id = test_support::user(&pool, "a@x.io", "-").await;
+        let key = test_support::api_key(&pool, user_id, &["view_reports"]).await;
+
+        let (status, content_type, body) = get_json(&pool, &key, "/api/v1/receipts/abc").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(content_type.as_deref(), Some("application/json"));
+        assert_eq!(body["code"], "invalid_path");
+
+        let (status, content_type, body) = get_json(&pool, &key, "/api/v1/stock_ledger?cursor=abc").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(content_type.as_deref(), Some("application/json"));
+        assert_eq!(body["code"], "invalid_query");
+    }
+}
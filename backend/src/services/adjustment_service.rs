@@ -0,0 +1,179 @@
+use crate::repository::{adjustment_repo, product_stock_repo, stock_ledger_repo};
+use crate::services::stock_service;
+use crate::repository::stock_ledger_repo::NewStockLedgerEntry;
+use crate::dto::adjustment_dto::{AdjustmentDto, CreateAdjustmentRequest, UpdateAdjustmentRequest};
+use crate::models::ids::{AdjustmentId, UserId};
+use crate::config::database::{self, DbPool};
+use crate::error::{AppError, Result};
+use crate::middleware::WarehouseScope;
+
+/// Records an adjustment and applies it to stock in one transaction.
+///
+/// With `quantity_change` the delta is applied as given. With `counted_quantity`
+/// the stock row is locked and the delta is whatever brings on-hand to the count.
+/// Either way, stock reserved by picked deliveries cannot be adjusted away
+/// under the `forbid` policy.
+pub async fn create_adjustment(pool: &DbPool, req: CreateAdjustmentRequest, user_id: UserId, scope: &WarehouseScope) -> Result<AdjustmentDto> {
+    scope.ensure(req.warehouse_id)?;
+    let mut tx = database::begin(pool).await?;
+
+    let level = product_stock_repo::lock(&mut tx, req.product_id, req.warehouse_id).await?;
+    let on_hand = level.quantity;
+    let quantity_change = match (req.quantity_change, req.counted_quantity) {
+        (Some(change), None) if change != 0 => change,
+        (Some(_), None) => return Err(AppError::BadRequest("quantity_change cannot be zero".to_string())),
+        (None, Some(counted)) if counted >= 0 => counted
+            .checked_sub(on_hand)
+            .ok_or_else(|| AppError::BadRequest("counted_quantity is too far from the quantity on hand".to_string()))?,
+        (None, Some(_)) => return Err(AppError::BadRequest("counted_quantity cannot be negative".to_string())),
+        _ => {
+            return Err(AppError::BadRequest(
+                "Provide exactly one of quantity_change or counted_quantity".to_string(),
+            ))
+        }
+    };
+    if quantity_change < 0 {
+        stock_service::ensure_can_remove(&mut tx, req.product_id, req.warehouse_id, on_hand - level.reserved_quantity, -quantity_change).await?;
+    }
+
+    let adjustment = adjustment_repo::create(&mut tx, &req, user_id, quantity_change).await?;
+    let new_balance = product_stock_repo::add_quantity(&mut tx, req.product_id, req.warehouse_id, quantity_change).await?;
+    stock_ledger_repo::insert(&mut tx, NewStockLedgerEntry {
+        product_id: req.product_id,
+        warehouse_id: req.warehouse_id,
+        user_id: Some(user_id),
+        movement_type: "Adjustment",
+        reference_id: adjustment.id.unwrap_or_default().0,
+        reference_type: "adjustment",
+        quantity_change,
+        new_balance,
+        notes: Some(&req.reason),
+        reversal_of: None,
+    })
+    .await?;
+
+    tx.commit().await?;
+    Ok(adjustment)
+}
+
+/// Only the reason and notes can change; the posted quantity cannot.
+pub async fn update_adjustment(pool: &DbPool, adjustment_id: AdjustmentId, req: UpdateAdjustmentRequest, scope: &WarehouseScope) -> Result<AdjustmentDto> {
+    let mut tx = database::begin(pool).await?;
+    adjustment_repo::find_for_update(&mut tx, adjustment_id)
+        .await?
+        .filter(|adjustment| scope.allows(adjustment.warehouse_id))
+        .ok_or_else(|| AppError::NotFound(format!("Adjustment {adjustment_id}")))?;
+    let adjustment = adjustment_repo::update(&mut tx, adjustment_id, req).await?;
+    tx.commit().await?;
+    Ok(adjustment)
+}
+
+/// Adjustments are posted as soon as they are created, so they are never
+/// deleted. Reversing one writes the compensating ledger entry and marks it cancelled.
+pub async fn reverse_adjustment(pool: &DbPool, adjustment_id: AdjustmentId, user_id: UserId, scope: &WarehouseScope) -> Result<AdjustmentDto> {
+    let mut tx = database::begin(pool).await?;
+    let adjustment = adjustment_repo::find_for_update(&mut tx, adjustment_id)
+        .await?
+        .filter(|adjustment| scope.allows(adjustment.warehouse_id))
+        .ok_or_else(|| AppError::NotFound(format!("Adjustment {adjustment_id}")))?;
+    if adjustment.status.as_deref() != Some("posted") {
+        return Err(AppError::Conflict(format!(
+            "Adjustment is '{}'; only posted adjustments can be reversed",
+            adjustment.status.unwrap_or_default()
+        )));
+    }
+    stock_service::reverse_ledger_entries(&mut tx, "adjustment", adjustment_id.0, user_id).await?;
+    let adjustment = adjustment_repo::set_status(&mut tx, adjustment_id, "cancelled").await?;
+    tx.commit().await?;
+    Ok(adjustment)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ids::{ProductId, WarehouseId};
+    use crate::test_support;
+
+    fn request(product_id: ProductId, warehouse_id: WarehouseId, quantity_change: Option<i32>, counted_quantity: Option<i32>) -> CreateAdjustmentRequest {
+        CreateAdjustmentRequest { product_id, warehouse_id, reason: "Damage".to_string(), quantity_change, counted_quantity, notes: None }
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn negative_adjustments_cannot_take_reserved_stock(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let user_id = test_support::user(&pool, "a@x.io", "-").await;
+        let warehouse_id = test_support::warehouse(&pool, "Main").await;
+        let bolts = test_support::product(&pool, "BOLT").await;
+        test_support::stock_in(&pool, bolts, warehouse_id, 10).await;
+        test_support::reserve(&pool, bolts, warehouse_id, 6).await;
+
+        let too_much = create_adjustment(&pool, request(bolts, warehouse_id, Some(-5), None), user_id, &WarehouseScope::All).await;
+        assert!(matches!(too_much, Err(AppError::InsufficientStock { available: 4, requested: 5, .. })));
+
+        create_adjustment(&pool, request(bolts, warehouse_id, Some(-4), None), user_id, &WarehouseScope::All).await.unwrap();
+        assert_eq!(test_support::stock(&pool, bolts, warehouse_id).await, (6, 6));
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn counts_below_the_reserved_quantity_are_rejected(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let user_id = test_support::user(&pool, "a@x.io", "-").await;
+        let warehouse_id = test_support::warehouse(&pool, "Main").await;
+        let bolts = test_support::product(&pool, "BOLT").await;
+        test_support::stock_in(&pool, bolts, warehouse_id, 10).await;
+        test_support::reserve(&pool, bolts, warehouse_id, 6).await;
+
+        let counted = create_adjustment(&pool, request(bolts, warehouse_id, None, Some(3)), user_id, &WarehouseScope::All).await;
+
+        assert!(matches!(counted, Err(AppError::InsufficientStock { available: 4, requested: 7, .. })));
+        assert_eq!(test_support::stock(&pool, bolts, warehouse_id).await, (10, 6));
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn counted_quantity_posts_the_difference_to_the_ledger(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let user_id = test_support::user(&pool, "a@x.io", "-").await;
+        let warehouse_id = test_support::warehouse(&pool, "Main").await;
+        let bolts = test_support::product(&pool, "BOLT").await;
+        test_support::stock_in(&pool, bolts, warehouse_id, 10).await;
+
+        let adjustment = create_adjustment(&pool, request(bolts, warehouse_id, None, Some(7)), user_id, &WarehouseScope::All).await.unwrap();
+
+        assert_eq!(adjustment.quantity_change, Some(-3));
+        assert_eq!(test_support::stock(&pool, bolts, warehouse_id).await, (7, 0));
+        assert_eq!(
+            test_support::ledger(&pool, "adjustment", adjustment.id.unwrap().0).await,
+            [("Adjustment".to_string(), -3, 7)]
+        );
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn the_allow_policy_lets_stock_go_negative(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let user_id = test_support::user(&pool, "a@x.io", "-").await;
+        let warehouse_id = test_support::warehouse(&pool, "Main").await;
+        let bolts = test_support::product(&pool, "BOLT").await;
+        test_support::stock_in(&pool, bolts, warehouse_id, 2).await;
+
+        let forbidden = create_adjustment(&pool, request(bolts, warehouse_id, Some(-5), None), user_id, &WarehouseScope::All).await;
+        assert!(matches!(forbidden, Err(AppError::InsufficientStock { .. })));
+
+        test_support::product_policy(&pool, bolts, "allow").await;
+        create_adjustment(&pool, request(bolts, warehouse_id, Some(-5), None), user_id, &WarehouseScope::All).await.unwrap();
+        assert_eq!(test_support::stock(&pool, bolts, warehouse_id).await, (-3, 0));
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn counts_too_far_from_negative_stock_are_rejected(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let user_id = test_support::user(&pool, "a@x.io", "-").await;
+        let warehouse_id = test_support::warehouse(&pool, "Main").await;
+        let bolts = test_support::product(&pool, "BOLT").await;
+        test_support::stock_in(&pool, bolts, warehouse_id, -10).await;
+
+        let counted = create_adjustment(&pool, request(bolts, warehouse_id, None, Some(i32::MAX)), user_id, &WarehouseScope::All).await;
+
+        assert!(matches!(counted, Err(AppError::BadRequest(_))));
+        assert_eq!(test_support::stock(&pool, bolts, warehouse_id).await, (-10, 0));
+    }
+}
@@ -0,0 +1,59 @@
+use crate::config::database::{self, DbPool};
+use crate::dto::api_key_dto::{ApiKeyDto, CreateApiKeyRequest, CreatedApiKeyDto};
+use crate::error::{AppError, Result};
+use crate::middleware::{auth::API_KEY_PREFIX, AuthUser};
+use crate::models::ids::ApiKeyId;
+use crate::repository::api_key_repo;
+use crate::utils::token;
+use chrono::Utc;
+
+pub async fn list(pool: &DbPool) -> Result<Vec<ApiKeyDto>> {
+    Ok(api_key_repo::get_all(pool).await?)
+}
+
+/// Creates a key acting on behalf of `creator`. A key can be given no more
+/// than its creator has: only permissions the creator may use, and only a
+/// warehouse assigned to them.
+pub async fn create(pool: &DbPool, creator: &AuthUser, req: CreateApiKeyRequest) -> Result<CreatedApiKeyDto> {
+    if req.name.trim().is_empty() {
+        return Err(AppError::BadRequest("name must not be empty".to_string()));
+    }
+    if let Some(denied) = req.scopes.iter().find(|p| !creator.can(**p)) {
+        return Err(AppError::Forbidden(denied.as_str().to_string()));
+    }
+    if let Some(warehouse_id) = req.warehouse_id {
+        creator.warehouses.ensure(warehouse_id)?;
+    }
+    if req.expires_at.is_some_and(|at| at <= Utc::now().naive_utc()) {
+        return Err(AppError::BadRequest("expires_at must be in the future".to_string()));
+    }
+
+    let key = format!("{API_KEY_PREFIX}{}", token::generate());
+    let key_prefix = &key[..API_KEY_PREFIX.len() + 8];
+    let mut scopes: Vec<String> = req.scopes.iter().map(|p| p.as_str().to_string()).collect();
+    scopes.sort();
+    scopes.dedup();
+    let mut tx = database::begin(pool).await?;
+    let api_key = api_key_repo::create(
+        &mut *tx,
+        req.name.trim(),
+        key_prefix,
+        &token::hash(&key),
+        &scopes,
+        req.warehouse_id,
+        creator.id,
+        req.expires_at,
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(CreatedApiKeyDto { api_key, key })
+}
+
+pub async fn revoke(pool: &DbPool, id: ApiKeyId) -> Result<()> {
+    let mut tx = database::begin(pool).await?;
+    if !api_key_repo::revoke(&mut *tx, id).await? {
+        return Err(AppError::NotFound(format!("API key {id}")));
+    }
+    tx.commit().await?;
+    Ok(())
+}
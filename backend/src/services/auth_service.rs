@@ -1,28 +1,378 @@
-use crate::repository::user_repo;
+use crate::repository::audit_repo::{self, AuditEvent};
+use crate::repository::{session_repo, user_repo, user_token_repo};
+use crate::services::{login_throttle_service, mail_service};
 use crate::dto::user_dto::UserDto;
-use crate::dto::auth_dto::{LoginRequest, RegisterRequest};
-use crate::config::database::DbPool;
-use crate::utils::{jwt, password};
-use sqlx::Error;
+use crate::dto::auth_dto::{LoginRequest, RegisterRequest, RevokedSessionsDto, TokenResponse};
+use crate::config::database::{self, DbPool};
+use crate::error::{AppError, Result};
+use crate::models::ids::{SessionId, UserId};
+use crate::models::user_token::TokenPurpose;
+use crate::utils::{jwt, password, token};
+use chrono::{Duration, Utc};
+use serde_json::json;
+use sqlx::{PgConnection, PgExecutor};
 
 
-pub async fn register_user(pool: &DbPool, req: RegisterRequest) -> Result<UserDto, Error> {
-    if user_repo::find_by_email(pool, &req.email).await?.is_some() {
-        return Err(Error::RowNotFound); // User exists
-    }
-    let password_hash = password::hash_password(&req.password).map_err(|_| Error::RowNotFound)?;
-    let user = user_repo::create_with_password(pool, &req.name, &req.email, &password_hash).await?;
+/// Creates the account unverified and mails a verification link; login is
+/// refused until the link is followed. A registered email fails on the
+/// unique constraint, answered with 409.
+pub async fn register_user(pool: &DbPool, req: RegisterRequest) -> Result<UserDto> {
+    let password_hash = password::hash_password(&req.password).await?;
+    let mut tx = database::begin(pool).await?;
+    let user = user_repo::create_with_password(&mut *tx, &req.name, &req.email, &password_hash).await?;
+    let user_id = user.id.ok_or(AppError::Internal)?;
+    send_token(&mut tx, user_id, &user.name, &user.email, TokenPurpose::VerifyEmail).await?;
+    tx.commit().await?;
     Ok(user)
 }
 
-pub async fn login_user(pool: &DbPool, req: LoginRequest) -> Result<String, Error> {
-    let user = user_repo::find_by_email(pool, &req.email).await?.ok_or(Error::RowNotFound)?;
-    let valid = password::verify_password(&req.password, &user.password_hash).map_err(|_| Error::RowNotFound)?;
+/// Mails a fresh verification link if the address belongs to an unverified
+/// account. Succeeds either way so the endpoint does not reveal which
+/// addresses are registered.
+pub async fn request_email_verification(pool: &DbPool, email: &str) -> Result<()> {
+    let Some(user) = user_repo::find_by_email(pool, email).await? else {
+        return Ok(());
+    };
+    let Some(user_id) = user.id.filter(|_| user.email_verified_at.is_none()) else {
+        return Ok(());
+    };
+    let mut tx = database::begin(pool).await?;
+    send_token(&mut tx, user_id, &user.name, &user.email, TokenPurpose::VerifyEmail).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn verify_email(pool: &DbPool, token: &str) -> Result<()> {
+    let mut tx = database::begin(pool).await?;
+    let user_id = user_token_repo::consume(&mut tx, TokenPurpose::VerifyEmail, &token::hash(token))
+        .await?
+        .ok_or_else(invalid_token)?;
+    user_repo::mark_email_verified(&mut tx, user_id).await?;
+    record(&mut *tx, "auth.email_verified", Some(user_id), Some(user_id), None).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Mails a reset link if the address is registered; like
+/// `request_email_verification`, succeeds either way.
+pub async fn request_password_reset(pool: &DbPool, email: &str) -> Result<()> {
+    let Some(user) = user_repo::find_by_email(pool, email).await? else {
+        return Ok(());
+    };
+    let Some(user_id) = user.id else {
+        return Ok(());
+    };
+    let mut tx = database::begin(pool).await?;
+    send_token(&mut tx, user_id, &user.name, &user.email, TokenPurpose::ResetPassword).await?;
+    record(&mut *tx, "auth.password_reset_requested", Some(user_id), None, None).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Sets a new password and logs the user out everywhere. Following the link
+/// also proves the address, so an unverified account becomes verified.
+pub async fn reset_password(pool: &DbPool, token: &str, new_password: &str) -> Result<()> {
+    let password_hash = password::hash_password(new_password).await?;
+    let mut tx = database::begin(pool).await?;
+    let user_id = user_token_repo::consume(&mut tx, TokenPurpose::ResetPassword, &token::hash(token))
+        .await?
+        .ok_or_else(invalid_token)?;
+    user_repo::set_password_hash(&mut *tx, user_id, &password_hash).await?;
+    user_repo::mark_email_verified(&mut tx, user_id).await?;
+    let revoked = session_repo::revoke_all(&mut *tx, user_id).await?;
+    record(&mut *tx, "auth.password_reset", Some(user_id), Some(user_id), Some(json!({ "revoked": revoked }))).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+fn invalid_token() -> AppError {
+    AppError::BadRequest("Invalid or expired token".to_string())
+}
+
+/// Stores a new single-use token and queues the mail carrying it.
+async fn send_token(conn: &mut PgConnection, user_id: UserId, name: &str, email: &str, purpose: TokenPurpose) -> Result<()> {
+    let token = token::generate();
+    user_token_repo::create(conn, user_id, purpose, &token::hash(&token)).await?;
+    let app_url = mail_service::app_url();
+    let (subject, body) = match purpose {
+        TokenPurpose::VerifyEmail => (
+            "Confirm your email address",
+            format!(
+                "Hi {name},\n\nConfirm your StockMaster email address by opening this link within 24 hours:\n\n\
+                {app_url}/verify-email?token={token}\n\nIf you did not create an account, you can ignore this message.\n"
+            ),
+        ),
+        TokenPurpose::ResetPassword => (
+            "Reset your password",
+            format!(
+                "Hi {name},\n\nSomeone asked to reset your StockMaster password. To choose a new one, open this link within an hour:\n\n\
+                {app_url}/reset-password?token={token}\n\nIf it was not you, you can ignore this message; your password has not changed.\n"
+            ),
+        ),
+    };
+    mail_service::enqueue(conn, email, subject, &body).await
+}
+
+/// Repeated failures slow down and then lock out further attempts for the
+/// account and the caller's address; see `login_throttle_service`.
+pub async fn login_user(pool: &DbPool, req: LoginRequest) -> Result<TokenResponse> {
+    // One spelling of the address for the throttle and the account lookup, so
+    // `Alice@x.io` and `alice@x.io` are the same account to both.
+    let email = req.email.trim().to_lowercase();
+    login_throttle_service::check(pool, &email).await?;
+    let Some(user) = user_repo::find_by_email(pool, &email).await? else {
+        password::verify_dummy(&req.password).await;
+        login_throttle_service::record_failure(pool, &email, None).await?;
+        login_failed(pool, None, &email, "unknown_email").await?;
+        return Err(AppError::InvalidCredentials);
+    };
+    let user_id = user.id.ok_or(AppError::InvalidCredentials)?;
+    let valid = password::verify_password(&req.password, &user.password_hash).await.map_err(|_| AppError::InvalidCredentials)?;
     if !valid {
-        return Err(Error::RowNotFound);
+        login_throttle_service::record_failure(pool, &email, Some(user_id)).await?;
+        login_failed(pool, Some(user_id), &email, "wrong_password").await?;
+        return Err(AppError::InvalidCredentials);
+    }
+    login_throttle_service::record_success(pool, &email).await?;
+    if user.email_verified_at.is_none() {
+        login_failed(pool, Some(user_id), &email, "email_not_verified").await?;
+        return Err(AppError::EmailNotVerified);
+    }
+
+    // Only now is the plaintext at hand to move a legacy or outdated hash to
+    // the current scheme. A failure here should not stop the login.
+    if password::needs_rehash(&user.password_hash) {
+        match password::hash_password(&req.password).await {
+            Ok(hash) => {
+                let mut tx = database::begin(pool).await?;
+                user_repo::set_password_hash(&mut *tx, user_id, &hash).await?;
+                tx.commit().await?;
+            }
+            Err(e) => tracing::warn!(%user_id, "Password rehash skipped: {e}"),
+        }
+    }
+
+    let mut tx = database::begin(pool).await?;
+    session_repo::delete_expired(&mut *tx, user_id).await?;
+    let refresh_token = token::generate();
+    let session_id = session_repo::create(&mut *tx, user_id, &token::hash(&refresh_token), refresh_expiry()).await?;
+    record(&mut *tx, "auth.login", Some(user_id), Some(user_id), Some(json!({ "session_id": session_id }))).await?;
+    tx.commit().await?;
+    issue(user_id, session_id, refresh_token)
+}
+
+async fn login_failed(pool: &DbPool, user_id: Option<UserId>, email: &str, reason: &str) -> Result<()> {
+    let mut tx = database::begin(pool).await?;
+    record(&mut *tx, "auth.login_failed", user_id, None, Some(json!({ "email": email, "reason": reason }))).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Records an auth event against a user. `actor_id` is for events that
+/// happen before the request is authenticated.
+async fn record<'e, E: PgExecutor<'e>>(
+    executor: E,
+    action: &str,
+    user_id: Option<UserId>,
+    actor_id: Option<UserId>,
+    details: Option<serde_json::Value>,
+) -> Result<()> {
+    let event = AuditEvent { action, entity_type: "users", entity_id: user_id.map(|id| id.0), details, actor_id };
+    audit_repo::record(executor, event).await?;
+    Ok(())
+}
+
+/// Trades a refresh token for a new access token and a new refresh token.
+/// Presenting a token that has already been rotated away revokes its
+/// session: either the client is confused or someone else has a copy.
+pub async fn refresh(pool: &DbPool, refresh_token: &str) -> Result<TokenResponse> {
+    let token_hash = token::hash(refresh_token);
+    let mut tx = database::begin(pool).await?;
+    let Some(session) = session_repo::lock_by_token(&mut tx, &token_hash).await? else {
+        drop(tx);
+        if let Some(reused) = session_repo::find_by_previous_token(pool, &token_hash).await? {
+            tracing::warn!(session_id = %reused.id, user_id = %reused.user_id, "Refresh token reused; revoking session");
+            let mut tx = database::begin(pool).await?;
+            session_repo::revoke(&mut *tx, reused.user_id, reused.id).await?;
+            let details = json!({ "session_id": reused.id });
+            record(&mut *tx, "auth.refresh_token_reused", Some(reused.user_id), None, Some(details)).await?;
+            tx.commit().await?;
+        }
+        return Err(AppError::Unauthorized);
+    };
+    if session.revoked_at.is_some() || session.expires_at < Utc::now().naive_utc() {
+        return Err(AppError::Unauthorized);
+    }
+
+    let refresh_token = token::generate();
+    session_repo::rotate(&mut tx, session.id, &token::hash(&refresh_token), refresh_expiry()).await?;
+    tx.commit().await?;
+    issue(session.user_id, session.id, refresh_token)
+}
+
+/// Ends the session the caller's access token belongs to.
+pub async fn logout(pool: &DbPool, user_id: UserId, session_id: SessionId) -> Result<()> {
+    let mut tx = database::begin(pool).await?;
+    session_repo::revoke(&mut *tx, user_id, session_id).await?;
+    record(&mut *tx, "auth.logout", Some(user_id), None, Some(json!({ "session_id": session_id }))).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Ends every session of the user, including the caller's own when they log
+/// themselves out everywhere.
+pub async fn logout_all(pool: &DbPool, user_id: UserId) -> Result<RevokedSessionsDto> {
+    let mut tx = database::begin(pool).await?;
+    let revoked = session_repo::revoke_all(&mut *tx, user_id).await?;
+    record(&mut *tx, "auth.logout_all", Some(user_id), None, Some(json!({ "revoked": revoked }))).await?;
+    tx.commit().await?;
+    Ok(RevokedSessionsDto { revoked })
+}
+
+/// Refresh tokens last `REFRESH_TOKEN_TTL_DAYS` (default 30) from their last use.
+fn refresh_expiry() -> chrono::NaiveDateTime {
+    let days = std::env::var("REFRESH_TOKEN_TTL_DAYS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(30);
+    (Utc::now() + Duration::days(days)).naive_utc()
+}
+
+fn issue(user_id: UserId, session_id: SessionId, refresh_token: String) -> Result<TokenResponse> {
+    Ok(TokenResponse {
+        access_token: jwt::create_jwt(user_id, session_id)?,
+        refresh_token,
+        token_type: "Bearer".to_string(),
+        expires_in: jwt::access_token_ttl().num_seconds(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    #[sqlx::test(migrations = false)]
+    async fn registration_makes_operators_even_on_an_empty_install(pool: DbPool) {
+        test_support::migrate(&pool).await;
+
+        let req = RegisterRequest { name: "First".to_string(), email: "first@x.io".to_string(), password: "secret123".to_string() };
+        let user = register_user(&pool, req).await.unwrap();
+
+        assert_eq!(user.role, "operator");
+    }
+
+    /// A session whose refresh token has been rotated once, as a successful
+    /// refresh leaves it. Returns its user, its id, and the old and current tokens.
+    async fn rotated_session(pool: &DbPool) -> (UserId, SessionId, String, String) {
+        let user_id = test_support::user(pool, "a@x.io", "-").await;
+        let (old, current) = (token::generate(), token::generate());
+        let session_id = session_repo::create(pool, user_id, &token::hash(&old), refresh_expiry()).await.unwrap();
+        let mut tx = pool.begin().await.unwrap();
+        session_repo::rotate(&mut tx, session_id, &token::hash(&current), refresh_expiry()).await.unwrap();
+        tx.commit().await.unwrap();
+        (user_id, session_id, old, current)
+    }
+
+    async fn revoked(pool: &DbPool, session_id: SessionId) -> bool {
+        sqlx::query_scalar!(r#"SELECT revoked_at IS NOT NULL AS "revoked!" FROM sessions WHERE id = $1"#, session_id as _)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn reusing_a_rotated_refresh_token_revokes_the_session(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let (user_id, session_id, old, current) = rotated_session(&pool).await;
+
+        let reused = refresh(&pool, &old).await;
+
+        assert!(matches!(reused, Err(AppError::Unauthorized)));
+        assert!(revoked(&pool, session_id).await);
+        assert!(matches!(refresh(&pool, &current).await, Err(AppError::Unauthorized)));
+        let events = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM audit_log WHERE action = 'auth.refresh_token_reused' AND entity_id = $1",
+            user_id.0
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(events, Some(1));
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn unknown_refresh_tokens_revoke_nothing(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let (_, session_id, _, _) = rotated_session(&pool).await;
+
+        let unknown = refresh(&pool, &token::generate()).await;
+
+        assert!(matches!(unknown, Err(AppError::Unauthorized)));
+        assert!(!revoked(&pool, session_id).await);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn logged_out_sessions_cannot_be_refreshed(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let (user_id, session_id, _, current) = rotated_session(&pool).await;
+        let other = session_repo::create(&pool, user_id, &token::hash(&token::generate()), refresh_expiry()).await.unwrap();
+
+        let logged_out = logout_all(&pool, user_id).await.unwrap();
+
+        assert_eq!(logged_out.revoked, 2);
+        assert!(revoked(&pool, session_id).await && revoked(&pool, other).await);
+        assert!(matches!(refresh(&pool, &current).await, Err(AppError::Unauthorized)));
+    }
+
+    fn login(email: &str, password: &str) -> LoginRequest {
+        LoginRequest { email: email.to_string(), password: password.to_string() }
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn throttled_logins_are_refused_before_the_password_is_checked(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let hash = password::hash_password("secret123").await.unwrap();
+        test_support::user(&pool, "a@x.io", &hash).await;
+
+        for _ in 0..3 {
+            assert!(matches!(login_user(&pool, login("a@x.io", "wrong")).await, Err(AppError::InvalidCredentials)));
+        }
+        let right_password = login_user(&pool, login("a@x.io", "secret123")).await;
+
+        assert!(matches!(right_password, Err(AppError::TooManyAttempts(_))));
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn unknown_emails_are_throttled_like_real_accounts(pool: DbPool) {
+        test_support::migrate(&pool).await;
+
+        for _ in 0..3 {
+            assert!(matches!(login_user(&pool, login("nobody@x.io", "wrong")).await, Err(AppError::InvalidCredentials)));
+        }
+
+        assert!(matches!(login_user(&pool, login("nobody@x.io", "wrong")).await, Err(AppError::TooManyAttempts(_))));
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn emails_differing_in_case_are_one_account_to_login_and_throttle(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let hash = password::hash_password("secret123").await.unwrap();
+        let user_id = test_support::user(&pool, "Alice@x.io", &hash).await;
+
+        for email in ["alice@x.io", "ALICE@X.IO", " Alice@x.io"] {
+            assert!(matches!(login_user(&pool, login(email, "wrong")).await, Err(AppError::InvalidCredentials)));
+        }
+        let throttled = login_user(&pool, login("Alice@x.io", "secret123")).await;
+
+        assert!(matches!(throttled, Err(AppError::TooManyAttempts(_))));
+        let wrong_passwords = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM audit_log WHERE action = 'auth.login_failed' AND entity_id = $1 AND after->>'reason' = 'wrong_password'",
+            user_id.0
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(wrong_passwords, Some(3));
     }
-    // Use id as Uuid if available, else error
-    let user_id = user.id.ok_or(Error::RowNotFound)?;
-    let token = jwt::create_jwt(uuid::Uuid::from_u128(user_id as u128)).map_err(|_| Error::RowNotFound)?;
-    Ok(token)
 }
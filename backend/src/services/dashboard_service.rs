@@ -0,0 +1,62 @@
+use std::collections::BTreeMap;
+use crate::config::database::DbPool;
+use crate::dto::dashboard_dto::{DashboardFilter, DashboardSummaryDto, StatusCountDto};
+use crate::error::Result;
+use crate::middleware::WarehouseScope;
+use crate::models::delivery::DeliveryStatus;
+use crate::models::receipt::ReceiptStatus;
+use crate::models::transfer::TransferStatus;
+use crate::repository::dashboard_repo;
+
+const PENDING_RECEIPTS: &[&str] = &[ReceiptStatus::Draft.as_str()];
+const PENDING_DELIVERIES: &[&str] = &[
+    DeliveryStatus::Draft.as_str(),
+    DeliveryStatus::Picked.as_str(),
+    DeliveryStatus::Packed.as_str(),
+];
+const PENDING_TRANSFERS: &[&str] = &[TransferStatus::Draft.as_str(), TransferStatus::InTransit.as_str()];
+
+/// Each figure is a single aggregate query and they run concurrently, so the
+/// summary is cheap enough for the frontend to poll. Without a warehouse
+/// filter it covers every warehouse the caller may see.
+pub async fn summary(pool: &DbPool, filter: DashboardFilter, scope: &WarehouseScope) -> Result<DashboardSummaryDto> {
+    let warehouse_id = filter.warehouse_id;
+    let selected;
+    let warehouses = match warehouse_id {
+        Some(id) => {
+            scope.ensure(id)?;
+            selected = [id];
+            Some(&selected[..])
+        }
+        None => scope.ids(),
+    };
+    let (stock, receipts, deliveries, transfers, today) = tokio::try_join!(
+        dashboard_repo::stock_counts(pool, warehouses),
+        dashboard_repo::receipts_by_status(pool, warehouses, PENDING_RECEIPTS),
+        dashboard_repo::deliveries_by_status(pool, warehouses, PENDING_DELIVERIES),
+        dashboard_repo::transfers_by_status(pool, warehouses, PENDING_TRANSFERS),
+        dashboard_repo::movement_volume_today(pool, warehouses),
+    )?;
+
+    Ok(DashboardSummaryDto {
+        warehouse_id,
+        products_in_stock: stock.products_in_stock.unwrap_or_default(),
+        low_stock: stock.low_stock.unwrap_or_default(),
+        out_of_stock: stock.out_of_stock.unwrap_or_default(),
+        pending_receipts: by_status(PENDING_RECEIPTS, receipts),
+        pending_deliveries: by_status(PENDING_DELIVERIES, deliveries),
+        pending_transfers: by_status(PENDING_TRANSFERS, transfers),
+        today,
+    })
+}
+
+/// Every pending status appears in the result, with zero when nothing is in it.
+fn by_status(statuses: &[&str], rows: Vec<StatusCountDto>) -> BTreeMap<String, i64> {
+    let mut counts: BTreeMap<String, i64> = statuses.iter().map(|s| (s.to_string(), 0)).collect();
+    for row in rows {
+        if let Some(status) = row.status {
+            counts.insert(status, row.count.unwrap_or_default());
+        }
+    }
+    counts
+}
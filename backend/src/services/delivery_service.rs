@@ -0,0 +1,312 @@
+use crate::repository::{delivery_item_repo, delivery_repo, product_stock_repo, stock_ledger_repo};
+use crate::services::stock_service;
+use crate::repository::stock_ledger_repo::NewStockLedgerEntry;
+use crate::dto::delivery_dto::{DeliveryDto, DeliveryItemDto, DeliveryWithItemsDto, CreateDeliveryRequest, UpdateDeliveryRequest, CreateDeliveryItemRequest, UpdateDeliveryItemRequest};
+use crate::models::delivery::DeliveryStatus;
+use crate::models::ids::{ProductId, DeliveryId, DeliveryItemId, UserId};
+use crate::config::database::{self, DbPool};
+use crate::error::{AppError, Result};
+use crate::middleware::WarehouseScope;
+
+pub async fn get_delivery(pool: &DbPool, delivery_id: DeliveryId, scope: &WarehouseScope) -> Result<DeliveryWithItemsDto> {
+    let delivery = delivery_repo::get_by_id(pool, delivery_id)
+        .await?
+        .filter(|delivery| scope.allows(delivery.warehouse_id))
+        .ok_or_else(|| AppError::NotFound(format!("Delivery {delivery_id}")))?;
+    let items = delivery_item_repo::get_by_delivery(pool, delivery_id).await?;
+    Ok(DeliveryWithItemsDto { delivery, items })
+}
+
+pub async fn create_delivery(pool: &DbPool, req: CreateDeliveryRequest, user_id: UserId, scope: &WarehouseScope) -> Result<DeliveryDto> {
+    scope.ensure(req.warehouse_id)?;
+    Ok(delivery_repo::create(pool, req, user_id).await?)
+}
+
+/// Header fields can only be edited on drafts; once picked, the warehouse
+/// holds a reservation that must stay consistent with the delivery.
+pub async fn update_delivery(pool: &DbPool, delivery_id: DeliveryId, req: UpdateDeliveryRequest, scope: &WarehouseScope) -> Result<DeliveryDto> {
+    if let Some(warehouse_id) = req.warehouse_id {
+        scope.ensure(warehouse_id)?;
+    }
+    let mut tx = database::begin(pool).await?;
+    lock_draft(&mut tx, delivery_id, scope).await?;
+    let delivery = delivery_repo::update(&mut tx, delivery_id, req).await?;
+    tx.commit().await?;
+    Ok(delivery)
+}
+
+pub async fn list_items(pool: &DbPool, delivery_id: DeliveryId, scope: &WarehouseScope) -> Result<Vec<DeliveryItemDto>> {
+    Ok(get_delivery(pool, delivery_id, scope).await?.items)
+}
+
+pub async fn add_item(pool: &DbPool, delivery_id: DeliveryId, req: CreateDeliveryItemRequest, scope: &WarehouseScope) -> Result<DeliveryItemDto> {
+    if req.quantity <= 0 {
+        return Err(AppError::BadRequest("Quantity must be positive".to_string()));
+    }
+    let mut tx = database::begin(pool).await?;
+    lock_draft(&mut tx, delivery_id, scope).await?;
+    let item = delivery_item_repo::create(&mut tx, delivery_id, req).await?;
+    tx.commit().await?;
+    Ok(item)
+}
+
+pub async fn update_item(pool: &DbPool, delivery_id: DeliveryId, item_id: DeliveryItemId, req: UpdateDeliveryItemRequest, scope: &WarehouseScope) -> Result<DeliveryItemDto> {
+    if req.quantity.is_some_and(|q| q <= 0) {
+        return Err(AppError::BadRequest("Quantity must be positive".to_string()));
+    }
+    let mut tx = database::begin(pool).await?;
+    lock_draft(&mut tx, delivery_id, scope).await?;
+    let item = delivery_item_repo::update(&mut tx, delivery_id, item_id, req)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Delivery item {item_id}")))?;
+    tx.commit().await?;
+    Ok(item)
+}
+
+pub async fn remove_item(pool: &DbPool, delivery_id: DeliveryId, item_id: DeliveryItemId, scope: &WarehouseScope) -> Result<()> {
+    let mut tx = database::begin(pool).await?;
+    lock_draft(&mut tx, delivery_id, scope).await?;
+    if !delivery_item_repo::delete(&mut tx, delivery_id, item_id).await? {
+        return Err(AppError::NotFound(format!("Delivery item {item_id}")));
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Locks the delivery row and fails unless the delivery is still a draft.
+async fn lock_draft(conn: &mut sqlx::PgConnection, delivery_id: DeliveryId, scope: &WarehouseScope) -> Result<DeliveryDto> {
+    let delivery = delivery_repo::find_for_update(conn, delivery_id)
+        .await?
+        .filter(|delivery| scope.allows(delivery.warehouse_id))
+        .ok_or_else(|| AppError::NotFound(format!("Delivery {delivery_id}")))?;
+    if delivery.status.as_deref() != Some(DeliveryStatus::Draft.as_str()) {
+        return Err(AppError::Conflict(format!(
+            "Delivery is '{}'; only draft deliveries can be changed",
+            delivery.status.unwrap_or_default()
+        )));
+    }
+    Ok(delivery)
+}
+
+/// Only drafts can be deleted; use cancel or reverse for others.
+pub async fn delete_delivery(pool: &DbPool, delivery_id: DeliveryId, scope: &WarehouseScope) -> Result<()> {
+    let mut tx = database::begin(pool).await?;
+    lock_draft(&mut tx, delivery_id, scope).await?;
+    delivery_repo::delete(&mut tx, delivery_id).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Puts a delivered delivery's stock back with compensating ledger entries
+/// and marks it cancelled.
+pub async fn reverse_delivery(pool: &DbPool, delivery_id: DeliveryId, user_id: UserId, scope: &WarehouseScope) -> Result<DeliveryDto> {
+    let mut tx = database::begin(pool).await?;
+    let delivery = delivery_repo::find_for_update(&mut tx, delivery_id)
+        .await?
+        .filter(|delivery| scope.allows(delivery.warehouse_id))
+        .ok_or_else(|| AppError::NotFound(format!("Delivery {delivery_id}")))?;
+    if delivery.status.as_deref() != Some(DeliveryStatus::Delivered.as_str()) {
+        return Err(AppError::Conflict(format!(
+            "Delivery is '{}'; only delivered deliveries can be reversed",
+            delivery.status.unwrap_or_default()
+        )));
+    }
+    stock_service::reverse_ledger_entries(&mut tx, "delivery", delivery_id.0, user_id).await?;
+    let delivery = delivery_repo::set_status(&mut *tx, delivery_id, DeliveryStatus::Cancelled.as_str()).await?;
+    tx.commit().await?;
+    Ok(delivery)
+}
+
+/// Moves a delivery to `next`, applying the stock effect of the transition:
+/// picking reserves the items, delivering removes them from stock and writes
+/// `Delivery` ledger rows, cancelling releases any reservation.
+pub async fn transition(pool: &DbPool, delivery_id: DeliveryId, next: DeliveryStatus, user_id: UserId, scope: &WarehouseScope) -> Result<DeliveryDto> {
+    let mut tx = database::begin(pool).await?;
+
+    let delivery = delivery_repo::find_for_update(&mut tx, delivery_id)
+        .await?
+        .filter(|delivery| scope.allows(delivery.warehouse_id))
+        .ok_or_else(|| AppError::NotFound(format!("Delivery {delivery_id}")))?;
+    let status = delivery.status.as_deref().unwrap_or_default();
+    let current = DeliveryStatus::parse(status)
+        .ok_or_else(|| AppError::BadRequest(format!("Delivery has unknown status '{status}'")))?;
+    if !current.can_transition_to(next) {
+        return Err(AppError::Conflict(format!(
+            "Cannot move delivery from '{}' to '{}'",
+            current.as_str(),
+            next.as_str()
+        )));
+    }
+    let warehouse_id = delivery
+        .warehouse_id
+        .ok_or_else(|| AppError::BadRequest("Delivery has no warehouse".to_string()))?;
+
+    let items = delivery_item_repo::get_by_delivery(&mut *tx, delivery_id).await?;
+    let touches_stock = match next {
+        DeliveryStatus::Picked | DeliveryStatus::Delivered => true,
+        DeliveryStatus::Cancelled => current.holds_reservation(),
+        _ => false,
+    };
+    if touches_stock {
+        stock_service::lock_rows(&mut tx, items.iter().filter_map(|item| item.product_id).map(|product_id| (product_id, warehouse_id))).await?;
+    }
+    match next {
+        DeliveryStatus::Picked => {
+            if items.is_empty() {
+                return Err(AppError::BadRequest("Delivery has no items".to_string()));
+            }
+            for item in &items {
+                let (product_id, quantity) = item_line(item)?;
+                stock_service::reserve(&mut tx, product_id, warehouse_id, quantity).await?;
+            }
+        }
+        DeliveryStatus::Delivered => {
+            for item in &items {
+                let (product_id, quantity) = item_line(item)?;
+                let new_balance = stock_service::remove_reserved(&mut tx, product_id, warehouse_id, quantity).await?;
+                stock_ledger_repo::insert(&mut tx, NewStockLedgerEntry {
+                    product_id,
+                    warehouse_id,
+                    user_id: Some(user_id),
+                    movement_type: "Delivery",
+                    reference_id: delivery_id.0,
+                    reference_type: "delivery",
+                    quantity_change: -quantity,
+                    new_balance,
+                    notes: delivery.customer_name.as_deref(),
+                    reversal_of: None,
+                })
+                .await?;
+            }
+        }
+        DeliveryStatus::Cancelled if current.holds_reservation() => {
+            for item in &items {
+                let (product_id, quantity) = item_line(item)?;
+                product_stock_repo::release(&mut tx, product_id, warehouse_id, quantity).await?;
+            }
+        }
+        _ => {}
+    }
+
+    let delivery = delivery_repo::set_status(&mut *tx, delivery_id, next.as_str()).await?;
+    tx.commit().await?;
+    Ok(delivery)
+}
+
+fn item_line(item: &DeliveryItemDto) -> Result<(ProductId, i32)> {
+    match (item.product_id, item.quantity) {
+        (Some(product_id), Some(quantity)) => Ok((product_id, quantity)),
+        _ => Err(AppError::BadRequest("Delivery item is missing a product or quantity".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ids::WarehouseId;
+    use crate::test_support;
+
+    async fn draft(pool: &DbPool, user_id: UserId, warehouse_id: WarehouseId, product_id: ProductId, quantity: i32) -> DeliveryId {
+        let req = CreateDeliveryRequest { customer_name: "Globex".to_string(), warehouse_id };
+        let delivery_id = create_delivery(pool, req, user_id, &WarehouseScope::All).await.unwrap().id.unwrap();
+        let req = CreateDeliveryItemRequest { product_id, quantity };
+        add_item(pool, delivery_id, req, &WarehouseScope::All).await.unwrap();
+        delivery_id
+    }
+
+    async fn move_to(pool: &DbPool, delivery_id: DeliveryId, next: DeliveryStatus, user_id: UserId) -> Result<DeliveryDto> {
+        transition(pool, delivery_id, next, user_id, &WarehouseScope::All).await
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn picking_reserves_and_delivering_takes_the_reservation_out(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let user_id = test_support::user(&pool, "a@x.io", "-").await;
+        let warehouse_id = test_support::warehouse(&pool, "Main").await;
+        let bolts = test_support::product(&pool, "BOLT").await;
+        test_support::stock_in(&pool, bolts, warehouse_id, 10).await;
+        let delivery_id = draft(&pool, user_id, warehouse_id, bolts, 6).await;
+
+        move_to(&pool, delivery_id, DeliveryStatus::Picked, user_id).await.unwrap();
+        assert_eq!(test_support::stock(&pool, bolts, warehouse_id).await, (10, 6));
+        assert!(test_support::ledger(&pool, "delivery", delivery_id.0).await.is_empty());
+
+        move_to(&pool, delivery_id, DeliveryStatus::Packed, user_id).await.unwrap();
+        let delivery = move_to(&pool, delivery_id, DeliveryStatus::Delivered, user_id).await.unwrap();
+
+        assert_eq!(delivery.status.as_deref(), Some("delivered"));
+        assert_eq!(test_support::stock(&pool, bolts, warehouse_id).await, (4, 0));
+        assert_eq!(test_support::ledger(&pool, "delivery", delivery_id.0).await, [("Delivery".to_string(), -6, 4)]);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn cancelling_a_picked_delivery_releases_its_reservation(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let user_id = test_support::user(&pool, "a@x.io", "-").await;
+        let warehouse_id = test_support::warehouse(&pool, "Main").await;
+        let bolts = test_support::product(&pool, "BOLT").await;
+        test_support::stock_in(&pool, bolts, warehouse_id, 10).await;
+        let delivery_id = draft(&pool, user_id, warehouse_id, bolts, 6).await;
+
+        move_to(&pool, delivery_id, DeliveryStatus::Picked, user_id).await.unwrap();
+        move_to(&pool, delivery_id, DeliveryStatus::Cancelled, user_id).await.unwrap();
+
+        assert_eq!(test_support::stock(&pool, bolts, warehouse_id).await, (10, 0));
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn stock_reserved_by_one_delivery_cannot_be_picked_by_another(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let user_id = test_support::user(&pool, "a@x.io", "-").await;
+        let warehouse_id = test_support::warehouse(&pool, "Main").await;
+        let bolts = test_support::product(&pool, "BOLT").await;
+        test_support::stock_in(&pool, bolts, warehouse_id, 10).await;
+        let first = draft(&pool, user_id, warehouse_id, bolts, 6).await;
+        let second = draft(&pool, user_id, warehouse_id, bolts, 5).await;
+
+        move_to(&pool, first, DeliveryStatus::Picked, user_id).await.unwrap();
+        let picked = move_to(&pool, second, DeliveryStatus::Picked, user_id).await;
+
+        assert!(matches!(picked, Err(AppError::InsufficientStock { available: 4, requested: 5, .. })));
+        assert_eq!(test_support::stock(&pool, bolts, warehouse_id).await, (10, 6));
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn transitions_outside_the_state_machine_are_rejected(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let user_id = test_support::user(&pool, "a@x.io", "-").await;
+        let warehouse_id = test_support::warehouse(&pool, "Main").await;
+        let bolts = test_support::product(&pool, "BOLT").await;
+        test_support::stock_in(&pool, bolts, warehouse_id, 10).await;
+        let delivery_id = draft(&pool, user_id, warehouse_id, bolts, 6).await;
+
+        let skipped = move_to(&pool, delivery_id, DeliveryStatus::Delivered, user_id).await;
+
+        assert!(matches!(skipped, Err(AppError::Conflict(_))));
+        assert_eq!(test_support::stock(&pool, bolts, warehouse_id).await, (10, 0));
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn reversing_a_delivered_delivery_puts_the_stock_back(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let user_id = test_support::user(&pool, "a@x.io", "-").await;
+        let warehouse_id = test_support::warehouse(&pool, "Main").await;
+        let bolts = test_support::product(&pool, "BOLT").await;
+        test_support::stock_in(&pool, bolts, warehouse_id, 10).await;
+        let delivery_id = draft(&pool, user_id, warehouse_id, bolts, 6).await;
+        for next in [DeliveryStatus::Picked, DeliveryStatus::Packed, DeliveryStatus::Delivered] {
+            move_to(&pool, delivery_id, next, user_id).await.unwrap();
+        }
+
+        let delivery = reverse_delivery(&pool, delivery_id, user_id, &WarehouseScope::All).await.unwrap();
+        let reversed_again = reverse_delivery(&pool, delivery_id, user_id, &WarehouseScope::All).await;
+
+        assert_eq!(delivery.status.as_deref(), Some("cancelled"));
+        assert!(matches!(reversed_again, Err(AppError::Conflict(_))));
+        assert_eq!(test_support::stock(&pool, bolts, warehouse_id).await, (10, 0));
+        assert_eq!(
+            test_support::ledger(&pool, "delivery", delivery_id.0).await,
+            [("Delivery".to_string(), -6, 4), ("Delivery".to_string(), 6, 10)]
+        );
+    }
+}
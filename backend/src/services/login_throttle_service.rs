@@ -0,0 +1,181 @@
+use crate::repository::audit_repo::{self, AuditEvent};
+use crate::repository::{login_throttle_repo, user_repo};
+use crate::config::database::{self, DbPool};
+use crate::error::{AppError, Result};
+use crate::middleware::audit;
+use crate::models::ids::UserId;
+use serde_json::json;
+
+/// When failed logins start to slow down, and then lock out, one key.
+struct Policy {
+    kind: &'static str,
+    backoff_after: i32,
+    lockout_after: i32,
+}
+
+/// Guessing one account's password. Counted whether or not the account
+/// exists, so the responses do not reveal which emails are registered.
+const ACCOUNT: Policy = Policy { kind: "account", backoff_after: 3, lockout_after: 10 };
+
+/// Spraying many accounts from one address; looser, since several users
+/// may share a NAT.
+const IP: Policy = Policy { kind: "ip", backoff_after: 20, lockout_after: 100 };
+
+/// Failures are forgotten after an hour without one.
+const FAILURE_WINDOW_SECS: i64 = 60 * 60;
+const MAX_BACKOFF_SECS: i64 = 5 * 60;
+const LOCKOUT_SECS: i64 = 15 * 60;
+
+/// Refuses the attempt while the account or the caller's address is locked.
+/// Runs before the password is checked, so a locked account cannot be
+/// probed either.
+pub async fn check(pool: &DbPool, email: &str) -> Result<()> {
+    let ip = client_ip();
+    match login_throttle_repo::retry_after(pool, &account_key(email), ip.as_deref()).await? {
+        Some(secs) => Err(AppError::TooManyAttempts(secs.max(1))),
+        None => Ok(()),
+    }
+}
+
+/// Counts a failed attempt against the account and the address. Past
+/// `backoff_after` failures the key is locked for a delay that doubles with
+/// each further failure; at `lockout_after` it is locked out for
+/// `LOCKOUT_SECS`, which is recorded in the audit log.
+pub async fn record_failure(pool: &DbPool, email: &str, user_id: Option<UserId>) -> Result<()> {
+    penalize(pool, &ACCOUNT, &account_key(email), user_id).await?;
+    if let Some(ip) = client_ip() {
+        penalize(pool, &IP, &ip, None).await?;
+    }
+    Ok(())
+}
+
+/// A successful login clears the account's failures. The address keeps its
+/// count: one right password says nothing about the other accounts tried
+/// from it.
+pub async fn record_success(pool: &DbPool, email: &str) -> Result<()> {
+    login_throttle_repo::clear(pool, ACCOUNT.kind, &account_key(email)).await?;
+    Ok(())
+}
+
+/// Lifts a lockout or backoff on the user's account before it expires.
+pub async fn unlock_user(pool: &DbPool, user_id: UserId) -> Result<()> {
+    let user = user_repo::get_by_id(pool, user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("User {user_id}")))?;
+    let mut tx = database::begin(pool).await?;
+    let cleared = login_throttle_repo::clear(&mut *tx, ACCOUNT.kind, &account_key(&user.email)).await?;
+    let event = AuditEvent {
+        action: "auth.unlock",
+        entity_type: "users",
+        entity_id: Some(user_id.0),
+        details: Some(json!({ "cleared": cleared })),
+        actor_id: None,
+    };
+    audit_repo::record(&mut *tx, event).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+async fn penalize(pool: &DbPool, policy: &Policy, subject: &str, user_id: Option<UserId>) -> Result<()> {
+    let failures = login_throttle_repo::record_failure(pool, policy.kind, subject, FAILURE_WINDOW_SECS).await?;
+    if failures < policy.backoff_after {
+        return Ok(());
+    }
+    if failures < policy.lockout_after {
+        let exponent = (failures - policy.backoff_after).min(16) as u32;
+        let secs = 2_i64.pow(exponent).min(MAX_BACKOFF_SECS);
+        login_throttle_repo::lock(pool, policy.kind, subject, secs).await?;
+        return Ok(());
+    }
+
+    let locked_until = login_throttle_repo::lock(pool, policy.kind, subject, LOCKOUT_SECS).await?;
+    tracing::warn!(kind = policy.kind, subject, failures, "Login locked out after repeated failures");
+    let event = AuditEvent {
+        action: "auth.lockout",
+        entity_type: if policy.kind == ACCOUNT.kind { "users" } else { "client_ips" },
+        entity_id: user_id.map(|id| id.0),
+        details: Some(json!({ "kind": policy.kind, "subject": subject, "failures": failures, "locked_until": locked_until })),
+        actor_id: None,
+    };
+    let mut tx = database::begin(pool).await?;
+    audit_repo::record(&mut *tx, event).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+fn account_key(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+fn client_ip() -> Option<String> {
+    audit::current().and_then(|ctx| ctx.client_ip)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::audit::AuditContext;
+    use crate::test_support;
+
+    async fn fail(pool: &DbPool, email: &str, times: usize) {
+        for _ in 0..times {
+            record_failure(pool, email, None).await.unwrap();
+        }
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn backoff_starts_at_the_third_failure(pool: DbPool) {
+        test_support::migrate(&pool).await;
+
+        fail(&pool, "a@x.io", 2).await;
+        assert!(check(&pool, "a@x.io").await.is_ok());
+
+        fail(&pool, "a@x.io", 1).await;
+        assert!(matches!(check(&pool, "a@x.io").await, Err(AppError::TooManyAttempts(_))));
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn the_tenth_failure_locks_the_account_out_and_is_audited(pool: DbPool) {
+        test_support::migrate(&pool).await;
+
+        fail(&pool, "a@x.io", 9).await;
+        let backoff = check(&pool, "a@x.io").await;
+        fail(&pool, "a@x.io", 1).await;
+        let lockout = check(&pool, "a@x.io").await;
+
+        assert!(matches!(backoff, Err(AppError::TooManyAttempts(secs)) if secs <= MAX_BACKOFF_SECS));
+        assert!(matches!(lockout, Err(AppError::TooManyAttempts(secs)) if secs > LOCKOUT_SECS - 60));
+        let lockouts = sqlx::query_scalar!("SELECT COUNT(*) FROM audit_log WHERE action = 'auth.lockout'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(lockouts, Some(1));
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn accounts_are_keyed_by_lowercased_email(pool: DbPool) {
+        test_support::migrate(&pool).await;
+
+        fail(&pool, " A@X.io", 3).await;
+
+        assert!(check(&pool, "a@x.io").await.is_err());
+        assert!(check(&pool, "b@x.io").await.is_ok());
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn success_clears_the_account_but_not_the_address(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let ctx = AuditContext { client_ip: Some("203.0.113.7".to_string()), ..AuditContext::default() };
+
+        audit::scope(ctx, async {
+            for n in 0..IP.backoff_after {
+                fail(&pool, &format!("user{n}@x.io"), 1).await;
+            }
+            record_success(&pool, "user0@x.io").await.unwrap();
+
+            assert!(matches!(check(&pool, "new@x.io").await, Err(AppError::TooManyAttempts(_))));
+        })
+        .await;
+        assert!(check(&pool, "user0@x.io").await.is_ok());
+    }
+}
@@ -0,0 +1,55 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::config::database::{self, DbPool};
+use crate::error::Result;
+use crate::repository::mail_outbox_repo;
+use crate::utils::mailer::Mailer;
+use sqlx::PgExecutor;
+
+/// Queues a message for the outbox worker. Pass the transaction that makes
+/// the change the message is about, so either both happen or neither does.
+pub async fn enqueue<'e, E: PgExecutor<'e>>(executor: E, to: &str, subject: &str, body: &str) -> Result<()> {
+    mail_outbox_repo::enqueue(executor, to, subject, body).await?;
+    Ok(())
+}
+
+/// Base URL of the frontend pages the mailed links point at (`APP_URL`).
+pub fn app_url() -> String {
+    std::env::var("APP_URL").unwrap_or_else(|_| "http://localhost:3000".to_string())
+}
+
+/// Sends whatever is due every `MAIL_POLL_SECONDS` (default 5) until the
+/// process exits.
+pub fn spawn_outbox_worker(pool: DbPool, mailer: Arc<dyn Mailer>) {
+    let seconds = std::env::var("MAIL_POLL_SECONDS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(5);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(seconds));
+        loop {
+            interval.tick().await;
+            if let Err(e) = deliver_due(&pool, mailer.as_ref()).await {
+                tracing::error!("Mail outbox: {e}");
+            }
+        }
+    });
+}
+
+/// Sends one batch of due messages. A failed send is retried later and does
+/// not hold up the rest of the batch.
+pub async fn deliver_due(pool: &DbPool, mailer: &dyn Mailer) -> Result<usize> {
+    let mut tx = database::begin(pool).await?;
+    let mails = mail_outbox_repo::lock_due(&mut tx, 20).await?;
+    for mail in &mails {
+        match mailer.send(&mail.recipient, &mail.subject, &mail.body).await {
+            Ok(()) => mail_outbox_repo::mark_sent(&mut tx, mail.id).await?,
+            Err(e) => {
+                tracing::warn!(mail_id = mail.id, attempt = mail.attempts + 1, "Mail delivery failed: {e:#}");
+                mail_outbox_repo::mark_attempt_failed(&mut tx, mail.id, &format!("{e:#}")).await?;
+            }
+        }
+    }
+    tx.commit().await?;
+    Ok(mails.len())
+}
@@ -1,2 +1,11 @@
 pub mod auth_service;
 pub mod stock_service;
+pub mod receipt_service;
+pub mod delivery_service;
+pub mod transfer_service;
+pub mod adjustment_service;
+pub mod dashboard_service;
+pub mod user_service;
+pub mod mail_service;
+pub mod api_key_service;
+pub mod login_throttle_service;
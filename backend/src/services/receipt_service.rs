@@ -0,0 +1,315 @@
+use crate::repository::{product_stock_repo, receipt_item_repo, receipt_repo, stock_ledger_repo};
+use crate::repository::stock_ledger_repo::NewStockLedgerEntry;
+use crate::services::stock_service;
+use crate::dto::receipt_dto::{ReceiptDto, ReceiptItemDto, ReceiptWithItemsDto, CreateReceiptRequest, UpdateReceiptRequest, CreateReceiptItemRequest, UpdateReceiptItemRequest};
+use crate::models::ids::{ReceiptId, ReceiptItemId, UserId};
+use crate::models::receipt::ReceiptStatus;
+use crate::config::database::{self, DbPool};
+use crate::error::{AppError, Result};
+use crate::middleware::WarehouseScope;
+
+pub async fn get_receipt(pool: &DbPool, receipt_id: ReceiptId, scope: &WarehouseScope) -> Result<ReceiptWithItemsDto> {
+    let receipt = receipt_repo::get_by_id(pool, receipt_id)
+        .await?
+        .filter(|receipt| scope.allows(receipt.warehouse_id))
+        .ok_or_else(|| AppError::NotFound(format!("Receipt {receipt_id}")))?;
+    let items = receipt_item_repo::get_by_receipt(pool, receipt_id).await?;
+    Ok(ReceiptWithItemsDto { receipt, items })
+}
+
+pub async fn create_receipt(pool: &DbPool, req: CreateReceiptRequest, user_id: UserId, scope: &WarehouseScope) -> Result<ReceiptDto> {
+    scope.ensure(req.warehouse_id)?;
+    Ok(receipt_repo::create(pool, req, user_id).await?)
+}
+
+/// Header fields can only be edited on drafts; a validated receipt's stock
+/// is already posted to its warehouse.
+pub async fn update_receipt(pool: &DbPool, receipt_id: ReceiptId, req: UpdateReceiptRequest, scope: &WarehouseScope) -> Result<ReceiptDto> {
+    if let Some(warehouse_id) = req.warehouse_id {
+        scope.ensure(warehouse_id)?;
+    }
+    let mut tx = database::begin(pool).await?;
+    lock_draft(&mut tx, receipt_id, scope).await?;
+    let receipt = receipt_repo::update(&mut tx, receipt_id, req).await?;
+    tx.commit().await?;
+    Ok(receipt)
+}
+
+pub async fn list_items(pool: &DbPool, receipt_id: ReceiptId, scope: &WarehouseScope) -> Result<Vec<ReceiptItemDto>> {
+    Ok(get_receipt(pool, receipt_id, scope).await?.items)
+}
+
+pub async fn add_item(pool: &DbPool, receipt_id: ReceiptId, req: CreateReceiptItemRequest, scope: &WarehouseScope) -> Result<ReceiptItemDto> {
+    if req.quantity <= 0 {
+        return Err(AppError::BadRequest("Quantity must be positive".to_string()));
+    }
+    let mut tx = database::begin(pool).await?;
+    lock_draft(&mut tx, receipt_id, scope).await?;
+    let item = receipt_item_repo::create(&mut tx, receipt_id, req).await?;
+    tx.commit().await?;
+    Ok(item)
+}
+
+pub async fn update_item(pool: &DbPool, receipt_id: ReceiptId, item_id: ReceiptItemId, req: UpdateReceiptItemRequest, scope: &WarehouseScope) -> Result<ReceiptItemDto> {
+    if req.quantity.is_some_and(|q| q <= 0) {
+        return Err(AppError::BadRequest("Quantity must be positive".to_string()));
+    }
+    let mut tx = database::begin(pool).await?;
+    lock_draft(&mut tx, receipt_id, scope).await?;
+    let item = receipt_item_repo::update(&mut tx, receipt_id, item_id, req)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Receipt item {item_id}")))?;
+    tx.commit().await?;
+    Ok(item)
+}
+
+pub async fn remove_item(pool: &DbPool, receipt_id: ReceiptId, item_id: ReceiptItemId, scope: &WarehouseScope) -> Result<()> {
+    let mut tx = database::begin(pool).await?;
+    lock_draft(&mut tx, receipt_id, scope).await?;
+    if !receipt_item_repo::delete(&mut tx, receipt_id, item_id).await? {
+        return Err(AppError::NotFound(format!("Receipt item {item_id}")));
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Only drafts can be deleted; validated receipts are reversed instead so
+/// their ledger history stays intact.
+pub async fn delete_receipt(pool: &DbPool, receipt_id: ReceiptId, scope: &WarehouseScope) -> Result<()> {
+    let mut tx = database::begin(pool).await?;
+    lock_draft(&mut tx, receipt_id, scope).await?;
+    receipt_repo::delete(&mut tx, receipt_id).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Takes a validated receipt's stock back out with compensating ledger
+/// entries and marks the receipt cancelled.
+pub async fn reverse_receipt(pool: &DbPool, receipt_id: ReceiptId, user_id: UserId, scope: &WarehouseScope) -> Result<ReceiptDto> {
+    let mut tx = database::begin(pool).await?;
+    let receipt = receipt_repo::find_for_update(&mut tx, receipt_id)
+        .await?
+        .filter(|receipt| scope.allows(receipt.warehouse_id))
+        .ok_or_else(|| AppError::NotFound(format!("Receipt {receipt_id}")))?;
+    if receipt.status.as_deref() != Some(ReceiptStatus::Validated.as_str()) {
+        return Err(AppError::Conflict(format!(
+            "Receipt is '{}'; only validated receipts can be reversed",
+            receipt.status.unwrap_or_default()
+        )));
+    }
+    stock_service::reverse_ledger_entries(&mut tx, "receipt", receipt_id.0, user_id).await?;
+    let receipt = receipt_repo::set_status(&mut *tx, receipt_id, ReceiptStatus::Cancelled.as_str()).await?;
+    tx.commit().await?;
+    Ok(receipt)
+}
+
+/// Locks the receipt row and fails unless the receipt is still a draft,
+/// so its lines cannot change underneath a concurrent validation.
+async fn lock_draft(conn: &mut sqlx::PgConnection, receipt_id: ReceiptId, scope: &WarehouseScope) -> Result<ReceiptDto> {
+    let receipt = receipt_repo::find_for_update(conn, receipt_id)
+        .await?
+        .filter(|receipt| scope.allows(receipt.warehouse_id))
+        .ok_or_else(|| AppError::NotFound(format!("Receipt {receipt_id}")))?;
+    if receipt.status.as_deref() != Some(ReceiptStatus::Draft.as_str()) {
+        return Err(AppError::Conflict(format!(
+            "Receipt is '{}'; only draft receipts can be changed",
+            receipt.status.unwrap_or_default()
+        )));
+    }
+    Ok(receipt)
+}
+
+/// Posts every line of a draft receipt into `product_stock` and `stock_ledger`
+/// and marks the receipt validated. Either all of it lands or none of it does.
+pub async fn validate_receipt(pool: &DbPool, receipt_id: ReceiptId, user_id: UserId, scope: &WarehouseScope) -> Result<ReceiptDto> {
+    let mut tx = database::begin(pool).await?;
+
+    let receipt = lock_draft(&mut tx, receipt_id, scope).await?;
+    let warehouse_id = receipt
+        .warehouse_id
+        .ok_or_else(|| AppError::BadRequest("Receipt has no warehouse".to_string()))?;
+
+    let items = receipt_item_repo::get_by_receipt(&mut *tx, receipt_id).await?;
+    if items.is_empty() {
+        return Err(AppError::BadRequest("Receipt has no items".to_string()));
+    }
+    stock_service::lock_rows(&mut tx, items.iter().filter_map(|item| item.product_id).map(|product_id| (product_id, warehouse_id))).await?;
+
+    for item in items {
+        let (Some(product_id), Some(quantity)) = (item.product_id, item.quantity) else {
+            return Err(AppError::BadRequest("Receipt item is missing a product or quantity".to_string()));
+        };
+        if quantity <= 0 {
+            return Err(AppError::BadRequest(format!("Receipt item for product {product_id} has a non-positive quantity")));
+        }
+
+        let new_balance = product_stock_repo::add_quantity(&mut tx, product_id, warehouse_id, quantity).await?;
+        stock_ledger_repo::insert(&mut tx, NewStockLedgerEntry {
+            product_id,
+            warehouse_id,
+            user_id: Some(user_id),
+            movement_type: "Receipt",
+            reference_id: receipt_id.0,
+            reference_type: "receipt",
+            quantity_change: quantity,
+            new_balance,
+            notes: receipt.supplier_name.as_deref(),
+            reversal_of: None,
+        })
+        .await?;
+    }
+
+    let receipt = receipt_repo::set_status(&mut *tx, receipt_id, ReceiptStatus::Validated.as_str()).await?;
+    tx.commit().await?;
+    Ok(receipt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ids::{ProductId, WarehouseId};
+    use crate::test_support;
+
+    async fn draft(pool: &DbPool, user_id: UserId, warehouse_id: WarehouseId, lines: &[(ProductId, i32)]) -> ReceiptId {
+        let req = CreateReceiptRequest { supplier_name: "Acme".to_string(), warehouse_id };
+        let receipt_id = create_receipt(pool, req, user_id, &WarehouseScope::All).await.unwrap().id.unwrap();
+        for &(product_id, quantity) in lines {
+            let req = CreateReceiptItemRequest { product_id, quantity, expiry_date: None };
+            add_item(pool, receipt_id, req, &WarehouseScope::All).await.unwrap();
+        }
+        receipt_id
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn validate_posts_every_line_to_stock_and_ledger(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let user_id = test_support::user(&pool, "a@x.io", "-").await;
+        let warehouse_id = test_support::warehouse(&pool, "Main").await;
+        let bolts = test_support::product(&pool, "BOLT").await;
+        let nuts = test_support::product(&pool, "NUT").await;
+        let receipt_id = draft(&pool, user_id, warehouse_id, &[(bolts, 10), (nuts, 4), (bolts, 5)]).await;
+
+        let receipt = validate_receipt(&pool, receipt_id, user_id, &WarehouseScope::All).await.unwrap();
+
+        assert_eq!(receipt.status.as_deref(), Some("validated"));
+        assert_eq!(test_support::stock(&pool, bolts, warehouse_id).await, (15, 0));
+        assert_eq!(test_support::stock(&pool, nuts, warehouse_id).await, (4, 0));
+        assert_eq!(
+            test_support::ledger(&pool, "receipt", receipt_id.0).await,
+            [("Receipt".to_string(), 10, 10), ("Receipt".to_string(), 4, 4), ("Receipt".to_string(), 5, 15)]
+        );
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn validating_twice_is_rejected_without_posting_again(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let user_id = test_support::user(&pool, "a@x.io", "-").await;
+        let warehouse_id = test_support::warehouse(&pool, "Main").await;
+        let bolts = test_support::product(&pool, "BOLT").await;
+        let receipt_id = draft(&pool, user_id, warehouse_id, &[(bolts, 10)]).await;
+
+        validate_receipt(&pool, receipt_id, user_id, &WarehouseScope::All).await.unwrap();
+        let again = validate_receipt(&pool, receipt_id, user_id, &WarehouseScope::All).await;
+
+        assert!(matches!(again, Err(AppError::Conflict(_))));
+        assert_eq!(test_support::stock(&pool, bolts, warehouse_id).await, (10, 0));
+        assert_eq!(test_support::ledger(&pool, "receipt", receipt_id.0).await.len(), 1);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn validated_receipts_cannot_be_edited(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let user_id = test_support::user(&pool, "a@x.io", "-").await;
+        let warehouse_id = test_support::warehouse(&pool, "Main").await;
+        let bolts = test_support::product(&pool, "BOLT").await;
+        let receipt_id = draft(&pool, user_id, warehouse_id, &[(bolts, 10)]).await;
+        validate_receipt(&pool, receipt_id, user_id, &WarehouseScope::All).await.unwrap();
+
+        let req = CreateReceiptItemRequest { product_id: bolts, quantity: 1, expiry_date: None };
+        let added = add_item(&pool, receipt_id, req, &WarehouseScope::All).await;
+        let req = UpdateReceiptRequest { supplier_name: Some("Other".to_string()), warehouse_id: None };
+        let updated = update_receipt(&pool, receipt_id, req, &WarehouseScope::All).await;
+
+        assert!(matches!(added, Err(AppError::Conflict(_))));
+        assert!(matches!(updated, Err(AppError::Conflict(_))));
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn receipts_outside_the_callers_warehouses_are_not_found(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let user_id = test_support::user(&pool, "a@x.io", "-").await;
+        let warehouse_id = test_support::warehouse(&pool, "Main").await;
+        let other = test_support::warehouse(&pool, "Other").await;
+        let bolts = test_support::product(&pool, "BOLT").await;
+        let receipt_id = draft(&pool, user_id, warehouse_id, &[(bolts, 10)]).await;
+
+        let validated = validate_receipt(&pool, receipt_id, user_id, &WarehouseScope::Only(vec![other])).await;
+
+        assert!(matches!(validated, Err(AppError::NotFound(_))));
+        assert_eq!(test_support::stock(&pool, bolts, warehouse_id).await, (0, 0));
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn reversing_takes_the_stock_back_out_with_linked_ledger_rows(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let user_id = test_support::user(&pool, "a@x.io", "-").await;
+        let warehouse_id = test_support::warehouse(&pool, "Main").await;
+        let bolts = test_support::product(&pool, "BOLT").await;
+        test_support::stock_in(&pool, bolts, warehouse_id, 3).await;
+        let receipt_id = draft(&pool, user_id, warehouse_id, &[(bolts, 10)]).await;
+        validate_receipt(&pool, receipt_id, user_id, &WarehouseScope::All).await.unwrap();
+
+        let receipt = reverse_receipt(&pool, receipt_id, user_id, &WarehouseScope::All).await.unwrap();
+
+        assert_eq!(receipt.status.as_deref(), Some("cancelled"));
+        assert_eq!(test_support::stock(&pool, bolts, warehouse_id).await, (3, 0));
+        assert_eq!(
+            test_support::ledger(&pool, "receipt", receipt_id.0).await,
+            [("Receipt".to_string(), 10, 13), ("Receipt".to_string(), -10, 3)]
+        );
+        let unlinked = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM stock_ledger original WHERE reference_type = 'receipt' AND reference_id = $1 AND reversal_of IS NULL
+                AND NOT EXISTS (SELECT 1 FROM stock_ledger reversal WHERE reversal.reversal_of = original.id)",
+            receipt_id.0
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(unlinked, Some(0));
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn only_validated_receipts_can_be_reversed_and_only_once(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let user_id = test_support::user(&pool, "a@x.io", "-").await;
+        let warehouse_id = test_support::warehouse(&pool, "Main").await;
+        let bolts = test_support::product(&pool, "BOLT").await;
+        let receipt_id = draft(&pool, user_id, warehouse_id, &[(bolts, 10)]).await;
+
+        let draft_reversed = reverse_receipt(&pool, receipt_id, user_id, &WarehouseScope::All).await;
+        validate_receipt(&pool, receipt_id, user_id, &WarehouseScope::All).await.unwrap();
+        reverse_receipt(&pool, receipt_id, user_id, &WarehouseScope::All).await.unwrap();
+        let reversed_again = reverse_receipt(&pool, receipt_id, user_id, &WarehouseScope::All).await;
+
+        assert!(matches!(draft_reversed, Err(AppError::Conflict(_))));
+        assert!(matches!(reversed_again, Err(AppError::Conflict(_))));
+        assert_eq!(test_support::stock(&pool, bolts, warehouse_id).await, (0, 0));
+        assert_eq!(test_support::ledger(&pool, "receipt", receipt_id.0).await.len(), 2);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn reversing_a_receipt_whose_stock_is_reserved_is_refused(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let user_id = test_support::user(&pool, "a@x.io", "-").await;
+        let warehouse_id = test_support::warehouse(&pool, "Main").await;
+        let bolts = test_support::product(&pool, "BOLT").await;
+        let receipt_id = draft(&pool, user_id, warehouse_id, &[(bolts, 10)]).await;
+        validate_receipt(&pool, receipt_id, user_id, &WarehouseScope::All).await.unwrap();
+        test_support::reserve(&pool, bolts, warehouse_id, 8).await;
+
+        let reversed = reverse_receipt(&pool, receipt_id, user_id, &WarehouseScope::All).await;
+
+        assert!(matches!(reversed, Err(AppError::InsufficientStock { available: 2, requested: 10, .. })));
+        assert_eq!(test_support::stock(&pool, bolts, warehouse_id).await, (10, 8));
+    }
+}
@@ -1,16 +1,137 @@
-use crate::repository::product_stock_repo;
-use crate::models::product_stock::ProductStock;
+use crate::repository::{product_stock_repo, stock_ledger_repo, transfer_item_repo};
+use crate::repository::stock_ledger_repo::NewStockLedgerEntry;
+use crate::dto::transfer_dto::{InTransitDto, InTransitFilter};
+use crate::models::product_stock::{NegativeStockPolicy, ProductStock};
+use crate::models::ids::{ProductId, UserId, WarehouseId};
 use crate::config::database::DbPool;
-use sqlx::Error;
+use crate::error::AppError;
+use crate::middleware::WarehouseScope;
+use sqlx::PgConnection;
+use std::collections::BTreeSet;
 
-pub async fn get_current_stock(pool: &DbPool) -> Result<Vec<ProductStock>, Error> {
-    product_stock_repo::get_all(pool).await
+pub async fn get_current_stock(pool: &DbPool, scope: &WarehouseScope) -> Result<Vec<ProductStock>, AppError> {
+    Ok(product_stock_repo::get_all(pool, scope.ids()).await?)
 }
 
-pub async fn get_stock_by_product(pool: &DbPool, product_id: uuid::Uuid) -> Result<Vec<ProductStock>, Error> {
-    product_stock_repo::get_by_product(pool, product_id).await
+pub async fn get_stock_by_product(pool: &DbPool, product_id: ProductId, scope: &WarehouseScope) -> Result<Vec<ProductStock>, AppError> {
+    Ok(product_stock_repo::get_by_product(pool, product_id, scope.ids()).await?)
 }
 
-pub async fn get_stock_by_warehouse(pool: &DbPool, warehouse_id: uuid::Uuid) -> Result<Vec<ProductStock>, Error> {
-        product_stock_repo::get_by_warehouse(pool, warehouse_id).await
+pub async fn get_stock_by_warehouse(pool: &DbPool, warehouse_id: WarehouseId, scope: &WarehouseScope) -> Result<Vec<ProductStock>, AppError> {
+    scope.ensure(warehouse_id)?;
+    Ok(product_stock_repo::get_by_warehouse(pool, warehouse_id).await?)
+}
+
+/// Transfers show up for the warehouses on either end.
+pub async fn get_in_transit(pool: &DbPool, filter: InTransitFilter, scope: &WarehouseScope) -> Result<Vec<InTransitDto>, AppError> {
+    Ok(transfer_item_repo::get_in_transit(pool, filter, scope.ids()).await?)
+}
+
+/// Locks the stock rows a document is about to post to, creating missing
+/// ones. Rows are locked in a stable order so concurrent documents cannot
+/// deadlock; the document's lines can then be posted in any order.
+pub async fn lock_rows(conn: &mut PgConnection, rows: impl IntoIterator<Item = (ProductId, WarehouseId)>) -> Result<(), AppError> {
+    let rows: BTreeSet<_> = rows.into_iter().collect();
+    for (product_id, warehouse_id) in rows {
+        product_stock_repo::lock(conn, product_id, warehouse_id).await?;
+    }
+    Ok(())
+}
+
+// The operations below take stock out of a warehouse. Each one locks the
+// product_stock row first, so concurrent documents touching the same product
+// serialize on that row, and then applies the negative-stock policy.
+
+/// Reserves `quantity` for a delivery. Reservations already held by other
+/// documents are not available.
+pub async fn reserve(conn: &mut PgConnection, product_id: ProductId, warehouse_id: WarehouseId, quantity: i32) -> Result<(), AppError> {
+    let level = product_stock_repo::lock(conn, product_id, warehouse_id).await?;
+    ensure_can_remove(conn, product_id, warehouse_id, level.quantity - level.reserved_quantity, quantity).await?;
+    product_stock_repo::reserve(conn, product_id, warehouse_id, quantity).await?;
+    Ok(())
+}
+
+/// Takes unreserved stock out of a warehouse and returns the new balance.
+pub async fn remove(conn: &mut PgConnection, product_id: ProductId, warehouse_id: WarehouseId, quantity: i32) -> Result<i32, AppError> {
+    let level = product_stock_repo::lock(conn, product_id, warehouse_id).await?;
+    ensure_can_remove(conn, product_id, warehouse_id, level.quantity - level.reserved_quantity, quantity).await?;
+    Ok(product_stock_repo::add_quantity(conn, product_id, warehouse_id, -quantity).await?)
+}
+
+/// Ships stock that was reserved earlier and returns the new balance. On-hand
+/// may have shrunk since the reservation, so it is checked again.
+pub async fn remove_reserved(conn: &mut PgConnection, product_id: ProductId, warehouse_id: WarehouseId, quantity: i32) -> Result<i32, AppError> {
+    let level = product_stock_repo::lock(conn, product_id, warehouse_id).await?;
+    ensure_can_remove(conn, product_id, warehouse_id, level.quantity, quantity).await?;
+    Ok(product_stock_repo::consume_reserved(conn, product_id, warehouse_id, quantity).await?)
+}
+
+/// Applies the product/warehouse negative-stock policy to taking `requested`
+/// out of `available`. The caller must hold the stock row lock.
+pub async fn ensure_can_remove(
+    conn: &mut PgConnection,
+    product_id: ProductId,
+    warehouse_id: WarehouseId,
+    available: i32,
+    requested: i32,
+) -> Result<(), AppError> {
+    if requested <= available {
+        return Ok(());
+    }
+    let policy = product_stock_repo::negative_stock_policy(conn, product_id, warehouse_id)
+        .await?
+        .and_then(|policy| NegativeStockPolicy::parse(&policy))
+        .unwrap_or(NegativeStockPolicy::Forbid);
+    match policy {
+        NegativeStockPolicy::Forbid => Err(AppError::InsufficientStock { product_id, warehouse_id, available, requested }),
+        NegativeStockPolicy::Warn => {
+            tracing::warn!(%product_id, %warehouse_id, available, requested, "stock going negative");
+            Ok(())
+        }
+        NegativeStockPolicy::Allow => Ok(()),
     }
+}
+
+/// Undoes every ledger entry a document posted, writing a compensating entry
+/// for each that points back at the original via `reversal_of`. Stock taken
+/// back out of a warehouse is subject to the negative-stock policy; within a
+/// stock row, stock is put back before any is taken out, so a received and
+/// written-off transfer line reverses without going negative.
+pub async fn reverse_ledger_entries(
+    conn: &mut PgConnection,
+    reference_type: &str,
+    reference_id: i32,
+    user_id: UserId,
+) -> Result<(), AppError> {
+    let mut entries = stock_ledger_repo::get_unreversed_by_reference(conn, reference_type, reference_id).await?;
+    lock_rows(conn, entries.iter().filter_map(|entry| entry.product_id.zip(entry.warehouse_id))).await?;
+    entries.sort_by_key(|entry| (entry.product_id, entry.warehouse_id, entry.quantity_change));
+
+    for entry in entries {
+        let (Some(id), Some(product_id), Some(warehouse_id), Some(quantity_change)) =
+            (entry.id, entry.product_id, entry.warehouse_id, entry.quantity_change)
+        else {
+            continue;
+        };
+        let new_balance = if quantity_change > 0 {
+            remove(conn, product_id, warehouse_id, quantity_change).await?
+        } else {
+            product_stock_repo::add_quantity(conn, product_id, warehouse_id, -quantity_change).await?
+        };
+        let notes = format!("Reversal of ledger entry {id}");
+        stock_ledger_repo::insert(conn, NewStockLedgerEntry {
+            product_id,
+            warehouse_id,
+            user_id: Some(user_id),
+            movement_type: entry.movement_type.as_deref().unwrap_or_default(),
+            reference_id,
+            reference_type,
+            quantity_change: -quantity_change,
+            new_balance,
+            notes: Some(&notes),
+            reversal_of: Some(id),
+        })
+        .await?;
+    }
+    Ok(())
+}
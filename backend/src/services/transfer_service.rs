@@ -0,0 +1,449 @@
+use crate::repository::{product_stock_repo, stock_ledger_repo, transfer_item_repo, transfer_repo};
+use crate::services::stock_service;
+use crate::repository::stock_ledger_repo::NewStockLedgerEntry;
+use crate::dto::transfer_dto::{
+    TransferDto, TransferItemDto, TransferWithItemsDto, CreateTransferRequest, UpdateTransferRequest, CreateTransferItemRequest, UpdateTransferItemRequest,
+    ReceiveTransferRequest, ReceiveTransferLine,
+};
+use crate::models::transfer::TransferStatus;
+use crate::models::ids::{ProductId, TransferId, TransferItemId, UserId};
+use crate::config::database::{self, DbPool};
+use crate::error::{AppError, Result};
+use crate::middleware::WarehouseScope;
+use validator::Validate;
+
+pub async fn get_transfer(pool: &DbPool, transfer_id: TransferId, scope: &WarehouseScope) -> Result<TransferWithItemsDto> {
+    let transfer = transfer_repo::get_by_id(pool, transfer_id)
+        .await?
+        .filter(|transfer| visible(scope, transfer))
+        .ok_or_else(|| AppError::NotFound(format!("Transfer {transfer_id}")))?;
+    let items = transfer_item_repo::get_by_transfer(pool, transfer_id).await?;
+    Ok(TransferWithItemsDto { transfer, items })
+}
+
+/// Stock leaves from the source warehouse, so that is the one the caller
+/// must be assigned to; the destination can be any warehouse.
+pub async fn create_transfer(pool: &DbPool, req: CreateTransferRequest, user_id: UserId, scope: &WarehouseScope) -> Result<TransferDto> {
+    scope.ensure(req.from_warehouse_id)?;
+    Ok(transfer_repo::create(pool, req, user_id).await?)
+}
+
+pub async fn update_transfer(pool: &DbPool, transfer_id: TransferId, req: UpdateTransferRequest, scope: &WarehouseScope) -> Result<TransferDto> {
+    if let Some(warehouse_id) = req.from_warehouse_id {
+        scope.ensure(warehouse_id)?;
+    }
+    let mut tx = database::begin(pool).await?;
+    let current = lock_draft(&mut tx, transfer_id, scope).await?;
+    // The request may change one side only, so check the pair it leaves behind.
+    let from_warehouse_id = req.from_warehouse_id.or(current.from_warehouse_id);
+    let to_warehouse_id = req.to_warehouse_id.or(current.to_warehouse_id);
+    if let (Some(from_warehouse_id), Some(to_warehouse_id)) = (from_warehouse_id, to_warehouse_id) {
+        CreateTransferRequest { from_warehouse_id, to_warehouse_id }.validate()?;
+    }
+    let transfer = transfer_repo::update(&mut tx, transfer_id, req).await?;
+    tx.commit().await?;
+    Ok(transfer)
+}
+
+pub async fn list_items(pool: &DbPool, transfer_id: TransferId, scope: &WarehouseScope) -> Result<Vec<TransferItemDto>> {
+    Ok(get_transfer(pool, transfer_id, scope).await?.items)
+}
+
+pub async fn add_item(pool: &DbPool, transfer_id: TransferId, req: CreateTransferItemRequest, scope: &WarehouseScope) -> Result<TransferItemDto> {
+    if req.quantity <= 0 {
+        return Err(AppError::BadRequest("Quantity must be positive".to_string()));
+    }
+    let mut tx = database::begin(pool).await?;
+    lock_draft(&mut tx, transfer_id, scope).await?;
+    let item = transfer_item_repo::create(&mut tx, transfer_id, req).await?;
+    tx.commit().await?;
+    Ok(item)
+}
+
+pub async fn update_item(pool: &DbPool, transfer_id: TransferId, item_id: TransferItemId, req: UpdateTransferItemRequest, scope: &WarehouseScope) -> Result<TransferItemDto> {
+    if req.quantity.is_some_and(|q| q <= 0) {
+        return Err(AppError::BadRequest("Quantity must be positive".to_string()));
+    }
+    let mut tx = database::begin(pool).await?;
+    lock_draft(&mut tx, transfer_id, scope).await?;
+    let item = transfer_item_repo::update(&mut tx, transfer_id, item_id, req)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Transfer item {item_id}")))?;
+    tx.commit().await?;
+    Ok(item)
+}
+
+pub async fn remove_item(pool: &DbPool, transfer_id: TransferId, item_id: TransferItemId, scope: &WarehouseScope) -> Result<()> {
+    let mut tx = database::begin(pool).await?;
+    lock_draft(&mut tx, transfer_id, scope).await?;
+    if !transfer_item_repo::delete(&mut tx, transfer_id, item_id).await? {
+        return Err(AppError::NotFound(format!("Transfer item {item_id}")));
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Locks the transfer row and fails unless the transfer is still a draft and
+/// the caller may work its source warehouse.
+async fn lock_draft(conn: &mut sqlx::PgConnection, transfer_id: TransferId, scope: &WarehouseScope) -> Result<TransferDto> {
+    let transfer = transfer_repo::find_for_update(conn, transfer_id)
+        .await?
+        .filter(|transfer| visible(scope, transfer))
+        .ok_or_else(|| AppError::NotFound(format!("Transfer {transfer_id}")))?;
+    if transfer.status.as_deref() != Some(TransferStatus::Draft.as_str()) {
+        return Err(AppError::Conflict(format!(
+            "Transfer is '{}'; only draft transfers can be changed",
+            transfer.status.unwrap_or_default()
+        )));
+    }
+    if let Some(from_warehouse_id) = transfer.from_warehouse_id {
+        scope.ensure(from_warehouse_id)?;
+    }
+    Ok(transfer)
+}
+
+/// Only drafts can be deleted; shipped transfers are reversed instead.
+pub async fn delete_transfer(pool: &DbPool, transfer_id: TransferId, scope: &WarehouseScope) -> Result<()> {
+    let mut tx = database::begin(pool).await?;
+    lock_draft(&mut tx, transfer_id, scope).await?;
+    transfer_repo::delete(&mut tx, transfer_id).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Undoes both legs of a shipped transfer: whatever left the source goes back
+/// and whatever landed at the destination is taken out again. Anything still
+/// in transit is dropped along with the transfer, which is marked cancelled.
+pub async fn reverse_transfer(pool: &DbPool, transfer_id: TransferId, user_id: UserId, scope: &WarehouseScope) -> Result<TransferDto> {
+    let mut tx = database::begin(pool).await?;
+    let transfer = transfer_repo::find_for_update(&mut tx, transfer_id)
+        .await?
+        .filter(|transfer| visible(scope, transfer))
+        .ok_or_else(|| AppError::NotFound(format!("Transfer {transfer_id}")))?;
+    let status = transfer.status.as_deref().unwrap_or_default();
+    if status != TransferStatus::InTransit.as_str() && status != TransferStatus::Received.as_str() {
+        return Err(AppError::Conflict(format!(
+            "Transfer is '{status}'; only shipped transfers can be reversed"
+        )));
+    }
+    for warehouse_id in [transfer.from_warehouse_id, transfer.to_warehouse_id].into_iter().flatten() {
+        scope.ensure(warehouse_id)?;
+    }
+    stock_service::reverse_ledger_entries(&mut tx, "transfer", transfer_id.0, user_id).await?;
+    let transfer = transfer_repo::set_status(&mut *tx, transfer_id, TransferStatus::Cancelled.as_str()).await?;
+    tx.commit().await?;
+    Ok(transfer)
+}
+
+/// Ships a draft transfer: every line leaves the source warehouse in one
+/// transaction, with a `Transfer` ledger row per line, and is held in transit
+/// until it is received at the destination.
+pub async fn ship_transfer(pool: &DbPool, transfer_id: TransferId, user_id: UserId, scope: &WarehouseScope) -> Result<TransferDto> {
+    let mut tx = database::begin(pool).await?;
+
+    let transfer = lock_draft(&mut tx, transfer_id, scope).await?;
+    let (Some(from_warehouse_id), Some(to_warehouse_id)) = (transfer.from_warehouse_id, transfer.to_warehouse_id) else {
+        return Err(AppError::BadRequest("Transfer needs both a source and a destination warehouse".to_string()));
+    };
+    if from_warehouse_id == to_warehouse_id {
+        return Err(AppError::BadRequest("Source and destination warehouses must differ".to_string()));
+    }
+
+    let items = transfer_item_repo::get_by_transfer(&mut *tx, transfer_id).await?;
+    if items.is_empty() {
+        return Err(AppError::BadRequest("Transfer has no items".to_string()));
+    }
+    stock_service::lock_rows(&mut tx, items.iter().filter_map(|item| item.product_id).map(|product_id| (product_id, from_warehouse_id))).await?;
+
+    let note = format!("Shipped to warehouse {to_warehouse_id}");
+    for item in &items {
+        let (product_id, quantity) = item_line(item)?;
+        let item_id = item.id.unwrap_or_default();
+
+        let new_balance = stock_service::remove(&mut tx, product_id, from_warehouse_id, quantity).await?;
+        transfer_item_repo::mark_shipped(&mut tx, transfer_id, item_id).await?;
+        stock_ledger_repo::insert(&mut tx, NewStockLedgerEntry {
+            product_id,
+            warehouse_id: from_warehouse_id,
+            user_id: Some(user_id),
+            movement_type: "Transfer",
+            reference_id: transfer_id.0,
+            reference_type: "transfer",
+            quantity_change: -quantity,
+            new_balance,
+            notes: Some(&note),
+            reversal_of: None,
+        })
+        .await?;
+    }
+
+    let transfer = transfer_repo::mark_shipped(&mut tx, transfer_id).await?;
+    tx.commit().await?;
+    Ok(transfer)
+}
+
+/// Lands shipped quantities at the destination warehouse. Lines may be received
+/// partially and across several calls; units that will never arrive are booked
+/// as a discrepancy instead. They are received and then written off with a
+/// `Transfer loss` ledger row, so the ledger accounts for every unit shipped.
+/// The transfer becomes `received` once nothing is left in transit.
+pub async fn receive_transfer(pool: &DbPool, transfer_id: TransferId, req: ReceiveTransferRequest, user_id: UserId, scope: &WarehouseScope) -> Result<TransferWithItemsDto> {
+    let mut tx = database::begin(pool).await?;
+
+    let transfer = transfer_repo::find_for_update(&mut tx, transfer_id)
+        .await?
+        .filter(|transfer| visible(scope, transfer))
+        .ok_or_else(|| AppError::NotFound(format!("Transfer {transfer_id}")))?;
+    if transfer.status.as_deref() != Some(TransferStatus::InTransit.as_str()) {
+        return Err(AppError::Conflict(format!(
+            "Transfer is '{}'; only transfers in transit can be received",
+            transfer.status.unwrap_or_default()
+        )));
+    }
+    let (Some(from_warehouse_id), Some(to_warehouse_id)) = (transfer.from_warehouse_id, transfer.to_warehouse_id) else {
+        return Err(AppError::BadRequest("Transfer needs both a source and a destination warehouse".to_string()));
+    };
+    scope.ensure(to_warehouse_id)?;
+
+    let items = transfer_item_repo::get_by_transfer(&mut *tx, transfer_id).await?;
+    let lines = if req.lines.is_empty() {
+        items
+            .iter()
+            .filter(|item| in_transit(item) > 0)
+            .map(|item| ReceiveTransferLine {
+                item_id: item.id.unwrap_or_default(),
+                received_quantity: in_transit(item),
+                discrepancy_quantity: None,
+                notes: None,
+            })
+            .collect()
+    } else {
+        req.lines
+    };
+
+    let products = lines
+        .iter()
+        .filter_map(|line| items.iter().find(|item| item.id == Some(line.item_id)))
+        .filter_map(|item| item.product_id);
+    stock_service::lock_rows(&mut tx, products.map(|product_id| (product_id, to_warehouse_id))).await?;
+
+    let note = format!("Received from warehouse {from_warehouse_id}");
+    for line in lines {
+        let discrepancy = line.discrepancy_quantity.unwrap_or(0);
+        let landed = line
+            .received_quantity
+            .checked_add(discrepancy)
+            .filter(|&landed| landed > 0 && line.received_quantity >= 0 && discrepancy >= 0);
+        let Some(landed) = landed else {
+            return Err(AppError::BadRequest(format!(
+                "Item {} needs a positive received or discrepancy quantity",
+                line.item_id
+            )));
+        };
+        let item = items
+            .iter()
+            .find(|item| item.id == Some(line.item_id))
+            .ok_or_else(|| AppError::NotFound(format!("Transfer item {}", line.item_id)))?;
+        let (product_id, _) = item_line(item)?;
+
+        transfer_item_repo::receive(&mut tx, transfer_id, line.item_id, line.received_quantity, discrepancy, line.notes.as_deref())
+            .await?
+            .ok_or_else(|| AppError::BadRequest(format!(
+                "Item {} has less than {} units in transit",
+                line.item_id,
+                landed
+            )))?;
+
+        let new_balance = product_stock_repo::add_quantity(&mut tx, product_id, to_warehouse_id, landed).await?;
+        stock_ledger_repo::insert(&mut tx, NewStockLedgerEntry {
+            product_id,
+            warehouse_id: to_warehouse_id,
+            user_id: Some(user_id),
+            movement_type: "Transfer",
+            reference_id: transfer_id.0,
+            reference_type: "transfer",
+            quantity_change: landed,
+            new_balance,
+            notes: Some(&note),
+            reversal_of: None,
+        })
+        .await?;
+        if discrepancy > 0 {
+            let new_balance = product_stock_repo::add_quantity(&mut tx, product_id, to_warehouse_id, -discrepancy).await?;
+            let loss_note = line.notes.as_deref().map_or_else(|| "Lost in transit".to_string(), |notes| format!("Lost in transit: {notes}"));
+            stock_ledger_repo::insert(&mut tx, NewStockLedgerEntry {
+                product_id,
+                warehouse_id: to_warehouse_id,
+                user_id: Some(user_id),
+                movement_type: "Transfer loss",
+                reference_id: transfer_id.0,
+                reference_type: "transfer",
+                quantity_change: -discrepancy,
+                new_balance,
+                notes: Some(&loss_note),
+                reversal_of: None,
+            })
+            .await?;
+        }
+    }
+
+    let items = transfer_item_repo::get_by_transfer(&mut *tx, transfer_id).await?;
+    let transfer = if items.iter().all(|item| in_transit(item) == 0) {
+        transfer_repo::set_status(&mut *tx, transfer_id, TransferStatus::Received.as_str()).await?
+    } else {
+        transfer
+    };
+    tx.commit().await?;
+    Ok(TransferWithItemsDto { transfer, items })
+}
+
+/// A transfer is visible from the warehouses on either end.
+fn visible(scope: &WarehouseScope, transfer: &TransferDto) -> bool {
+    scope.allows(transfer.from_warehouse_id) || scope.allows(transfer.to_warehouse_id)
+}
+
+fn in_transit(item: &TransferItemDto) -> i32 {
+    item.shipped_quantity.unwrap_or(0) - item.received_quantity.unwrap_or(0) - item.discrepancy_quantity.unwrap_or(0)
+}
+
+fn item_line(item: &TransferItemDto) -> Result<(ProductId, i32)> {
+    match (item.product_id, item.quantity) {
+        (Some(product_id), Some(quantity)) => Ok((product_id, quantity)),
+        _ => Err(AppError::BadRequest("Transfer item is missing a product or quantity".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ids::WarehouseId;
+    use crate::test_support;
+
+    struct Shipped {
+        user_id: UserId,
+        from: WarehouseId,
+        to: WarehouseId,
+        product_id: ProductId,
+        transfer_id: TransferId,
+        item_id: TransferItemId,
+    }
+
+    /// Ships 10 of a product from a warehouse holding 10 to an empty one.
+    async fn shipped(pool: &DbPool) -> Shipped {
+        let user_id = test_support::user(pool, "a@x.io", "-").await;
+        let from = test_support::warehouse(pool, "North").await;
+        let to = test_support::warehouse(pool, "South").await;
+        let product_id = test_support::product(pool, "BOLT").await;
+        test_support::stock_in(pool, product_id, from, 10).await;
+
+        let req = CreateTransferRequest { from_warehouse_id: from, to_warehouse_id: to };
+        let transfer_id = create_transfer(pool, req, user_id, &WarehouseScope::All).await.unwrap().id.unwrap();
+        let req = CreateTransferItemRequest { product_id, quantity: 10 };
+        let item_id = add_item(pool, transfer_id, req, &WarehouseScope::All).await.unwrap().id.unwrap();
+        ship_transfer(pool, transfer_id, user_id, &WarehouseScope::All).await.unwrap();
+        Shipped { user_id, from, to, product_id, transfer_id, item_id }
+    }
+
+    fn line(item_id: TransferItemId, received_quantity: i32, discrepancy_quantity: Option<i32>) -> ReceiveTransferRequest {
+        ReceiveTransferRequest { lines: vec![ReceiveTransferLine { item_id, received_quantity, discrepancy_quantity, notes: None }] }
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn shipping_takes_stock_out_and_leaves_it_in_transit(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let t = shipped(&pool).await;
+
+        let transfer = get_transfer(&pool, t.transfer_id, &WarehouseScope::All).await.unwrap();
+
+        assert_eq!(transfer.transfer.status.as_deref(), Some("in_transit"));
+        assert_eq!(test_support::stock(&pool, t.product_id, t.from).await, (0, 0));
+        assert_eq!(test_support::stock(&pool, t.product_id, t.to).await, (0, 0));
+        assert_eq!(test_support::ledger(&pool, "transfer", t.transfer_id.0).await, [("Transfer".to_string(), -10, 0)]);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn partial_receipts_stay_in_transit_until_everything_lands(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let t = shipped(&pool).await;
+
+        let first = receive_transfer(&pool, t.transfer_id, line(t.item_id, 4, None), t.user_id, &WarehouseScope::All).await.unwrap();
+        assert_eq!(first.transfer.status.as_deref(), Some("in_transit"));
+        assert_eq!(test_support::stock(&pool, t.product_id, t.to).await, (4, 0));
+
+        let rest = ReceiveTransferRequest { lines: Vec::new() };
+        let second = receive_transfer(&pool, t.transfer_id, rest, t.user_id, &WarehouseScope::All).await.unwrap();
+        assert_eq!(second.transfer.status.as_deref(), Some("received"));
+        assert_eq!(test_support::stock(&pool, t.product_id, t.to).await, (10, 0));
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn discrepancies_are_received_and_written_off_as_transfer_loss(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let t = shipped(&pool).await;
+
+        let received = receive_transfer(&pool, t.transfer_id, line(t.item_id, 7, Some(3)), t.user_id, &WarehouseScope::All).await.unwrap();
+
+        assert_eq!(received.transfer.status.as_deref(), Some("received"));
+        assert_eq!(test_support::stock(&pool, t.product_id, t.to).await, (7, 0));
+        assert_eq!(
+            test_support::ledger(&pool, "transfer", t.transfer_id.0).await,
+            [("Transfer".to_string(), -10, 0), ("Transfer".to_string(), 10, 10), ("Transfer loss".to_string(), -3, 7)]
+        );
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn receiving_more_than_is_in_transit_is_rejected(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let t = shipped(&pool).await;
+
+        let too_many = receive_transfer(&pool, t.transfer_id, line(t.item_id, 11, None), t.user_id, &WarehouseScope::All).await;
+        let overflow = receive_transfer(&pool, t.transfer_id, line(t.item_id, 1, Some(i32::MAX)), t.user_id, &WarehouseScope::All).await;
+
+        assert!(matches!(too_many, Err(AppError::BadRequest(_))));
+        assert!(matches!(overflow, Err(AppError::BadRequest(_))));
+        assert_eq!(test_support::stock(&pool, t.product_id, t.to).await, (0, 0));
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn reversing_a_received_transfer_restores_both_warehouses(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let t = shipped(&pool).await;
+        receive_transfer(&pool, t.transfer_id, line(t.item_id, 7, Some(3)), t.user_id, &WarehouseScope::All).await.unwrap();
+
+        let transfer = reverse_transfer(&pool, t.transfer_id, t.user_id, &WarehouseScope::All).await.unwrap();
+
+        assert_eq!(transfer.status.as_deref(), Some("cancelled"));
+        assert_eq!(test_support::stock(&pool, t.product_id, t.from).await, (10, 0));
+        assert_eq!(test_support::stock(&pool, t.product_id, t.to).await, (0, 0));
+        let total: i32 = test_support::ledger(&pool, "transfer", t.transfer_id.0).await.iter().map(|(_, change, _)| change).sum();
+        assert_eq!(total, 0);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn updates_cannot_point_one_side_at_the_other(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let user_id = test_support::user(&pool, "a@x.io", "-").await;
+        let from = test_support::warehouse(&pool, "North").await;
+        let to = test_support::warehouse(&pool, "South").await;
+        let req = CreateTransferRequest { from_warehouse_id: from, to_warehouse_id: to };
+        let transfer_id = create_transfer(&pool, req, user_id, &WarehouseScope::All).await.unwrap().id.unwrap();
+
+        let req = UpdateTransferRequest { from_warehouse_id: None, to_warehouse_id: Some(from) };
+        let updated = update_transfer(&pool, transfer_id, req, &WarehouseScope::All).await;
+
+        assert!(matches!(updated, Err(AppError::Validation(details)) if details[0].field == "to_warehouse_id"));
+        let transfer = get_transfer(&pool, transfer_id, &WarehouseScope::All).await.unwrap().transfer;
+        assert_eq!(transfer.to_warehouse_id, Some(to));
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn only_transfers_in_transit_can_be_received(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let t = shipped(&pool).await;
+        receive_transfer(&pool, t.transfer_id, line(t.item_id, 10, None), t.user_id, &WarehouseScope::All).await.unwrap();
+
+        let again = receive_transfer(&pool, t.transfer_id, line(t.item_id, 1, None), t.user_id, &WarehouseScope::All).await;
+
+        assert!(matches!(again, Err(AppError::Conflict(_))));
+    }
+}
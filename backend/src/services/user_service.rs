@@ -0,0 +1,166 @@
+use crate::repository::audit_repo::{self, AuditEvent};
+use crate::repository::{session_repo, user_repo, user_warehouse_repo};
+use crate::dto::auth_dto::RevokedSessionsDto;
+use crate::dto::user_dto::{CreateUserRequest, RoleDto, UpdateUserRequest, UserDto, UserWarehousesDto};
+use crate::models::ids::{UserId, WarehouseId};
+use crate::models::role::Role;
+use crate::config::database::{self, DbPool};
+use crate::error::{AppError, Result};
+use crate::utils::password;
+use sqlx::PgConnection;
+
+pub fn list_roles() -> Vec<RoleDto> {
+    Role::ALL
+        .into_iter()
+        .map(|role| RoleDto { role, permissions: role.permissions().to_vec() })
+        .collect()
+}
+
+/// Creates the first admin from `ADMIN_EMAIL`, `ADMIN_PASSWORD` and
+/// `ADMIN_NAME` (default "Admin") at startup. Self-registration only makes
+/// operators, so a fresh install cannot be claimed by whoever signs up first.
+/// Does nothing when the variables are unset or an admin already exists.
+pub async fn bootstrap_admin(pool: &DbPool) -> Result<()> {
+    let (Ok(email), Ok(password)) = (std::env::var("ADMIN_EMAIL"), std::env::var("ADMIN_PASSWORD")) else {
+        return Ok(());
+    };
+    let name = std::env::var("ADMIN_NAME").unwrap_or_else(|_| "Admin".to_string());
+    let password_hash = password::hash_password(&password).await?;
+    if let Some(admin) = user_repo::create_first_admin(pool, &name, &email, &password_hash).await? {
+        tracing::info!("Created admin {}", admin.email);
+    }
+    Ok(())
+}
+
+pub async fn create_user(pool: &DbPool, req: CreateUserRequest) -> Result<UserDto> {
+    let password_hash = password::hash_password(&req.password).await?;
+    Ok(user_repo::create(pool, req, &password_hash).await?)
+}
+
+/// A new password also logs the user out everywhere, the same as
+/// `revoke_sessions`, so whoever held the old one keeps no session.
+pub async fn update_user(pool: &DbPool, user_id: UserId, req: UpdateUserRequest) -> Result<UserDto> {
+    let password_hash = match &req.password {
+        Some(new_password) => Some(password::hash_password(new_password).await?),
+        None => None,
+    };
+    let mut tx = database::begin(pool).await?;
+    let user = user_repo::update(&mut tx, user_id, req, password_hash.as_deref())
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("User {user_id}")))?;
+    if password_hash.is_some() {
+        revoke_all(&mut tx, user_id, "password_changed").await?;
+    }
+    tx.commit().await?;
+    Ok(user)
+}
+
+pub async fn assign_role(pool: &DbPool, user_id: UserId, role: Role) -> Result<UserDto> {
+    let mut tx = database::begin(pool).await?;
+    if role != Role::Admin {
+        ensure_not_last_admin(&mut tx, user_id).await?;
+    }
+    let user = user_repo::set_role(&mut tx, user_id, role)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("User {user_id}")))?;
+    tx.commit().await?;
+    Ok(user)
+}
+
+pub async fn delete_user(pool: &DbPool, user_id: UserId) -> Result<()> {
+    let mut tx = database::begin(pool).await?;
+    ensure_not_last_admin(&mut tx, user_id).await?;
+    if !user_repo::delete(&mut tx, user_id).await? {
+        return Err(AppError::NotFound(format!("User {user_id}")));
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn get_warehouses(pool: &DbPool, user_id: UserId) -> Result<UserWarehousesDto> {
+    user_repo::get_by_id(pool, user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("User {user_id}")))?;
+    let warehouse_ids = user_warehouse_repo::get_by_user(pool, user_id).await?;
+    Ok(UserWarehousesDto { user_id, warehouse_ids })
+}
+
+pub async fn assign_warehouses(pool: &DbPool, user_id: UserId, warehouse_ids: Vec<WarehouseId>) -> Result<UserWarehousesDto> {
+    let mut tx = database::begin(pool).await?;
+    user_repo::get_by_id(&mut *tx, user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("User {user_id}")))?;
+    user_warehouse_repo::replace(&mut tx, user_id, &warehouse_ids).await?;
+    let warehouse_ids = user_warehouse_repo::get_by_user(&mut *tx, user_id).await?;
+    tx.commit().await?;
+    Ok(UserWarehousesDto { user_id, warehouse_ids })
+}
+
+/// Logs the user out everywhere, e.g. when they leave.
+pub async fn revoke_sessions(pool: &DbPool, user_id: UserId) -> Result<RevokedSessionsDto> {
+    user_repo::get_by_id(pool, user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("User {user_id}")))?;
+    let mut tx = database::begin(pool).await?;
+    let revoked = revoke_all(&mut tx, user_id, "revoked_by_admin").await?;
+    tx.commit().await?;
+    Ok(RevokedSessionsDto { revoked })
+}
+
+/// Revokes every session of the user and records why in the audit log.
+async fn revoke_all(conn: &mut PgConnection, user_id: UserId, reason: &str) -> Result<u64> {
+    let revoked = session_repo::revoke_all(&mut *conn, user_id).await?;
+    let event = AuditEvent {
+        action: "auth.sessions_revoked",
+        entity_type: "users",
+        entity_id: Some(user_id.0),
+        details: Some(serde_json::json!({ "revoked": revoked, "reason": reason })),
+        actor_id: None,
+    };
+    audit_repo::record(conn, event).await?;
+    Ok(revoked)
+}
+
+/// Refuses to demote or delete the only remaining admin, which would leave
+/// nobody able to assign roles.
+async fn ensure_not_last_admin(conn: &mut sqlx::PgConnection, user_id: UserId) -> Result<()> {
+    let admins = user_repo::lock_admins(conn).await?;
+    if admins == [user_id] {
+        return Err(AppError::Conflict("Cannot remove the last admin".to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    #[sqlx::test(migrations = false)]
+    async fn only_the_first_admin_is_created(pool: DbPool) {
+        test_support::migrate(&pool).await;
+
+        let first = user_repo::create_first_admin(&pool, "Admin", "admin@x.io", "-").await.unwrap();
+        let second = user_repo::create_first_admin(&pool, "Admin", "other@x.io", "-").await.unwrap();
+
+        assert_eq!(first.map(|user| user.role).as_deref(), Some("admin"));
+        assert!(second.is_none());
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn changing_a_password_revokes_the_users_sessions(pool: DbPool) {
+        test_support::migrate(&pool).await;
+        let user_id = test_support::user(&pool, "a@x.io", "-").await;
+        let expires_at = (chrono::Utc::now() + chrono::Duration::days(1)).naive_utc();
+        session_repo::create(&pool, user_id, "old-refresh-token", expires_at).await.unwrap();
+        let active = || sqlx::query_scalar!("SELECT COUNT(*) FROM sessions WHERE user_id = $1 AND revoked_at IS NULL", user_id.0).fetch_one(&pool);
+
+        let rename = UpdateUserRequest { name: Some("Renamed".to_string()), email: None, password: None };
+        update_user(&pool, user_id, rename).await.unwrap();
+        assert_eq!(active().await.unwrap(), Some(1));
+
+        let reset = UpdateUserRequest { name: None, email: None, password: Some("new-secret".to_string()) };
+        update_user(&pool, user_id, reset).await.unwrap();
+        assert_eq!(active().await.unwrap(), Some(0));
+    }
+}
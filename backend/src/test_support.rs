@@ -0,0 +1,121 @@
+//! Fixtures for the `#[sqlx::test]` tests. Each test gets a fresh database;
+//! `migrate` builds the schema and the rest insert the minimum the services
+//! need to run against it.
+use crate::config::database::DbPool;
+use crate::middleware::auth::API_KEY_PREFIX;
+use crate::models::ids::{ProductId, UserId, WarehouseId};
+use crate::utils::token;
+use sqlx::Executor;
+
+/// Applies `migrations/` in file name order, the way they are run by hand.
+/// The files share one date prefix, which sqlx's migrator reads as a single
+/// version, so tests opt out of it with `migrations = false`.
+pub async fn migrate(pool: &DbPool) {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/migrations");
+    let mut files: Vec<_> = std::fs::read_dir(dir).unwrap().map(|entry| entry.unwrap().path()).collect();
+    files.sort();
+    for file in files {
+        let sql = std::fs::read_to_string(&file).unwrap();
+        pool.execute(sql.as_str()).await.unwrap_or_else(|e| panic!("{}: {e}", file.display()));
+    }
+}
+
+pub async fn user(pool: &DbPool, email: &str, password_hash: &str) -> UserId {
+    sqlx::query_scalar!(
+        r#"INSERT INTO users (name, email, password_hash, role, email_verified_at) VALUES ('Test', $1, $2, 'admin', NOW())
+        RETURNING id AS "id: UserId""#,
+        email, password_hash
+    )
+    .fetch_one(pool)
+    .await
+    .unwrap()
+}
+
+pub async fn warehouse(pool: &DbPool, name: &str) -> WarehouseId {
+    sqlx::query_scalar!(r#"INSERT INTO warehouses (name) VALUES ($1) RETURNING id AS "id: WarehouseId""#, name)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+pub async fn product(pool: &DbPool, sku: &str) -> ProductId {
+    sqlx::query_scalar!(
+        r#"INSERT INTO products (sku, name, unit_of_measure, reorder_threshold) VALUES ($1, $1, 'pcs', 0)
+        RETURNING id AS "id: ProductId""#,
+        sku
+    )
+    .fetch_one(pool)
+    .await
+    .unwrap()
+}
+
+/// An API key acting for `user_id` with the given scopes; returns the key.
+pub async fn api_key(pool: &DbPool, user_id: UserId, scopes: &[&str]) -> String {
+    let key = format!("{API_KEY_PREFIX}{}", token::generate());
+    let scopes: Vec<String> = scopes.iter().map(|scope| scope.to_string()).collect();
+    sqlx::query!(
+        "INSERT INTO api_keys (name, key_prefix, key_hash, scopes, created_by) VALUES ('Test', $1, $2, $3, $4)",
+        &key[..8], token::hash(&key), &scopes, user_id as _
+    )
+    .execute(pool)
+    .await
+    .unwrap();
+    key
+}
+
+/// Puts `quantity` on hand directly, for tests that start from existing stock.
+pub async fn stock_in(pool: &DbPool, product_id: ProductId, warehouse_id: WarehouseId, quantity: i32) {
+    sqlx::query!(
+        "INSERT INTO product_stock (product_id, warehouse_id, quantity) VALUES ($1, $2, $3)",
+        product_id as _, warehouse_id as _, quantity
+    )
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
+/// Holds `quantity` of existing stock, as a picked delivery would.
+pub async fn reserve(pool: &DbPool, product_id: ProductId, warehouse_id: WarehouseId, quantity: i32) {
+    sqlx::query!(
+        "UPDATE product_stock SET reserved_quantity = reserved_quantity + $3 WHERE product_id = $1 AND warehouse_id = $2",
+        product_id as _, warehouse_id as _, quantity
+    )
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
+/// Sets the product's negative-stock policy, overriding its warehouses'.
+pub async fn product_policy(pool: &DbPool, product_id: ProductId, policy: &str) {
+    sqlx::query!("UPDATE products SET negative_stock_policy = $2 WHERE id = $1", product_id as _, policy)
+        .execute(pool)
+        .await
+        .unwrap();
+}
+
+/// On-hand and reserved quantity; a missing stock row counts as zero.
+pub async fn stock(pool: &DbPool, product_id: ProductId, warehouse_id: WarehouseId) -> (i32, i32) {
+    sqlx::query!(
+        "SELECT quantity, reserved_quantity FROM product_stock WHERE product_id = $1 AND warehouse_id = $2",
+        product_id as _, warehouse_id as _
+    )
+    .fetch_optional(pool)
+    .await
+    .unwrap()
+    .map_or((0, 0), |row| (row.quantity, row.reserved_quantity))
+}
+
+/// A document's ledger rows in posting order, as (movement type, change, new balance).
+pub async fn ledger(pool: &DbPool, reference_type: &str, reference_id: i32) -> Vec<(String, i32, i32)> {
+    sqlx::query!(
+        "SELECT movement_type, quantity_change, new_balance FROM stock_ledger
+        WHERE reference_type = $1 AND reference_id = $2 ORDER BY id",
+        reference_type, reference_id
+    )
+    .fetch_all(pool)
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|row| (row.movement_type, row.quantity_change, row.new_balance))
+    .collect()
+}
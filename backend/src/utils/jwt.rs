@@ -1,25 +1,37 @@
 // src/utils/jwt.rs
 use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
+use crate::models::ids::{SessionId, UserId};
 use chrono::{Utc, Duration};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
-    pub sub: Uuid,           // user id
+    pub sub: UserId,         // user id
+    pub sid: SessionId,      // session the token was issued for
     pub exp: usize,          // expiration timestamp
     pub iat: usize,          // issued at
 }
 
-pub fn create_jwt(user_id: Uuid) -> crate::error::Result<String> {
+/// Access tokens are short-lived; clients renew them with a refresh token.
+/// `ACCESS_TOKEN_TTL_MINUTES` overrides the default of 15.
+pub fn access_token_ttl() -> Duration {
+    let minutes = std::env::var("ACCESS_TOKEN_TTL_MINUTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(15);
+    Duration::minutes(minutes)
+}
+
+pub fn create_jwt(user_id: UserId, session_id: SessionId) -> crate::error::Result<String> {
     let secret = std::env::var("JWT_SECRET").expect("JWT_SECRET missing");
     let expiration = Utc::now()
-        .checked_add_signed(Duration::hours(24))
+        .checked_add_signed(access_token_ttl())
         .expect("valid timestamp")
         .timestamp() as usize;
 
     let claims = Claims {
         sub: user_id,
+        sid: session_id,
         exp: expiration,
         iat: Utc::now().timestamp() as usize,
     };
@@ -32,6 +44,8 @@ pub fn create_jwt(user_id: Uuid) -> crate::error::Result<String> {
         .map_err(Into::into)
 }
 
+/// Checks the signature and expiry only; whether the session has since been
+/// revoked is up to the caller (see `require_auth`).
 pub fn verify_jwt(token: &str) -> crate::error::Result<Claims> {
     let secret = std::env::var("JWT_SECRET").expect("JWT_SECRET missing");
     let mut validation = Validation::new(Algorithm::HS256);
@@ -44,4 +58,4 @@ pub fn verify_jwt(token: &str) -> crate::error::Result<Claims> {
     )
         .map(|data| data.claims)
         .map_err(Into::into)
-}
\ No newline at end of file
+}
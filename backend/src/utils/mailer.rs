@@ -0,0 +1,110 @@
+// src/utils/mailer.rs
+use std::{path::PathBuf, sync::Arc};
+
+use anyhow::Context;
+use axum::async_trait;
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+
+/// Delivers one plain-text message. Picked at startup by `from_env`; the
+/// outbox worker is the only caller.
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, to: &str, subject: &str, body: &str) -> anyhow::Result<()>;
+}
+
+/// Sends through an SMTP server, e.g. a local mail catcher in development.
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+/// Writes each message as an `.eml` file into a directory.
+pub struct FileMailer {
+    dir: PathBuf,
+    from: Mailbox,
+}
+
+/// Logs each message instead of sending it.
+pub struct LogMailer;
+
+fn build_message(from: &Mailbox, to: &str, subject: &str, body: &str) -> anyhow::Result<Message> {
+    let to: Mailbox = to.parse().with_context(|| format!("invalid recipient {to:?}"))?;
+    Message::builder()
+        .from(from.clone())
+        .to(to)
+        .subject(subject)
+        .header(ContentType::TEXT_PLAIN)
+        .body(body.to_string())
+        .context("building message")
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, to: &str, subject: &str, body: &str) -> anyhow::Result<()> {
+        let message = build_message(&self.from, to, subject, body)?;
+        self.transport.send(message).await.context("SMTP delivery")?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, to: &str, subject: &str, body: &str) -> anyhow::Result<()> {
+        let message = build_message(&self.from, to, subject, body)?;
+        let name = format!("{}.eml", chrono::Utc::now().format("%Y%m%dT%H%M%S%.6f"));
+        tokio::fs::create_dir_all(&self.dir).await?;
+        tokio::fs::write(self.dir.join(name), message.formatted()).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, to: &str, subject: &str, body: &str) -> anyhow::Result<()> {
+        tracing::info!(%to, %subject, "Mail not sent (MAIL_TRANSPORT=log):\n{body}");
+        Ok(())
+    }
+}
+
+/// Builds the mailer named by `MAIL_TRANSPORT`: `smtp`, `file` or `log` (the
+/// default). Panics on incomplete settings, like the rest of startup.
+///
+/// - `MAIL_FROM`: sender, default `StockMaster <noreply@localhost>`
+/// - `SMTP_HOST`, `SMTP_PORT`, `SMTP_USERNAME`, `SMTP_PASSWORD`
+/// - `SMTP_TLS`: `starttls` (default), `tls`, or `none` for a local mail catcher
+/// - `MAIL_DIR`: where the file sink writes, default `mail`
+pub fn from_env() -> Arc<dyn Mailer> {
+    let from: Mailbox = std::env::var("MAIL_FROM")
+        .unwrap_or_else(|_| "StockMaster <noreply@localhost>".to_string())
+        .parse()
+        .expect("MAIL_FROM must be a valid mailbox");
+
+    match std::env::var("MAIL_TRANSPORT").as_deref() {
+        Ok("smtp") => {
+            let host = std::env::var("SMTP_HOST").expect("SMTP_HOST must be set when MAIL_TRANSPORT=smtp");
+            let mut builder = match std::env::var("SMTP_TLS").as_deref() {
+                Ok("none") => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&host),
+                Ok("tls") => AsyncSmtpTransport::<Tokio1Executor>::relay(&host).expect("valid SMTP_HOST"),
+                Ok("starttls") | Err(_) => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host).expect("valid SMTP_HOST"),
+                Ok(other) => panic!("SMTP_TLS must be none, tls or starttls, not {other:?}"),
+            };
+            if let Ok(port) = std::env::var("SMTP_PORT") {
+                builder = builder.port(port.parse().expect("SMTP_PORT must be a valid number"));
+            }
+            if let (Ok(user), Ok(password)) = (std::env::var("SMTP_USERNAME"), std::env::var("SMTP_PASSWORD")) {
+                builder = builder.credentials(Credentials::new(user, password));
+            }
+            Arc::new(SmtpMailer { transport: builder.build(), from })
+        }
+        Ok("file") => {
+            let dir = std::env::var("MAIL_DIR").unwrap_or_else(|_| "mail".to_string());
+            Arc::new(FileMailer { dir: dir.into(), from })
+        }
+        Ok("log") | Err(_) => Arc::new(LogMailer),
+        Ok(other) => panic!("MAIL_TRANSPORT must be smtp, file or log, not {other:?}"),
+    }
+}
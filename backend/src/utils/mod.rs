@@ -1,3 +1,6 @@
 // src/utils/mod.rs
 pub mod jwt;
-pub mod password;
\ No newline at end of file
+pub mod mailer;
+pub mod password;
+pub mod token;
+pub mod validation;
@@ -1,10 +1,107 @@
 // src/utils/password.rs
-use bcrypt::{hash, verify, DEFAULT_COST};
+use std::sync::OnceLock;
 
-pub fn hash_password(password: &str) -> crate::error::Result<String> {
-    hash(password, DEFAULT_COST).map_err(Into::into)
+use argon2::{Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version};
+use password_hash::{SaltString, rand_core::OsRng};
+
+use crate::error::{AppError, Result};
+use crate::utils::token;
+
+/// Argon2id with the parameters from `PASSWORD_ARGON2_MEMORY_KIB`,
+/// `PASSWORD_ARGON2_ITERATIONS` and `PASSWORD_ARGON2_PARALLELISM`. The
+/// defaults (19 MiB, 2, 1) follow the OWASP recommendation.
+fn argon2() -> Argon2<'static> {
+    let var = |name: &str, default: u32| {
+        std::env::var(name)
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(default)
+    };
+    let params = Params::new(
+        var("PASSWORD_ARGON2_MEMORY_KIB", Params::DEFAULT_M_COST),
+        var("PASSWORD_ARGON2_ITERATIONS", Params::DEFAULT_T_COST),
+        var("PASSWORD_ARGON2_PARALLELISM", Params::DEFAULT_P_COST),
+        None,
+    )
+    .expect("invalid PASSWORD_ARGON2_* parameters");
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
 }
 
-pub fn verify_password(password: &str, hash: &str) -> crate::error::Result<bool> {
-    verify(password, hash).map_err(Into::into)
-}
\ No newline at end of file
+pub async fn hash_password(password: &str) -> Result<String> {
+    let password = password.to_string();
+    blocking(move || hash(&password)).await
+}
+
+fn hash(password: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    argon2()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| {
+            tracing::error!("Password hashing failed: {e}");
+            AppError::Internal
+        })
+}
+
+/// Accepts argon2 hashes (any variant, with the parameters recorded in the
+/// hash) and the bcrypt hashes older accounts were created with.
+pub async fn verify_password(password: &str, hash: &str) -> Result<bool> {
+    let (password, hash) = (password.to_string(), hash.to_string());
+    blocking(move || verify(&password, &hash)).await
+}
+
+/// Does the work of `verify_password` against a throwaway hash, for logins
+/// to unknown emails, so response times do not reveal which are registered.
+pub async fn verify_dummy(password: &str) {
+    static DUMMY_HASH: OnceLock<Result<String>> = OnceLock::new();
+    let password = password.to_string();
+    let _ = blocking(move || match DUMMY_HASH.get_or_init(|| hash(&token::generate())) {
+        Ok(dummy) => verify(&password, dummy),
+        Err(_) => Err(AppError::Internal),
+    })
+    .await;
+}
+
+fn verify(password: &str, hash: &str) -> Result<bool> {
+    if is_bcrypt(hash) {
+        return bcrypt::verify(password, hash).map_err(Into::into);
+    }
+    let parsed = PasswordHash::new(hash).map_err(|e| {
+        tracing::error!("Unreadable password hash: {e}");
+        AppError::Internal
+    })?;
+    Ok(Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok())
+}
+
+/// Whether a hash that just verified should be replaced by `hash_password`:
+/// bcrypt, another argon2 variant, or argon2id with outdated parameters.
+pub fn needs_rehash(hash: &str) -> bool {
+    if is_bcrypt(hash) {
+        return true;
+    }
+    let Ok(parsed) = PasswordHash::new(hash) else {
+        return true;
+    };
+    let current = argon2();
+    let current = current.params();
+    // Compared field by field: a parsed hash also records its output length
+    let outdated = |params: Params| {
+        params.m_cost() != current.m_cost() || params.t_cost() != current.t_cost() || params.p_cost() != current.p_cost()
+    };
+    parsed.algorithm != Algorithm::Argon2id.ident()
+        || parsed.version != Some(Version::V0x13.into())
+        || Params::try_from(&parsed).map_or(true, outdated)
+}
+
+/// Hashing takes tens of milliseconds of CPU, so it runs on the blocking
+/// pool rather than holding up every other request on an async worker.
+async fn blocking<T: Send + 'static>(work: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T> {
+    tokio::task::spawn_blocking(work).await.map_err(|e| {
+        tracing::error!("Password hashing task failed: {e}");
+        AppError::Internal
+    })?
+}
+
+fn is_bcrypt(hash: &str) -> bool {
+    hash.starts_with("$2a$") || hash.starts_with("$2b$") || hash.starts_with("$2y$")
+}
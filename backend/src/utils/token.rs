@@ -0,0 +1,16 @@
+// src/utils/token.rs
+use rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256};
+
+/// A random opaque token for handing to a client, 256 bits hex-encoded.
+pub fn generate() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// What gets stored in place of a token. The tokens are random, so a plain
+/// SHA-256 is enough; there is nothing to brute-force.
+pub fn hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
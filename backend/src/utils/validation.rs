@@ -0,0 +1,35 @@
+// src/utils/validation.rs
+//! Rules shared by the request DTOs, for use with `#[derive(Validate)]`.
+use std::borrow::Cow;
+
+use validator::ValidationError;
+
+/// Rejects empty and whitespace-only strings.
+pub fn not_blank(value: &str) -> Result<(), ValidationError> {
+    if value.trim().is_empty() {
+        return Err(ValidationError::new("blank").with_message(Cow::Borrowed("must not be empty")));
+    }
+    Ok(())
+}
+
+pub fn non_zero(value: i32) -> Result<(), ValidationError> {
+    if value == 0 {
+        return Err(ValidationError::new("zero").with_message(Cow::Borrowed("must not be zero")));
+    }
+    Ok(())
+}
+
+pub fn stock_policy(value: &str) -> Result<(), ValidationError> {
+    if !matches!(value, "forbid" | "warn" | "allow") {
+        return Err(ValidationError::new("stock_policy").with_message(Cow::Borrowed("must be forbid, warn or allow")));
+    }
+    Ok(())
+}
+
+/// An error from a struct-level check, reported against `field` rather than
+/// the request as a whole.
+pub fn field_error(field: &'static str, code: &'static str, message: &'static str) -> ValidationError {
+    let mut error = ValidationError::new(code).with_message(Cow::Borrowed(message));
+    error.add_param(Cow::Borrowed("field"), &field);
+    error
+}